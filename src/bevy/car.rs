@@ -15,6 +15,7 @@ impl CarWheel {
         let particle_mass = 1.0; //g_to_kg(10.0);
 
         // wheel hub - this is on mask layer zero which is a special no collisions layer
+        // todo: when ported to v5 use Particle::set_collision_mask(COLLISION_MASK_NONE) instead
         let hub_particle_handle = {
             let mask = 0x0;
             let particle_radius = cm_to_m(4.0);
@@ -71,7 +72,7 @@ impl CarWheel {
         // or add a flag to particles to say they are "invisible"?
 
          
-         /* todo: port to v4
+         /* todo: port the car to v5 and use v5::constraints::attachment_constraint::AttachmentConstraint
         // to optimise this we really only need maybe 4 points to determine the centre of the wheel for the incoming particles
         // we set all particles as output particles so the axle can be pushed by any sticks
         let mut weighted_particles = vec![];
@@ -16,9 +16,9 @@ use bevy::{
 use bytemuck::{Pod, Zeroable};
 use rand_pcg::Pcg64;
 
-use crate::{level::level::{setup_level, update_level}, random::Random, v4::{particle_sim::ParticleSim, particle_solvers::spatial_hash_particle_solver::SpatialHashParticleSolver}, v5::{particle::Particle, particle_system::ParticleSystem, shape_builder::{circle, rectangle, shape_builder::ShapeBuilder}}};
+use crate::{level::level::{setup_level, update_level}, random::Random, v4::{particle_sim::ParticleSim, particle_solvers::spatial_hash_particle_solver::SpatialHashParticleSolver}, v5::{particle::Particle, particle_system::ParticleSystem, shape_builder::{circle, packing::Packing, rectangle, shape_builder::ShapeBuilder}}};
 
-use super::{car::{self, Car}, instance_material_data::{InstanceData, InstanceMaterialData}, performance_ui::performance_ui_build};
+use super::{car::{self, Car}, instance_material_data::{InstanceData, InstanceMaterialData}, mouse_tool::{update_mouse_tool, MouseTool}, performance_ui::performance_ui_build};
 
 pub fn m_to_cm(m: f32) -> f32 {
     m * 100.0
@@ -63,6 +63,7 @@ pub struct CarScene {
     pub particle_sim: ParticleSim, // todo: remove this
     
     pub particle_system: ParticleSystem,
+    pub mouse_tool: MouseTool,
     //pub rng: Pcg64,
     paused: bool,
 }
@@ -165,7 +166,7 @@ impl CarScene {
                 let mut liquid = ShapeBuilder::new();
                 liquid
                     .set_particle_template(Particle::default().set_mass(20.0 * 0.001).set_radius(particle_radius).set_color(Color::from(LinearRgba::BLUE)).clone())
-                    .apply_operation(rectangle::Rectangle::from_center_size(vec2(0.0, 0.0), vec2(60.0, 60.0)))
+                    .apply_operation(rectangle::Rectangle::from_center_size(vec2(0.0, 0.0), vec2(60.0, 60.0)).with_packing(Packing::Hexagonal))
                     .create_in_particle_system(&mut particle_system);
 /* 
 
@@ -251,6 +252,7 @@ impl CarScene {
             car,
             particle_sim,
             particle_system,
+            mouse_tool: MouseTool::default(),
             //rng: Random::seed_from_beginning_of_week(),
             paused: true,
         }
@@ -282,7 +284,8 @@ impl CarScene {
 */
 
         // default to 100hz for teting for now
-        self.particle_system.update(delta_seconds);
+        let mouse_tool = &self.mouse_tool;
+        self.particle_system.update_with(delta_seconds, |particle_system, sub_dt| mouse_tool.apply_sub_step(particle_system, sub_dt));
     }
     
 }
@@ -304,6 +307,7 @@ impl Plugin for CarScenePlugin {
             .add_systems(Update, update_level)
             .add_systems(Update, update_particle_instances)
             .add_systems(Update, update_camera)
+            .add_systems(Update, update_mouse_tool)
             ;
 
         performance_ui_build(app);
@@ -3,4 +3,5 @@ pub mod instancing;
 pub mod main_bevy;
 pub mod car_scene;
 pub mod performance_ui;
-pub mod car;
\ No newline at end of file
+pub mod car;
+pub mod mouse_tool;
\ No newline at end of file
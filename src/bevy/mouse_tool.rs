@@ -0,0 +1,226 @@
+use bevy::{input::mouse::{MouseScrollUnit, MouseWheel}, prelude::*, window::PrimaryWindow};
+
+use crate::v5::{particle_handle::ParticleHandle, particle_manipulator::ParticleManipulator, particle_system::ParticleSystem};
+
+use super::car_scene::CarScene;
+
+/// Mouse interaction with the particle system, like the SDL cloth scene:
+/// Left mouse = pull the nearest particle with a spring
+/// Right mouse = cut constraints along the drag stroke
+/// Mouse wheel = change the tool radius
+pub struct MouseTool {
+    pub cursor_pos: Option<Vec2>, // world space. None when the cursor is outside the window
+    pub radius: f32,
+    pub min_radius: f32,
+    pub max_radius: f32,
+    pub radius_increment: f32, // per scroll wheel line
+
+    pub stiffness: f32, // spring stiffness per kg, so light and heavy particles respond the same
+    pub damping: f32, // spring damping per kg
+
+    pub grabbed_particle_handle: Option<ParticleHandle>,
+    pub cut_pos_prev: Option<Vec2>,
+}
+
+impl Default for MouseTool {
+    fn default() -> Self {
+        Self {
+            cursor_pos: None,
+            radius: 2.0,
+            min_radius: 0.5,
+            max_radius: 20.0,
+            radius_increment: 0.5,
+            stiffness: 500.0,
+            damping: 20.0,
+            grabbed_particle_handle: None,
+            cut_pos_prev: None,
+        }
+    }
+}
+
+impl MouseTool {
+    pub fn change_radius(&mut self, increment: f32) {
+        self.radius = (self.radius + increment).clamp(self.min_radius, self.max_radius);
+    }
+
+    /// Pick or release a particle and cut constraints. Called every frame with the latest input.
+    pub fn update(&mut self, particle_system: &mut ParticleSystem, buttons: &ButtonInput<MouseButton>) {
+        let manipulator = ParticleManipulator::new();
+
+        let Some(cursor_pos) = self.cursor_pos else {
+            self.grabbed_particle_handle = None;
+            self.cut_pos_prev = None;
+            return;
+        };
+
+        if buttons.just_pressed(MouseButton::Left) {
+            self.grabbed_particle_handle = manipulator.find_nearest_dynamic_particle(particle_system, cursor_pos, self.radius);
+        }
+        if !buttons.pressed(MouseButton::Left) {
+            self.grabbed_particle_handle = None;
+        }
+
+        if buttons.pressed(MouseButton::Right) {
+            if let Some(cut_pos_prev) = self.cut_pos_prev {
+                manipulator.cut_constraints_crossing_line_segment(particle_system, cut_pos_prev, cursor_pos);
+            }
+            self.cut_pos_prev = Some(cursor_pos);
+        } else {
+            self.cut_pos_prev = None;
+        }
+    }
+
+    /// Pull the grabbed particle towards the cursor. Called before every sub step (see ParticleSystem::update_with),
+    /// so the pull is the same however many sub steps each frame takes.
+    pub fn apply_sub_step(&self, particle_system: &mut ParticleSystem, delta_seconds: f32) {
+        let (Some(particle_handle), Some(cursor_pos)) = (self.grabbed_particle_handle, self.cursor_pos) else { return };
+        let particle_data = &mut particle_system.particle_data;
+        if particle_data.is_static(particle_handle) || !particle_data.is_enabled(particle_handle) {
+            return;
+        }
+
+        let pos = particle_data.get_pos_vec2(particle_handle);
+        let pos_prev = particle_data.get_pos_prev_vec2(particle_handle);
+        let velocity = (pos - pos_prev) / delta_seconds;
+        let delta_velocity = spring_delta_velocity(pos, velocity, cursor_pos, self.stiffness, self.damping, delta_seconds);
+
+        // the same impulse as ParticleManipulator::add_radial_impulse: moving pos_prev back changes the velocity
+        particle_data.set_pos_prev_from_vec2(particle_handle, &(pos_prev - delta_velocity * delta_seconds));
+    }
+}
+
+/// The change in velocity over delta_seconds from a damped spring pulling a particle at pos towards target.
+/// stiffness and damping are per kg, so the mass cancels out.
+pub fn spring_delta_velocity(pos: Vec2, velocity: Vec2, target: Vec2, stiffness: f32, damping: f32, delta_seconds: f32) -> Vec2 {
+    ((target - pos) * stiffness - velocity * damping) * delta_seconds
+}
+
+pub fn update_mouse_tool(
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut query_car_scenes: Query<&mut CarScene>,
+    mut gizmos: Gizmos,
+) {
+    let Ok(mut car_scene) = query_car_scenes.get_single_mut() else { return };
+    let car_scene = &mut *car_scene;
+    let mouse_tool = &mut car_scene.mouse_tool;
+
+    for event in mouse_wheel_events.read() {
+        // pixel units come from track pads, so scale them down to roughly a line each
+        let lines = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 20.0,
+        };
+        mouse_tool.change_radius(lines * mouse_tool.radius_increment);
+    }
+
+    // unproject the cursor through the orthographic camera onto the z = 0 plane the particles are in
+    mouse_tool.cursor_pos = match (window_query.get_single(), camera_query.get_single()) {
+        (Ok(window), Ok((camera, camera_transform))) => window.cursor_position()
+            .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor)),
+        _ => None,
+    };
+
+    mouse_tool.update(&mut car_scene.particle_system, &buttons);
+
+    if let Some(cursor_pos) = mouse_tool.cursor_pos {
+        let color = if mouse_tool.grabbed_particle_handle.is_some() { Color::srgb(0.0, 1.0, 0.0) } else { Color::WHITE };
+        gizmos.circle_2d(cursor_pos, mouse_tool.radius, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::simd::f32x2;
+
+    use bevy::math::vec2;
+
+    use crate::v5::{constraints::{constraint::Constraint, stick_constraint::StickConstraint}, particle::Particle};
+
+    use super::*;
+
+    fn particle_at(pos: Vec2) -> Particle {
+        *Particle::default().set_position(pos).set_radius(0.5).set_mass(2.0)
+    }
+
+    #[test]
+    fn pick_and_release() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = particle_system.add_particles(&vec![particle_at(vec2(0.0, 0.0)), particle_at(vec2(3.0, 0.0))]);
+
+        let mut mouse_tool = MouseTool { cursor_pos: Some(vec2(2.5, 0.0)), ..MouseTool::default() };
+        let mut buttons = ButtonInput::<MouseButton>::default();
+
+        // nothing within the radius
+        mouse_tool.radius = 0.1;
+        buttons.press(MouseButton::Left);
+        mouse_tool.update(&mut particle_system, &buttons);
+        assert_eq!(mouse_tool.grabbed_particle_handle, None);
+
+        mouse_tool.radius = 2.0;
+        buttons.release(MouseButton::Left);
+        buttons.clear();
+        buttons.press(MouseButton::Left);
+        mouse_tool.update(&mut particle_system, &buttons);
+        assert_eq!(mouse_tool.grabbed_particle_handle, Some(handles[1]));
+
+        // keeps hold while the button is down, even if the cursor moves away
+        buttons.clear();
+        mouse_tool.cursor_pos = Some(vec2(10.0, 0.0));
+        mouse_tool.update(&mut particle_system, &buttons);
+        assert_eq!(mouse_tool.grabbed_particle_handle, Some(handles[1]));
+
+        buttons.release(MouseButton::Left);
+        mouse_tool.update(&mut particle_system, &buttons);
+        assert_eq!(mouse_tool.grabbed_particle_handle, None);
+    }
+
+    #[test]
+    fn cut_along_drag() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = particle_system.add_particles(&vec![particle_at(vec2(0.0, 0.0)), particle_at(vec2(2.0, 0.0))]);
+        let constraint_handle = particle_system.add_constraint(StickConstraint::default().set_particle_handles([handles[0], handles[1]]).set_length(2.0).box_clone());
+
+        let mut mouse_tool = MouseTool { cursor_pos: Some(vec2(1.0, 1.0)), ..MouseTool::default() };
+        let mut buttons = ButtonInput::<MouseButton>::default();
+        buttons.press(MouseButton::Right);
+        mouse_tool.update(&mut particle_system, &buttons);
+        assert!(particle_system.constraint_container.constraints[constraint_handle.id()].is_enabled());
+
+        mouse_tool.cursor_pos = Some(vec2(1.0, -1.0));
+        mouse_tool.update(&mut particle_system, &buttons);
+        assert!(!particle_system.constraint_container.constraints[constraint_handle.id()].is_enabled());
+    }
+
+    #[test]
+    fn spring_delta_velocity_is_per_kg() {
+        let delta_velocity = spring_delta_velocity(vec2(0.0, 0.0), vec2(0.0, 1.0), vec2(2.0, 0.0), 10.0, 4.0, 0.5);
+        assert_eq!(delta_velocity, vec2(10.0, -2.0));
+    }
+
+    // the drag is applied per sub step, so it does not depend on how many sub steps each frame takes
+    #[test]
+    fn drag_is_independent_of_sub_steps_per_frame() {
+        let drag = |frame_count: usize, sub_steps_per_frame: f32| {
+            let mut particle_system = ParticleSystem::default();
+            particle_system.set_gravity(f32x2::splat(0.0));
+            let handles = particle_system.add_particles(&vec![particle_at(vec2(0.0, 0.0))]);
+
+            let mouse_tool = MouseTool { cursor_pos: Some(vec2(1.0, 0.0)), grabbed_particle_handle: Some(handles[0]), ..MouseTool::default() };
+            // the extra half sub step is dropped, it just avoids rounding down a whole sub step
+            let delta_seconds = (sub_steps_per_frame + 0.5) * particle_system.sub_step_seconds();
+            for _ in 0..frame_count {
+                particle_system.pre_update();
+                particle_system.update_with(delta_seconds, |particle_system, sub_dt| mouse_tool.apply_sub_step(particle_system, sub_dt));
+            }
+            particle_system.particle_data.get_pos_vec2(handles[0])
+        };
+
+        let pos_1 = drag(24, 1.0);
+        let pos_4 = drag(6, 4.0);
+        assert!(pos_1.x > 0.1 && pos_1.x < 1.5);
+        assert!(pos_1.distance(pos_4) < 1e-5, "{pos_1} != {pos_4}");
+    }
+}
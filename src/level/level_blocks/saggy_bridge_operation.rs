@@ -1,7 +1,7 @@
 use bevy::{color::Color, math::vec2, prelude::*, sprite::{MaterialMesh2dBundle, Mesh2dHandle}};
 use rand::Rng;
 
-use crate::{bevy::car_scene::cm_to_m, level::{level_builder::{LevelBuilder, LevelBuilderContext}, level_builder_operation::LevelBuilderOperation}, v4::{constraints::stick_constraint::StickConstraint, particle::Particle, shape_builder::{line_segment::LineSegment, rectangle, rectangle_stick_grid::RectangleStickGrid, shape_builder::ShapeBuilder}}};
+use crate::{bevy::car_scene::cm_to_m, level::{level_builder::{LevelBuilder, LevelBuilderContext}, level_builder_operation::LevelBuilderOperation}, v4::{constraints::stick_constraint::StickConstraint, particle::Particle, shape_builder::{line_segment::LineSegment, rectangle, rectangle_stick_grid::RectangleStickGrid, shape_builder::ShapeBuilder}}, v5::shape_builder::selection::AabbEdge};
 
 pub struct SaggyBridgeOperation {
 }
@@ -32,17 +32,14 @@ impl LevelBuilderOperation for SaggyBridgeOperation {
         sb.apply_operation(RectangleStickGrid::from_rectangle(StickConstraint::default().set_stiffness_factor(500.0).clone(), 
             rectangle));
         
-        // set left and right most particles and make them static
-        // todo: make this a shape operation?
+        // make the left and right most particles static. the grid positions are computed so allow for rounding errors
+        let edge_tolerance = level_builder_context.particle_template.radius * 0.1;
+        // this block still uses the v4 ShapeBuilder, so share the edge test from the v5 selection API
         let aabb = sb.get_aabb();
-        sb.particles.iter_mut().for_each(|particle| {
-            if particle.pos.x == aabb.min.x {
-                particle.set_static(true);
-            }
-            if particle.pos.x == aabb.max.x {
-                particle.set_static(true);
-            }
-        });
+        let edges = [AabbEdge::Left, AabbEdge::Right];
+        sb.particles.iter_mut()
+            .filter(|particle| edges.iter().any(|edge| edge.is_near(&aabb, particle.pos, edge_tolerance)))
+            .for_each(|particle| { particle.set_static(true); });
 
         sb.create_in_particle_sim(level_builder_context.particle_sim);
 
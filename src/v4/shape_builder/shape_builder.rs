@@ -172,7 +172,7 @@ impl ShapeBuilder {
 
         let mut s = ShapeBuilder::from_shape_builder_templates(&self);
 
-        let r = self.particles.extract_if(|particle| particle.pos.x == aabb.min.x).collect::<Vec<_>>();
+        let r = self.particles.extract_if(.., |particle| particle.pos.x == aabb.min.x).collect::<Vec<_>>();
         //s.add_particles(r); // todo: add a fn for this
         for particle in r {
             s.add_particle(particle);
@@ -0,0 +1,65 @@
+use super::particle_handle::{BodyHandle, ConstraintHandle, ParticleHandle};
+
+/// A group of particles and constraints that can be addressed as one, eg. a wheel or a jelly cube
+#[derive(Debug, Clone)]
+pub struct Body {
+    pub particle_handles: Vec<ParticleHandle>,
+    pub constraint_handles: Vec<ConstraintHandle>,
+    pub is_enabled: bool,
+    pub is_removed: bool,
+}
+
+impl Body {
+    pub fn new(particle_handles: Vec<ParticleHandle>, constraint_handles: Vec<ConstraintHandle>) -> Self {
+        Self {
+            particle_handles,
+            constraint_handles,
+            is_enabled: true,
+            is_removed: false,
+        }
+    }
+}
+
+/// The body id stored on each particle in a body. 0 is reserved to mean "not in a body"
+#[inline(always)]
+pub fn body_handle_to_body_id(body_handle: BodyHandle) -> u32 {
+    body_handle.id() as u32 + 1
+}
+
+/// The inverse of body_handle_to_body_id. None for particles that are not in a body
+#[inline(always)]
+pub fn body_id_to_body_handle(body_id: u32) -> Option<BodyHandle> {
+    if body_id == 0 { None } else { Some(BodyHandle::new(body_id as usize - 1)) }
+}
+
+pub struct BodyContainer {
+    pub bodies: Vec<Body>,
+}
+
+impl BodyContainer {
+    pub fn add(&mut self, body: Body) -> BodyHandle {
+        let id = self.bodies.len();
+        self.bodies.push(body);
+        BodyHandle::new(id)
+    }
+
+    pub fn get(&self, body_handle: BodyHandle) -> Option<&Body> {
+        self.bodies.get(body_handle.id())
+    }
+
+    pub fn get_mut(&mut self, body_handle: BodyHandle) -> Option<&mut Body> {
+        self.bodies.get_mut(body_handle.id())
+    }
+
+    pub fn len(&self) -> usize {
+        self.bodies.len()
+    }
+}
+
+impl Default for BodyContainer {
+    fn default() -> Self {
+        Self {
+            bodies: vec![],
+        }
+    }
+}
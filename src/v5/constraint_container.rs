@@ -0,0 +1,36 @@
+use super::{constraints::constraint::Constraint, particle_handle::ConstraintHandle};
+
+
+pub struct ConstraintContainer {
+    pub constraints: Vec<Box<dyn Constraint + Send + Sync>>,
+}
+
+impl ConstraintContainer {
+    pub fn add(&mut self, constraint: Box<dyn Constraint + Send + Sync>) -> ConstraintHandle {
+        let id = self.constraints.len();
+        self.constraints.push(constraint);
+        ConstraintHandle::new(id) 
+    }
+
+    /// Get the constraint that the constraint_handle refers to, if it is of type T
+    pub fn get<T: Constraint + 'static>(&self, constraint_handle: ConstraintHandle) -> Option<&T> {
+        self.constraints.get(constraint_handle.id())?.as_any().downcast_ref::<T>()
+    }
+
+    /// Get the constraint that the constraint_handle refers to, if it is of type T
+    pub fn get_mut<T: Constraint + 'static>(&mut self, constraint_handle: ConstraintHandle) -> Option<&mut T> {
+        self.constraints.get_mut(constraint_handle.id())?.as_any_mut().downcast_mut::<T>()
+    }
+
+    pub fn len(&self) -> usize {
+        self.constraints.len()
+    }
+}
+
+impl Default for ConstraintContainer {
+    fn default() -> Self {
+        Self {
+            constraints: vec![],
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use super::{constraint_container::ConstraintContainer, particle_data::ParticleData};
+
+/// The order constraints see each others position corrections in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintSolverOrdering {
+    /// Each constraint moves particles immediately, so later constraints see the changes.
+    /// Converges faster, but the result depends on the constraint order.
+    GaussSeidel,
+
+    /// Every constraint sees the same positions and corrections are averaged per particle at the end
+    /// of each iteration. Converges slower, but is order independent and the constraints could be solved in parallel.
+    Jacobi,
+}
+
+pub struct ConstraintSolver {
+    pub iterations: usize, // solver iterations per sub step
+    pub ordering: ConstraintSolverOrdering,
+    pub jacobi_relaxation: f32, // scales the averaged Jacobi corrections. 1 = no over/under relaxation
+}
+
+impl Default for ConstraintSolver {
+    fn default() -> Self {
+        Self {
+            iterations: 1,
+            ordering: ConstraintSolverOrdering::GaussSeidel,
+            jacobi_relaxation: 1.0,
+        }
+    }
+}
+
+impl ConstraintSolver {
+
+    pub fn set_iterations(&mut self, iterations: usize) -> &mut Self {
+        debug_assert!(iterations > 0);
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn set_ordering(&mut self, ordering: ConstraintSolverOrdering) -> &mut Self {
+        self.ordering = ordering;
+        self
+    }
+
+    pub fn set_jacobi_relaxation(&mut self, jacobi_relaxation: f32) -> &mut Self {
+        debug_assert!(jacobi_relaxation > 0.0 && jacobi_relaxation < 2.0);
+        self.jacobi_relaxation = jacobi_relaxation;
+        self
+    }
+
+    pub fn update_constraints(&mut self, constraint_container: &mut ConstraintContainer, particle_data: &mut ParticleData, delta_seconds: f32) {
+        for constraint in constraint_container.constraints.iter_mut() {
+            constraint.begin_step(delta_seconds);
+        }
+
+        for _ in 0..self.iterations {
+            match self.ordering {
+                ConstraintSolverOrdering::GaussSeidel => {
+                    for constraint in constraint_container.constraints.iter_mut() {
+                        constraint.update_constraint(particle_data, delta_seconds);
+                    }
+                },
+                ConstraintSolverOrdering::Jacobi => {
+                    particle_data.begin_deferred_position_changes();
+                    for constraint in constraint_container.constraints.iter_mut() {
+                        constraint.update_constraint(particle_data, delta_seconds);
+                    }
+                    particle_data.apply_deferred_position_changes(self.jacobi_relaxation);
+                },
+            }
+        }
+    }
+
+    pub fn post_update_constraints(&mut self, constraint_container: &mut ConstraintContainer, particle_data: &mut ParticleData, delta_seconds: f32) {
+        for constraint in constraint_container.constraints.iter_mut() {
+            constraint.post_update_constraint(particle_data, delta_seconds);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::{constraints::{constraint::Constraint, stick_constraint::StickConstraint}, particle::Particle};
+
+    use super::*;
+
+    fn add_stretched_chain(particle_data: &mut ParticleData, constraint_container: &mut ConstraintContainer) {
+        let handles = particle_data.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)).set_static(true),
+            *Particle::default().set_position(vec2(2.0, 0.0)),
+            *Particle::default().set_position(vec2(4.0, 0.0)),
+        ]);
+
+        for i in 0..2 {
+            constraint_container.add(StickConstraint::default().set_particle_handles([handles[i], handles[i + 1]]).set_length(1.0).box_clone());
+        }
+    }
+
+    fn solve_error(ordering: ConstraintSolverOrdering, iterations: usize) -> f32 {
+        let mut particle_data = ParticleData::default();
+        let mut constraint_container = ConstraintContainer::default();
+        add_stretched_chain(&mut particle_data, &mut constraint_container);
+
+        let mut solver = ConstraintSolver::default();
+        solver.set_ordering(ordering).set_iterations(iterations);
+        solver.update_constraints(&mut constraint_container, &mut particle_data, 0.01);
+
+        // the end of the chain should end up at x = 2
+        (particle_data.dynamic_particles.get_pos_vec2(1).x - 2.0).abs()
+    }
+
+    #[test]
+    fn more_iterations_converge() {
+        assert!(solve_error(ConstraintSolverOrdering::GaussSeidel, 20) < solve_error(ConstraintSolverOrdering::GaussSeidel, 1));
+        assert!(solve_error(ConstraintSolverOrdering::GaussSeidel, 20) < 0.001);
+        assert!(solve_error(ConstraintSolverOrdering::Jacobi, 50) < 0.001);
+    }
+}
@@ -0,0 +1,307 @@
+use std::any::Any;
+use std::f32::consts::PI;
+
+use bevy::math::{Mat2, Vec2};
+
+use crate::v5::{particle_data::ParticleData, particle_handle::ParticleHandle};
+
+use super::constraint::Constraint;
+
+/// Wrap an angle in radians into the range (-PI, PI]
+pub fn wrap_angle(angle: f32) -> f32 {
+    let wrapped = (angle + PI).rem_euclid(2.0 * PI) - PI;
+    if wrapped <= -PI { wrapped + 2.0 * PI } else { wrapped }
+}
+
+/// Compute the turning (bend) angle at b for the chain a -> b -> c.
+/// 0 = straight, positive = turning counter clockwise.
+pub fn compute_bend_angle(pos_a: Vec2, pos_b: Vec2, pos_c: Vec2) -> f32 {
+    let ab = pos_b - pos_a;
+    let bc = pos_c - pos_b;
+    f32::atan2(ab.perp_dot(bc), ab.dot(bc))
+}
+
+/// Constraint between 3 particles, a -> b -> c, that resists bending at the middle particle b.
+/// Angles are the turning angle at b in radians, where 0 means the 3 particles are in a straight line.
+///
+/// The ends a and c are rotated around b to correct the angle, so b acts as a hinge.
+/// min_angle and max_angle are hard limits applied on top of the compliant rest angle, eg. for knees and elbows.
+#[derive(Debug, Copy, Clone)]
+pub struct AngleConstraint {
+    pub particle_handles: [ParticleHandle; 3],
+    pub rest_angle: f32,
+    pub compliance: f32, // 0 = fully stiff, > 0 = angular spring with stiffness 1 / compliance, f32::INFINITY = only the limits apply
+    pub lambda: f32, // accumulated lagrange multiplier for the current sub step
+    pub min_angle: f32, // hard limits. The angle is always kept within these regardless of compliance
+    pub max_angle: f32,
+    pub is_enabled: bool
+}
+
+impl AngleConstraint {
+    pub fn set_particle_handles(&mut self, particle_handles: [ParticleHandle; 3]) -> &mut Self {
+        self.particle_handles = particle_handles;
+        self
+    }
+
+    pub fn set_rest_angle(&mut self, rest_angle: f32) -> &mut Self {
+        self.rest_angle = rest_angle;
+        self
+    }
+
+    pub fn set_compliance(&mut self, compliance: f32) -> &mut Self {
+        debug_assert!(compliance >= 0.0);
+        self.compliance = compliance;
+        self
+    }
+
+    pub fn set_limits(&mut self, min_angle: f32, max_angle: f32) -> &mut Self {
+        debug_assert!(min_angle <= max_angle);
+        self.min_angle = min_angle;
+        self.max_angle = max_angle;
+        self
+    }
+}
+
+impl Constraint for AngleConstraint {
+    fn box_clone(&self) -> Box<dyn Constraint + Send + Sync> {
+        Box::new((*self).clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_enabled = is_enabled;
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+
+    fn particle_links(&self) -> Vec<[ParticleHandle; 2]> {
+        let [handle_a, handle_b, handle_c] = self.particle_handles;
+        vec![[handle_a, handle_b], [handle_b, handle_c]]
+    }
+
+    fn transform(&mut self, matrix: Mat2, _positions: &Vec<Vec2>) {
+        // mirroring turns counter clockwise bends into clockwise ones
+        if matrix.determinant() < 0.0 {
+            self.rest_angle = -self.rest_angle;
+            (self.min_angle, self.max_angle) = (-self.max_angle, -self.min_angle);
+        }
+    }
+
+    fn offset_particle_handles(&mut self, offset: u64) {
+        for particle_handle in self.particle_handles.iter_mut() {
+            particle_handle.offset(offset);
+        }
+    }
+
+    fn remap_particle_handles(&mut self, remap: &Vec<Option<ParticleHandle>>) -> bool {
+        self.particle_handles.iter_mut().all(|particle_handle| particle_handle.remap(remap))
+    }
+
+    fn begin_step(&mut self, _delta_seconds: f32) {
+        self.lambda = 0.0;
+    }
+
+    fn update_constraint(&mut self, particle_data: &mut ParticleData, delta_seconds: f32) {
+        if !self.is_enabled {
+            return;
+        }
+
+        let [handle_a, handle_b, handle_c] = self.particle_handles;
+        if !particle_data.is_enabled(handle_a) || !particle_data.is_enabled(handle_b) || !particle_data.is_enabled(handle_c) {
+            return;
+        }
+
+        let pos_a = particle_data.get_pos_vec2(handle_a);
+        let pos_b = particle_data.get_pos_vec2(handle_b);
+        let pos_c = particle_data.get_pos_vec2(handle_c);
+
+        if (pos_b - pos_a).length_squared() <= f32::EPSILON || (pos_c - pos_b).length_squared() <= f32::EPSILON {
+            return;
+        }
+
+        let angle = compute_bend_angle(pos_a, pos_b, pos_c);
+
+        // XPBD: https://matthias-research.github.io/pages/publications/XPBD.pdf
+        // w is the generalised inverse mass, from the gradient of the angle with respect to each particle.
+        // The correction is applied below by rotating the ends around b, so large errors are corrected exactly
+        let mut target_angle = angle;
+        if self.compliance.is_finite() {
+            let ab = pos_b - pos_a;
+            let bc = pos_c - pos_b;
+            let gradient_a = ab.perp() / ab.length_squared();
+            let gradient_c = bc.perp() / bc.length_squared();
+            let gradient_b = -(gradient_a + gradient_c);
+            let w = particle_data.get_inverse_mass(handle_a) * gradient_a.length_squared()
+                + particle_data.get_inverse_mass(handle_b) * gradient_b.length_squared()
+                + particle_data.get_inverse_mass(handle_c) * gradient_c.length_squared();
+
+            if w > f32::EPSILON {
+                let error = wrap_angle(angle - self.rest_angle);
+                let alpha = self.compliance / (delta_seconds * delta_seconds);
+                let delta_lambda = (-error - alpha * self.lambda) / (w + alpha);
+                self.lambda += delta_lambda;
+                target_angle += delta_lambda * w;
+            }
+        }
+
+        let delta_angle = target_angle.clamp(self.min_angle, self.max_angle) - angle;
+        if delta_angle.abs() <= f32::EPSILON {
+            return;
+        }
+
+        // rotating c around b increases the bend angle, rotating a around b decreases it.
+        // static particles do not move, so the other end takes up all the rotation
+        let a_is_static = particle_data.is_static(handle_a);
+        let b_is_static = particle_data.is_static(handle_b);
+        let c_is_static = particle_data.is_static(handle_c);
+
+        let (a_rotation_weight, c_rotation_weight) = match (a_is_static, c_is_static) {
+            (true, true) => return,
+            (true, false) => (0.0, 1.0),
+            (false, true) => (1.0, 0.0),
+            (false, false) => (0.5, 0.5),
+        };
+
+        let new_pos_a = pos_b + Vec2::from_angle(-delta_angle * a_rotation_weight).rotate(pos_a - pos_b);
+        let new_pos_c = pos_b + Vec2::from_angle(delta_angle * c_rotation_weight).rotate(pos_c - pos_b);
+
+        // when nothing is static, shift all 3 particles equally so the centre of the 3 particles does not move.
+        // a uniform shift does not change the angle
+        let shift = if a_is_static || b_is_static || c_is_static {
+            Vec2::new(0.0, 0.0)
+        } else {
+            -((new_pos_a - pos_a) + (new_pos_c - pos_c)) / 3.0
+        };
+
+        if !a_is_static {
+            let pos = new_pos_a + shift;
+            debug_assert!(!pos.x.is_nan());
+            debug_assert!(!pos.y.is_nan());
+            particle_data.set_pos_from_vec2(handle_a, &pos);
+        }
+
+        if !b_is_static {
+            let pos = pos_b + shift;
+            particle_data.set_pos_from_vec2(handle_b, &pos);
+        }
+
+        if !c_is_static {
+            let pos = new_pos_c + shift;
+            debug_assert!(!pos.x.is_nan());
+            debug_assert!(!pos.y.is_nan());
+            particle_data.set_pos_from_vec2(handle_c, &pos);
+        }
+    }
+}
+
+impl Default for AngleConstraint {
+    fn default() -> Self {
+        Self {
+            particle_handles: [ParticleHandle::default(); 3],
+            rest_angle: 0.0,
+            compliance: 0.0,
+            lambda: 0.0,
+            min_angle: -PI,
+            max_angle: PI,
+            is_enabled: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use bevy::math::vec2;
+
+    use crate::v5::particle::Particle;
+
+    use super::*;
+
+    fn add_right_angle(particle_data: &mut ParticleData) -> [ParticleHandle; 3] {
+        let handles = particle_data.add_particles(&vec![
+            *Particle::default().set_position(vec2(-1.0, 0.0)),
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+            *Particle::default().set_position(vec2(0.0, 1.0)),
+        ]);
+        [handles[0], handles[1], handles[2]]
+    }
+
+    #[test]
+    fn bend_angle() {
+        assert_eq!(compute_bend_angle(vec2(-1.0, 0.0), vec2(0.0, 0.0), vec2(1.0, 0.0)), 0.0);
+        assert!((compute_bend_angle(vec2(-1.0, 0.0), vec2(0.0, 0.0), vec2(0.0, 1.0)) - FRAC_PI_2).abs() < 0.0001);
+        assert!((compute_bend_angle(vec2(-1.0, 0.0), vec2(0.0, 0.0), vec2(0.0, -1.0)) + FRAC_PI_2).abs() < 0.0001);
+    }
+
+    #[test]
+    fn straightens_to_rest_angle() {
+        let mut particle_data = ParticleData::default();
+        let handles = add_right_angle(&mut particle_data);
+
+        let mut constraint = AngleConstraint::default();
+        constraint.set_particle_handles(handles).set_rest_angle(0.0).set_compliance(0.0);
+        constraint.update_constraint(&mut particle_data, 0.01);
+
+        let angle = compute_bend_angle(particle_data.get_pos_vec2(handles[0]), particle_data.get_pos_vec2(handles[1]), particle_data.get_pos_vec2(handles[2]));
+        assert!(angle.abs() < 0.0001);
+    }
+
+    #[test]
+    fn compliant_angle_is_partially_corrected() {
+        let mut particle_data = ParticleData::default();
+        let handles = add_right_angle(&mut particle_data);
+
+        // alpha = 0.001 / 0.01^2 = 10 and w = 4, so 4 / 14 of the error is corrected
+        let mut constraint = AngleConstraint::default();
+        constraint.set_particle_handles(handles).set_rest_angle(0.0).set_compliance(0.001);
+        constraint.begin_step(0.01);
+        constraint.update_constraint(&mut particle_data, 0.01);
+
+        let angle = compute_bend_angle(particle_data.get_pos_vec2(handles[0]), particle_data.get_pos_vec2(handles[1]), particle_data.get_pos_vec2(handles[2]));
+        assert!((angle - FRAC_PI_2 * 10.0 / 14.0).abs() < 0.0001);
+        assert!(constraint.lambda < 0.0);
+
+        constraint.begin_step(0.01);
+        assert_eq!(constraint.lambda, 0.0);
+    }
+
+    #[test]
+    fn limits_are_enforced_with_infinite_compliance() {
+        let mut particle_data = ParticleData::default();
+        let handles = add_right_angle(&mut particle_data);
+
+        let mut constraint = AngleConstraint::default();
+        constraint.set_particle_handles(handles).set_compliance(f32::INFINITY).set_limits(-0.5, 0.5);
+        constraint.update_constraint(&mut particle_data, 0.01);
+
+        let angle = compute_bend_angle(particle_data.get_pos_vec2(handles[0]), particle_data.get_pos_vec2(handles[1]), particle_data.get_pos_vec2(handles[2]));
+        assert!((angle - 0.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn static_end_does_not_move() {
+        let mut particle_data = ParticleData::default();
+        let handles = particle_data.add_particles(&vec![
+            *Particle::default().set_position(vec2(-1.0, 0.0)).set_static(true),
+            *Particle::default().set_position(vec2(0.0, 0.0)).set_static(true),
+            *Particle::default().set_position(vec2(0.0, 1.0)),
+        ]);
+
+        let mut constraint = AngleConstraint::default();
+        constraint.set_particle_handles([handles[0], handles[1], handles[2]]);
+        constraint.update_constraint(&mut particle_data, 0.01);
+
+        assert_eq!(particle_data.get_pos_vec2(handles[0]), vec2(-1.0, 0.0));
+        assert!((particle_data.get_pos_vec2(handles[2]) - vec2(1.0, 0.0)).length() < 0.0001);
+    }
+}
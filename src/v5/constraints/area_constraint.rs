@@ -0,0 +1,243 @@
+use std::any::Any;
+
+use bevy::math::{Mat2, Vec2};
+
+use crate::v5::{particle_data::ParticleData, particle_handle::ParticleHandle};
+
+use super::constraint::Constraint;
+
+/// Compute the signed area of a closed loop of points using the shoelace formula.
+/// Positive = counter clockwise winding, negative = clockwise winding.
+pub fn compute_signed_area(positions: &Vec<Vec2>) -> f32 {
+    let count = positions.len();
+    if count < 3 {
+        return 0.0;
+    }
+
+    let mut area = 0.0;
+    for i in 0..count {
+        let pos = positions[i];
+        let pos_next = positions[(i + 1) % count];
+        area += pos.perp_dot(pos_next);
+    }
+    area * 0.5
+}
+
+/// Constraint over an ordered closed loop of particles (eg. a Circle with AdjacentSticks)
+/// that tries to keep the area enclosed by the loop at rest_area * pressure.
+/// This lets a ring act like an inflated tyre or balloon instead of collapsing.
+///
+/// Each particle is pushed out along the normal of the loop at that particle.
+/// Pressure can be changed at runtime to inflate (> 1) or deflate (< 1) the shape.
+#[derive(Debug, Clone)]
+pub struct AreaConstraint {
+    pub particle_handles: Vec<ParticleHandle>, // ordered around the loop
+    pub rest_area: f32, // signed, so the winding order of the loop does not matter
+    pub pressure: f32, // 1 = maintain rest area, 2 = try to double the rest area etc.
+    pub compliance: f32, // 0 = fully stiff, > 0 = the area behaves like a spring with stiffness 1 / compliance
+    pub lambda: f32, // accumulated lagrange multiplier for the current sub step
+    pub is_enabled: bool
+}
+
+impl AreaConstraint {
+    pub fn set_compliance(&mut self, compliance: f32) -> &mut Self {
+        debug_assert!(compliance >= 0.0);
+        self.compliance = compliance;
+        self
+    }
+
+    pub fn set_pressure(&mut self, pressure: f32) -> &mut Self {
+        debug_assert!(pressure >= 0.0);
+        self.pressure = pressure;
+        self
+    }
+
+    /// Set the particles in the loop and compute the rest area from their positions
+    pub fn set_particles(&mut self, particle_handles: Vec<ParticleHandle>, positions: &Vec<Vec2>) -> &mut Self {
+        debug_assert!(particle_handles.len() == positions.len());
+        self.particle_handles = particle_handles;
+        self.rest_area = compute_signed_area(positions);
+        self
+    }
+
+    pub fn compute_target_area(&self) -> f32 {
+        self.rest_area * self.pressure
+    }
+
+    pub fn compute_current_area(&self, particle_data: &ParticleData) -> f32 {
+        let positions = self.particle_handles.iter().map(|handle| particle_data.get_pos_vec2(*handle)).collect::<Vec<Vec2>>();
+        compute_signed_area(&positions)
+    }
+}
+
+impl Constraint for AreaConstraint {
+    fn box_clone(&self) -> Box<dyn Constraint + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_enabled = is_enabled;
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+
+    fn particle_links(&self) -> Vec<[ParticleHandle; 2]> {
+        let count = self.particle_handles.len();
+        (0..count).map(|i| [self.particle_handles[i], self.particle_handles[(i + 1) % count]]).collect()
+    }
+
+    fn transform(&mut self, matrix: Mat2, _positions: &Vec<Vec2>) {
+        // the determinant is negative for a mirror, which also flips the sign of the area
+        self.rest_area *= matrix.determinant();
+    }
+
+    fn offset_particle_handles(&mut self, offset: u64) {
+        for particle_handle in self.particle_handles.iter_mut() {
+            particle_handle.offset(offset);
+        }
+    }
+
+    fn remap_particle_handles(&mut self, remap: &Vec<Option<ParticleHandle>>) -> bool {
+        self.particle_handles.iter_mut().all(|particle_handle| particle_handle.remap(remap))
+    }
+
+    fn begin_step(&mut self, _delta_seconds: f32) {
+        self.lambda = 0.0;
+    }
+
+    fn update_constraint(&mut self, particle_data: &mut ParticleData, delta_seconds: f32) {
+        let count = self.particle_handles.len();
+        if !self.is_enabled || count < 3 {
+            return;
+        }
+
+        if self.particle_handles.iter().any(|handle| !particle_data.is_enabled(*handle)) {
+            return;
+        }
+
+        let positions = self.particle_handles.iter().map(|handle| particle_data.get_pos_vec2(*handle)).collect::<Vec<Vec2>>();
+        let area_error = compute_signed_area(&positions) - self.compute_target_area();
+        if area_error.abs() <= f32::EPSILON {
+            return;
+        }
+
+        // the gradient of the area with respect to each particle is perpendicular to the line
+        // joining its 2 neighbours. Moving along it grows or shrinks the area the fastest
+        let mut gradients = Vec::with_capacity(count);
+        let mut inverse_masses = Vec::with_capacity(count);
+        let mut weighted_gradient_sum = 0.0;
+        for i in 0..count {
+            let pos_prev = positions[(i + count - 1) % count];
+            let pos_next = positions[(i + 1) % count];
+            let gradient = (pos_next - pos_prev).perp() * -0.5;
+            let inverse_mass = particle_data.get_inverse_mass(self.particle_handles[i]);
+            weighted_gradient_sum += gradient.length_squared() * inverse_mass;
+            gradients.push(gradient);
+            inverse_masses.push(inverse_mass);
+        }
+
+        if weighted_gradient_sum <= f32::EPSILON {
+            return;
+        }
+
+        let alpha = self.compliance / (delta_seconds * delta_seconds);
+        let delta_lambda = (-area_error - alpha * self.lambda) / (weighted_gradient_sum + alpha);
+        self.lambda += delta_lambda;
+
+        for i in 0..count {
+            if inverse_masses[i] <= 0.0 {
+                continue;
+            }
+
+            let new_pos = positions[i] + gradients[i] * (delta_lambda * inverse_masses[i]);
+            debug_assert!(!new_pos.x.is_nan());
+            debug_assert!(!new_pos.y.is_nan());
+            particle_data.set_pos_from_vec2(self.particle_handles[i], &new_pos);
+        }
+    }
+}
+
+impl Default for AreaConstraint {
+    fn default() -> Self {
+        Self {
+            particle_handles: vec![],
+            rest_area: 0.0,
+            pressure: 1.0,
+            compliance: 0.0,
+            lambda: 0.0,
+            is_enabled: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::particle::Particle;
+
+    use super::*;
+
+    fn square() -> Vec<Vec2> {
+        vec![vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(1.0, 1.0), vec2(-1.0, 1.0)]
+    }
+
+    fn add_loop(particle_data: &mut ParticleData, positions: &Vec<Vec2>) -> AreaConstraint {
+        let particles = positions.iter().map(|pos| *Particle::default().set_position(*pos)).collect::<Vec<Particle>>();
+        let handles = particle_data.add_particles(&particles);
+
+        let mut constraint = AreaConstraint::default();
+        constraint.set_particles(handles, positions);
+        constraint
+    }
+
+    #[test]
+    fn signed_area() {
+        assert_eq!(compute_signed_area(&square()), 4.0);
+        assert_eq!(compute_signed_area(&square().into_iter().rev().collect()), -4.0);
+    }
+
+    #[test]
+    fn squashed_loop_is_restored() {
+        let mut particle_data = ParticleData::default();
+        let mut constraint = add_loop(&mut particle_data, &square());
+
+        for (handle, pos) in constraint.particle_handles.clone().iter().zip(square().iter()) {
+            particle_data.set_pos_from_vec2(*handle, &(*pos * vec2(1.0, 0.5)));
+        }
+
+        for _ in 0..20 {
+            constraint.update_constraint(&mut particle_data, 0.01);
+        }
+        assert!((constraint.compute_current_area(&particle_data) - 4.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn pressure_inflates_and_deflates() {
+        let mut particle_data = ParticleData::default();
+        let clockwise = square().into_iter().rev().collect::<Vec<Vec2>>();
+        let mut constraint = add_loop(&mut particle_data, &clockwise);
+
+        constraint.set_pressure(2.0);
+        for _ in 0..20 {
+            constraint.update_constraint(&mut particle_data, 0.01);
+        }
+        assert!((constraint.compute_current_area(&particle_data) + 8.0).abs() < 0.001);
+
+        constraint.set_pressure(0.5);
+        for _ in 0..20 {
+            constraint.update_constraint(&mut particle_data, 0.01);
+        }
+        assert!((constraint.compute_current_area(&particle_data) + 2.0).abs() < 0.001);
+    }
+}
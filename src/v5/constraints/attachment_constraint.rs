@@ -0,0 +1,240 @@
+use std::any::Any;
+
+use bevy::math::Vec2;
+
+use crate::v5::{particle_data::ParticleData, particle_handle::ParticleHandle};
+
+use super::constraint::Constraint;
+
+#[derive(Debug, Copy, Clone)]
+pub struct WeightedParticle {
+    pub particle_handle: ParticleHandle,
+    pub weight: f32,
+}
+
+impl WeightedParticle {
+    pub fn new(particle_handle: ParticleHandle, weight: f32) -> Self {
+        Self {
+            particle_handle,
+            weight
+        }
+    }
+}
+
+// An Attachment has a set of weighted particles
+// such that when updated, computes a virtual position (the weighted centroid)
+// and moves the target particle there. This set is called incoming_weighted_particles.
+//
+// An Attachment also has a set of weighted particles to 'push' when the target particle itself is moved.
+// This set is called outgoing_weighted_particles.
+//
+// This lets something like a wheel hub follow the centre of the tyre without needing a stick between the hub and each tyre particle.
+#[derive(Debug, Clone)]
+pub struct AttachmentConstraint {
+    pub incoming_weighted_particles: Vec<WeightedParticle>, // source particles
+    pub outgoing_weighted_particles: Vec<WeightedParticle>, // particles pushed by the target particle
+    pub target_particle_handle: ParticleHandle, // target output particle
+    pub velocity_prev: Vec2,
+    pub is_enabled: bool
+}
+
+impl AttachmentConstraint {
+    pub fn new(incoming_weighted_particles: Vec<WeightedParticle>, outgoing_weighted_particles: Vec<WeightedParticle>, target_particle_handle: ParticleHandle) -> Self {
+        Self {
+            incoming_weighted_particles,
+            outgoing_weighted_particles,
+            target_particle_handle,
+            ..Self::default()
+        }
+    }
+
+    pub fn set_target_particle_handle(&mut self, target_particle_handle: ParticleHandle) -> &mut Self {
+        self.target_particle_handle = target_particle_handle;
+        self
+    }
+
+    pub fn add_incoming_particle(&mut self, particle_handle: ParticleHandle, weight: f32) -> &mut Self {
+        self.incoming_weighted_particles.push(WeightedParticle::new(particle_handle, weight));
+        self
+    }
+
+    pub fn add_outgoing_particle(&mut self, particle_handle: ParticleHandle, weight: f32) -> &mut Self {
+        self.outgoing_weighted_particles.push(WeightedParticle::new(particle_handle, weight));
+        self
+    }
+
+    pub fn add_outgoing_particles(&mut self, particle_handles: &Vec<ParticleHandle>, weight: f32) -> &mut Self {
+        for particle_handle in particle_handles.iter() {
+            self.add_outgoing_particle(*particle_handle, weight);
+        }
+        self
+    }
+
+    // to optimise this we really only need maybe 4 points to determine the centre of a wheel
+    pub fn add_even_distribution_of_incoming_particles(&mut self, particle_handles: &Vec<ParticleHandle>, weight: f32, num_particles_to_add: usize) -> &mut Self {
+        // todo: what if we cant get an even distribution?
+        let stride = usize::max(particle_handles.len() / num_particles_to_add, 1);
+        for particle_handle in particle_handles.iter().step_by(stride).take(num_particles_to_add) {
+            self.add_incoming_particle(*particle_handle, weight);
+        }
+        self
+    }
+
+    /// Compute the weighted centroid of the incoming particles
+    pub fn compute_weighted_centroid(&self, particle_data: &ParticleData) -> Option<Vec2> {
+        let mut pos = Vec2::new(0.0, 0.0);
+        let mut total_weight = 0.0;
+        for weighted_particle in self.incoming_weighted_particles.iter() {
+            pos += particle_data.get_pos_vec2(weighted_particle.particle_handle) * weighted_particle.weight;
+            total_weight += weighted_particle.weight;
+        }
+
+        if total_weight <= 0.0 {
+            return None;
+        }
+
+        Some(pos / total_weight)
+    }
+}
+
+impl Constraint for AttachmentConstraint {
+    fn box_clone(&self) -> Box<dyn Constraint + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_enabled = is_enabled;
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+
+    fn offset_particle_handles(&mut self, offset: u64) {
+        for weighted_particle in self.incoming_weighted_particles.iter_mut() {
+            weighted_particle.particle_handle.offset(offset);
+        }
+        for weighted_particle in self.outgoing_weighted_particles.iter_mut() {
+            weighted_particle.particle_handle.offset(offset);
+        }
+        self.target_particle_handle.offset(offset);
+    }
+
+    fn remap_particle_handles(&mut self, remap: &Vec<Option<ParticleHandle>>) -> bool {
+        self.incoming_weighted_particles.iter_mut().all(|weighted_particle| weighted_particle.particle_handle.remap(remap))
+            && self.outgoing_weighted_particles.iter_mut().all(|weighted_particle| weighted_particle.particle_handle.remap(remap))
+            && self.target_particle_handle.remap(remap)
+    }
+
+    fn update_constraint(&mut self, particle_data: &mut ParticleData, _delta_seconds: f32) {
+        if !self.is_enabled || !particle_data.is_enabled(self.target_particle_handle) {
+            return;
+        }
+
+        let Some(pos) = self.compute_weighted_centroid(particle_data) else { return };
+        particle_data.set_pos_from_vec2(self.target_particle_handle, &pos);
+
+        // store the velocity of the target particle
+        self.velocity_prev = pos - particle_data.get_pos_prev_vec2(self.target_particle_handle);
+    }
+
+    fn post_update_constraint(&mut self, particle_data: &mut ParticleData, _delta_seconds: f32) {
+        if !self.is_enabled || !particle_data.is_enabled(self.target_particle_handle) {
+            return;
+        }
+
+        // the incoming particles (I) push the target particle (T) by X
+        // so T has a given velocity.
+        // now at the end of the sub step, we see if there is any change in the velocity (i.e. acceleration)
+        // and only apply the acceleration to the outgoing particles.
+        //
+        // if we just apply the velocity to the outgoing particles (O) then we end up in a compounding
+        // situation (at least for the case where incoming particles = outgoing particles)
+        // and they zoom off to infinity as
+        // I push T, T push O, but I = O, so its a circular loop!
+        // Only applying acceleration fixes this.
+        let current_velocity = particle_data.get_pos_vec2(self.target_particle_handle) - particle_data.get_pos_prev_vec2(self.target_particle_handle);
+        let delta_velocity = current_velocity - self.velocity_prev;
+
+        // push any outgoing particles based on their weight
+        for weighted_particle in self.outgoing_weighted_particles.iter() {
+            if particle_data.is_static(weighted_particle.particle_handle) || !particle_data.is_enabled(weighted_particle.particle_handle) {
+                continue;
+            }
+
+            let pos = particle_data.get_pos_vec2(weighted_particle.particle_handle) + delta_velocity * weighted_particle.weight;
+            debug_assert!(!pos.x.is_nan());
+            debug_assert!(!pos.y.is_nan());
+            particle_data.set_pos_from_vec2(weighted_particle.particle_handle, &pos);
+        }
+    }
+}
+
+impl Default for AttachmentConstraint {
+    fn default() -> Self {
+        Self {
+            incoming_weighted_particles: vec![],
+            outgoing_weighted_particles: vec![],
+            target_particle_handle: ParticleHandle::default(),
+            velocity_prev: Vec2::new(0.0, 0.0),
+            is_enabled: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::particle::Particle;
+
+    use super::*;
+
+    #[test]
+    fn target_follows_weighted_centroid() {
+        let mut particle_data = ParticleData::default();
+        let rim = particle_data.add_particles(&vec![
+            *Particle::default().set_position(vec2(-1.0, 0.0)),
+            *Particle::default().set_position(vec2(1.0, 0.0)),
+            *Particle::default().set_position(vec2(0.0, 1.0)),
+            *Particle::default().set_position(vec2(0.0, -1.0)),
+        ]);
+        let hub = particle_data.add_particles(&vec![*Particle::default().set_position(vec2(5.0, 5.0))])[0];
+
+        let mut attachment = AttachmentConstraint::default();
+        attachment.set_target_particle_handle(hub)
+            .add_even_distribution_of_incoming_particles(&rim, 1.0, 4);
+        assert_eq!(attachment.incoming_weighted_particles.len(), 4);
+
+        attachment.update_constraint(&mut particle_data, 0.01);
+        assert_eq!(particle_data.get_pos_vec2(hub), vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn outgoing_particles_receive_delta_velocity() {
+        let mut particle_data = ParticleData::default();
+        let handles = particle_data.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+            *Particle::default().set_position(vec2(3.0, 0.0)),
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+        ]);
+        let (source, outgoing, hub) = (handles[0], handles[1], handles[2]);
+
+        let mut attachment = AttachmentConstraint::new(vec![WeightedParticle::new(source, 1.0)], vec![WeightedParticle::new(outgoing, 0.5)], hub);
+        attachment.update_constraint(&mut particle_data, 0.01);
+
+        // something (e.g. a collision or stick) pushes the hub after the attachment has been solved
+        particle_data.set_pos_from_vec2(hub, &vec2(0.5, 0.0));
+        attachment.post_update_constraint(&mut particle_data, 0.01);
+
+        assert_eq!(particle_data.get_pos_vec2(outgoing), vec2(3.25, 0.0));
+    }
+}
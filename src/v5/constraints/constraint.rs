@@ -0,0 +1,69 @@
+use std::any::Any;
+
+use bevy::math::{Mat2, Vec2};
+
+use crate::v5::{particle_data::ParticleData, particle_handle::ParticleHandle};
+
+/// Constraints are solved with XPBD (https://matthias-research.github.io/pages/publications/XPBD.pdf).
+/// Softness comes from compliance (the inverse of stiffness) rather than a per-iteration stiffness factor,
+/// so a constraint behaves the same regardless of desired_hertz or the number of solver iterations. A compliance of 0 is fully stiff.
+pub trait Constraint {
+    /// https://users.rust-lang.org/t/solved-is-it-possible-to-clone-a-boxed-trait-object/1714/7
+    /// todo: look at the improved version
+    fn box_clone(&self) -> Box<dyn Constraint + Send + Sync>;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn set_enabled(&mut self, is_enabled: bool);
+
+    fn is_enabled(&self) -> bool;
+
+    /// The pairs of particles this constraint visibly links, eg. the 2 ends of a stick.
+    /// Used by tools that need to know where a constraint is, like cutting.
+    fn particle_links(&self) -> Vec<[ParticleHandle; 2]> {
+        vec![]
+    }
+
+    // ShapeBuilder keeps particle handles in its own local index/space.
+    // then when we move these constraints into the particle system, we need to adjust these offsets
+    // so the handles continue to point to the correct particle which have new indices.
+    // when this adjustment occurs the shape builder will call this function with an offset.
+    // Constraints just need to forward this to their particle handles.
+    fn offset_particle_handles(&mut self, offset: u64);
+
+    // Like offset_particle_handles, but used when the ShapeBuilder removes or merges particles.
+    // Each particle handle should be replaced using ParticleHandle::remap.
+    // Returns false if any particle no longer exists, in which case the ShapeBuilder drops the constraint.
+    // Only constraints used with ShapeBuilder::extract_particles, remove_particles or merge need to implement this,
+    // so the default panics rather than breaking existing implementations or silently dropping the constraint.
+    fn remap_particle_handles(&mut self, _remap: &Vec<Option<ParticleHandle>>) -> bool {
+        unimplemented!("this constraint does not support remapping particle handles")
+    }
+
+    /// Called when a ShapeBuilder transforms the particles of this constraint, so rest lengths, areas and angles follow.
+    /// matrix is the linear part of the transform (translation makes no difference to a constraint),
+    /// and positions are the ShapeBuilder particle positions before the transform, indexed by particle handle id.
+    fn transform(&mut self, _matrix: Mat2, _positions: &Vec<Vec2>) {
+    }
+
+    /// Pairs of particles that should not collide with each other while this constraint exists,
+    /// eg. the 2 particles joined by a stick.
+    fn collision_exclusions(&self) -> Vec<[ParticleHandle; 2]> {
+        vec![]
+    }
+
+    /// Called once at the start of each sub step, before any solver iterations.
+    /// XPBD constraints reset their accumulated lagrange multiplier (lambda) here.
+    fn begin_step(&mut self, _delta_seconds: f32) {
+    }
+
+    /// Called each sub step after collisions are solved, but before particle positions are updated.
+    /// The ConstraintSolver may call this multiple times per sub step (once per iteration).
+    fn update_constraint(&mut self, particle_data: &mut ParticleData, delta_seconds: f32);
+
+    /// Called each sub step after particle positions are updated.
+    fn post_update_constraint(&mut self, _particle_data: &mut ParticleData, _delta_seconds: f32) {
+    }
+}
\ No newline at end of file
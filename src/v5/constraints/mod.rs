@@ -0,0 +1,6 @@
+pub mod constraint;
+pub mod stick_constraint;
+pub mod attachment_constraint;
+pub mod angle_constraint;
+pub mod shape_matching_constraint;
+pub mod area_constraint;
\ No newline at end of file
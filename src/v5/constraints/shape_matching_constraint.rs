@@ -0,0 +1,324 @@
+use std::any::Any;
+
+use bevy::math::{Mat2, Vec2};
+
+use crate::v5::{particle_data::ParticleData, particle_handle::ParticleHandle};
+
+use super::constraint::Constraint;
+
+/// Compute the mass weighted centre of a set of points
+pub fn compute_centre_of_mass(positions: &Vec<Vec2>, masses: &Vec<f32>) -> Vec2 {
+    let mut centre = Vec2::new(0.0, 0.0);
+    let mut total_mass = 0.0;
+    for (pos, mass) in positions.iter().zip(masses.iter()) {
+        centre += *pos * *mass;
+        total_mass += *mass;
+    }
+
+    if total_mass <= 0.0 {
+        return centre;
+    }
+    centre / total_mass
+}
+
+/// Shape matching (Müller et al. 2005, "Meshless Deformations Based on Shape Matching").
+///
+/// Stores the rest shape of a group of particles. Each update the optimal rotation of the rest shape
+/// onto the current particle positions is found, and each particle is pulled towards its goal position.
+/// This keeps soft bodies (jelly cubes etc.) from collapsing under load without needing a grid of sticks.
+///
+/// Each particle is pulled towards its goal position by its own zero rest length distance constraint,
+/// with a separate lambda per particle.
+///
+/// If plastic_yield > 0, then at the end of each sub step any particle further than plastic_yield from its goal position
+/// will have its rest position moved by plastic_creep % towards where it actually is. This
+/// lets the shape be permanently dented.
+#[derive(Debug, Clone)]
+pub struct ShapeMatchingConstraint {
+    pub particle_handles: Vec<ParticleHandle>,
+    pub rest_positions: Vec<Vec2>, // rest positions relative to the rest centre of mass
+    pub compliance: f32, // 0 = rigid, > 0 = each particle is held to its goal position by a spring with stiffness 1 / compliance
+    pub lambdas: Vec<f32>, // accumulated lagrange multiplier per particle for the current sub step
+    pub plastic_yield: f32, // distance from the goal position before deformation becomes permanent. 0 = disabled
+    pub plastic_creep: f32, // % of the deformation that becomes permanent each sub step once yield is exceeded
+    pub is_enabled: bool
+}
+
+impl ShapeMatchingConstraint {
+    pub fn set_compliance(&mut self, compliance: f32) -> &mut Self {
+        debug_assert!(compliance >= 0.0);
+        self.compliance = compliance;
+        self
+    }
+
+    pub fn set_plasticity(&mut self, plastic_yield: f32, plastic_creep: f32) -> &mut Self {
+        debug_assert!(plastic_yield >= 0.0);
+        debug_assert!(plastic_creep >= 0.0 && plastic_creep <= 1.0);
+        self.plastic_yield = plastic_yield;
+        self.plastic_creep = plastic_creep;
+        self
+    }
+
+    /// Set the particles in the group and their rest positions (in world space).
+    pub fn set_particles(&mut self, particle_handles: Vec<ParticleHandle>, positions: &Vec<Vec2>, masses: &Vec<f32>) -> &mut Self {
+        debug_assert!(particle_handles.len() == positions.len());
+        debug_assert!(particle_handles.len() == masses.len());
+
+        let centre = compute_centre_of_mass(positions, masses);
+        self.particle_handles = particle_handles;
+        self.rest_positions = positions.iter().map(|pos| *pos - centre).collect();
+        self
+    }
+
+    /// Find the rotation (in radians) that best maps the rest positions onto the current positions
+    pub fn compute_optimal_rotation(&self, positions: &Vec<Vec2>, masses: &Vec<f32>, centre: Vec2) -> f32 {
+        // in 2d the polar decomposition of A_pq reduces to a single angle
+        let mut dot = 0.0;
+        let mut cross = 0.0;
+        for ((pos, rest_pos), mass) in positions.iter().zip(self.rest_positions.iter()).zip(masses.iter()) {
+            let p = *pos - centre;
+            dot += mass * rest_pos.dot(p);
+            cross += mass * rest_pos.perp_dot(p);
+        }
+        f32::atan2(cross, dot)
+    }
+
+    /// Compute the current goal position for each particle
+    pub fn compute_goal_positions(&self, particle_data: &ParticleData) -> Vec<Vec2> {
+        let positions = self.particle_handles.iter().map(|handle| particle_data.get_pos_vec2(*handle)).collect::<Vec<Vec2>>();
+        let masses = self.particle_handles.iter().map(|handle| particle_data.get_mass(*handle)).collect::<Vec<f32>>();
+
+        let centre = compute_centre_of_mass(&positions, &masses);
+        let rotation = Vec2::from_angle(self.compute_optimal_rotation(&positions, &masses, centre));
+
+        self.rest_positions.iter().map(|rest_pos| centre + rotation.rotate(*rest_pos)).collect()
+    }
+
+    fn apply_plastic_deformation(&mut self, particle_data: &ParticleData, goal_positions: &Vec<Vec2>) {
+        let positions = self.particle_handles.iter().map(|handle| particle_data.get_pos_vec2(*handle)).collect::<Vec<Vec2>>();
+        let masses = self.particle_handles.iter().map(|handle| particle_data.get_mass(*handle)).collect::<Vec<f32>>();
+
+        let centre = compute_centre_of_mass(&positions, &masses);
+        let inverse_rotation = Vec2::from_angle(-self.compute_optimal_rotation(&positions, &masses, centre));
+
+        let mut deformed = false;
+        for i in 0..self.rest_positions.len() {
+            if (positions[i] - goal_positions[i]).length() <= self.plastic_yield {
+                continue;
+            }
+
+            // where the particle is now, in the rest shape's space
+            let actual_rest_pos = inverse_rotation.rotate(positions[i] - centre);
+            let rest_pos = self.rest_positions[i];
+            self.rest_positions[i] = rest_pos + (actual_rest_pos - rest_pos) * self.plastic_creep;
+            deformed = true;
+        }
+
+        // keep the rest shape centred on its centre of mass
+        if deformed {
+            let rest_centre = compute_centre_of_mass(&self.rest_positions, &masses);
+            for rest_pos in self.rest_positions.iter_mut() {
+                *rest_pos -= rest_centre;
+            }
+        }
+    }
+}
+
+impl Constraint for ShapeMatchingConstraint {
+    fn box_clone(&self) -> Box<dyn Constraint + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_enabled = is_enabled;
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+
+    fn transform(&mut self, matrix: Mat2, _positions: &Vec<Vec2>) {
+        for rest_pos in self.rest_positions.iter_mut() {
+            *rest_pos = matrix * *rest_pos;
+        }
+    }
+
+    fn offset_particle_handles(&mut self, offset: u64) {
+        for particle_handle in self.particle_handles.iter_mut() {
+            particle_handle.offset(offset);
+        }
+    }
+
+    fn remap_particle_handles(&mut self, remap: &Vec<Option<ParticleHandle>>) -> bool {
+        self.particle_handles.iter_mut().all(|particle_handle| particle_handle.remap(remap))
+    }
+
+    fn begin_step(&mut self, _delta_seconds: f32) {
+        self.lambdas.clear();
+        self.lambdas.resize(self.particle_handles.len(), 0.0);
+    }
+
+    fn update_constraint(&mut self, particle_data: &mut ParticleData, delta_seconds: f32) {
+        if !self.is_enabled || self.particle_handles.is_empty() {
+            return;
+        }
+
+        if self.lambdas.len() != self.particle_handles.len() {
+            self.lambdas = vec![0.0; self.particle_handles.len()];
+        }
+
+        // f32::INFINITY compliance turns off shape matching, leaving only plastic deformation of the rest shape
+        if !self.compliance.is_finite() {
+            return;
+        }
+
+        let goal_positions = self.compute_goal_positions(particle_data);
+
+        // XPBD: https://matthias-research.github.io/pages/publications/XPBD.pdf
+        // the goal position is treated as fixed, so only the particle moves
+        let alpha = self.compliance / (delta_seconds * delta_seconds);
+        for i in 0..self.particle_handles.len() {
+            let particle_handle = self.particle_handles[i];
+            let inverse_mass = particle_data.get_inverse_mass(particle_handle);
+            if inverse_mass <= 0.0 || !particle_data.is_enabled(particle_handle) {
+                continue;
+            }
+
+            let pos = particle_data.get_pos_vec2(particle_handle);
+            let difference = pos - goal_positions[i];
+            let error = difference.length();
+            if error <= f32::EPSILON {
+                continue;
+            }
+
+            let delta_lambda = (-error - alpha * self.lambdas[i]) / (inverse_mass + alpha);
+            self.lambdas[i] += delta_lambda;
+
+            let new_pos = pos + (difference / error) * (delta_lambda * inverse_mass);
+            debug_assert!(!new_pos.x.is_nan());
+            debug_assert!(!new_pos.y.is_nan());
+            particle_data.set_pos_from_vec2(particle_handle, &new_pos);
+        }
+    }
+
+    // plasticity is applied once per sub step rather than once per solver iteration,
+    // so the amount of permanent deformation does not depend on the iteration count
+    fn post_update_constraint(&mut self, particle_data: &mut ParticleData, _delta_seconds: f32) {
+        if !self.is_enabled || self.particle_handles.is_empty() || self.plastic_yield <= 0.0 {
+            return;
+        }
+
+        let goal_positions = self.compute_goal_positions(particle_data);
+        self.apply_plastic_deformation(particle_data, &goal_positions);
+    }
+}
+
+impl Default for ShapeMatchingConstraint {
+    fn default() -> Self {
+        Self {
+            particle_handles: vec![],
+            rest_positions: vec![],
+            // about the same softness as the old default of moving half way to the goal each update, at 240 hertz
+            compliance: 0.00002,
+            lambdas: vec![],
+            plastic_yield: 0.0,
+            plastic_creep: 0.0,
+            is_enabled: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use bevy::math::vec2;
+
+    use crate::v5::particle::Particle;
+
+    use super::*;
+
+    fn square() -> Vec<Vec2> {
+        vec![vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(1.0, 1.0), vec2(-1.0, 1.0)]
+    }
+
+    fn add_square(particle_data: &mut ParticleData, positions: &Vec<Vec2>) -> ShapeMatchingConstraint {
+        let particles = positions.iter().map(|pos| *Particle::default().set_position(*pos)).collect::<Vec<Particle>>();
+        let handles = particle_data.add_particles(&particles);
+
+        let mut constraint = ShapeMatchingConstraint::default();
+        constraint.set_particles(handles, &square(), &vec![1.0; 4]);
+        constraint
+    }
+
+    #[test]
+    fn rotated_shape_is_not_deformed() {
+        let rotation = Vec2::from_angle(FRAC_PI_2);
+        let positions = square().iter().map(|pos| rotation.rotate(*pos) + vec2(3.0, 0.0)).collect::<Vec<Vec2>>();
+
+        let mut particle_data = ParticleData::default();
+        let mut constraint = add_square(&mut particle_data, &positions);
+        constraint.set_compliance(0.0);
+        constraint.update_constraint(&mut particle_data, 0.01);
+
+        for (handle, pos) in constraint.particle_handles.iter().zip(positions.iter()) {
+            assert!((particle_data.get_pos_vec2(*handle) - *pos).length() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn squashed_shape_is_restored() {
+        let positions = vec![vec2(-1.0, -0.2), vec2(1.0, -0.2), vec2(1.0, 0.2), vec2(-1.0, 0.2)];
+
+        let mut particle_data = ParticleData::default();
+        let mut constraint = add_square(&mut particle_data, &positions);
+        constraint.set_compliance(0.0);
+        constraint.update_constraint(&mut particle_data, 0.01);
+
+        for (handle, pos) in constraint.particle_handles.iter().zip(square().iter()) {
+            assert!((particle_data.get_pos_vec2(*handle) - *pos).length() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn plastic_deformation_changes_rest_shape() {
+        let positions = vec![vec2(-1.0, -0.2), vec2(1.0, -0.2), vec2(1.0, 0.2), vec2(-1.0, 0.2)];
+
+        let mut particle_data = ParticleData::default();
+        let mut constraint = add_square(&mut particle_data, &positions);
+        constraint.set_compliance(f32::INFINITY).set_plasticity(0.1, 1.0);
+        constraint.update_constraint(&mut particle_data, 0.01);
+        constraint.post_update_constraint(&mut particle_data, 0.01);
+
+        for (rest_pos, pos) in constraint.rest_positions.iter().zip(positions.iter()) {
+            assert!((*rest_pos - *pos).length() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn plastic_deformation_is_once_per_sub_step() {
+        let positions = vec![vec2(-1.0, -0.2), vec2(1.0, -0.2), vec2(1.0, 0.2), vec2(-1.0, 0.2)];
+
+        let mut particle_data = ParticleData::default();
+        let mut constraint = add_square(&mut particle_data, &positions);
+        constraint.set_compliance(f32::INFINITY).set_plasticity(0.1, 0.5);
+
+        // several solver iterations in one sub step only creep once, half way to the squashed shape
+        for _ in 0..4 {
+            constraint.update_constraint(&mut particle_data, 0.01);
+        }
+        constraint.post_update_constraint(&mut particle_data, 0.01);
+
+        for ((rest_pos, pos), original_pos) in constraint.rest_positions.iter().zip(positions.iter()).zip(square().iter()) {
+            assert!((*rest_pos - (*original_pos + *pos) * 0.5).length() < 0.0001);
+        }
+    }
+}
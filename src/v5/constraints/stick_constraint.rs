@@ -0,0 +1,224 @@
+use std::any::Any;
+
+use bevy::math::{Mat2, Vec2};
+
+use crate::v5::{particle_data::ParticleData, particle_handle::ParticleHandle};
+
+use super::constraint::Constraint;
+
+
+/// XPBD distance constraint that moves particles (weighted by inverse mass) to try to maintain a
+/// given distance between them.
+///
+/// Unlike the v4 stick, softness comes from compliance (see Constraint), in metres per newton.
+#[derive(Debug, Copy, Clone)]
+pub struct StickConstraint {
+    pub particle_handles: [ParticleHandle; 2],
+    pub length: f32,
+    pub compliance: f32, // 0 = fully stiff, > 0 = spring with stiffness 1 / compliance
+    pub lambda: f32, // accumulated lagrange multiplier for the current sub step
+    pub exclude_collisions: bool, // stop the 2 particles colliding so collisions do not fight the stick. Off by default
+    pub is_enabled: bool
+}
+
+impl StickConstraint {
+    pub fn set_compliance(&mut self, compliance: f32) -> &mut Self {
+        debug_assert!(compliance >= 0.0);
+        self.compliance = compliance;
+        self
+    }
+
+    pub fn set_exclude_collisions(&mut self, exclude_collisions: bool) -> &mut Self {
+        self.exclude_collisions = exclude_collisions;
+        self
+    }
+
+    pub fn set_particle_handles(&mut self, particle_handles: [ParticleHandle; 2]) -> &mut Self {
+        self.particle_handles = particle_handles;
+        self
+    }
+
+    pub fn set_length(&mut self, length: f32) -> &mut Self {
+        self.length = length;
+        self
+    }
+}
+
+impl Constraint for StickConstraint {
+    fn box_clone(&self) -> Box<dyn Constraint + Send + Sync> {
+        Box::new((*self).clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_enabled = is_enabled;
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+
+    fn particle_links(&self) -> Vec<[ParticleHandle; 2]> {
+        vec![self.particle_handles]
+    }
+
+    fn transform(&mut self, matrix: Mat2, positions: &Vec<Vec2>) {
+        let delta = positions[self.particle_handles[1].id()] - positions[self.particle_handles[0].id()];
+        match delta.try_normalize() {
+            Some(direction) => self.length *= (matrix * direction).length(),
+            None => self.length *= matrix.determinant().abs().sqrt(),
+        }
+    }
+
+    fn offset_particle_handles(&mut self, offset: u64) {
+        for particle_handle in self.particle_handles.iter_mut() {
+            particle_handle.offset(offset);
+        }
+    }
+
+    fn remap_particle_handles(&mut self, remap: &Vec<Option<ParticleHandle>>) -> bool {
+        self.particle_handles.iter_mut().all(|particle_handle| particle_handle.remap(remap))
+    }
+
+    fn collision_exclusions(&self) -> Vec<[ParticleHandle; 2]> {
+        if self.exclude_collisions { vec![self.particle_handles] } else { vec![] }
+    }
+
+    fn begin_step(&mut self, _delta_seconds: f32) {
+        self.lambda = 0.0;
+    }
+
+    fn update_constraint(&mut self, particle_data: &mut ParticleData, delta_seconds: f32) {
+        if !self.is_enabled {
+            return;
+        }
+
+        let [handle_a, handle_b] = self.particle_handles;
+        if !particle_data.is_enabled(handle_a) || !particle_data.is_enabled(handle_b) {
+            return;
+        }
+
+        let a_inverse_mass = particle_data.get_inverse_mass(handle_a);
+        let b_inverse_mass = particle_data.get_inverse_mass(handle_b);
+        let inverse_mass_sum = a_inverse_mass + b_inverse_mass;
+        if inverse_mass_sum <= 0.0 {
+            return;
+        }
+
+        let pos_a = particle_data.get_pos_vec2(handle_a);
+        let pos_b = particle_data.get_pos_vec2(handle_b);
+
+        let difference = pos_a - pos_b;
+        let diff_length = difference.length();
+        if diff_length <= f32::EPSILON {
+            return;
+        }
+
+        // XPBD: https://matthias-research.github.io/pages/publications/XPBD.pdf
+        let error = diff_length - self.length;
+        let alpha = self.compliance / (delta_seconds * delta_seconds);
+        let delta_lambda = (-error - alpha * self.lambda) / (inverse_mass_sum + alpha);
+        self.lambda += delta_lambda;
+
+        let offset = (difference / diff_length) * delta_lambda;
+
+        let new_pos_a = pos_a + offset * a_inverse_mass;
+        debug_assert!(!new_pos_a.x.is_nan());
+        debug_assert!(!new_pos_a.y.is_nan());
+        particle_data.set_pos_from_vec2(handle_a, &new_pos_a);
+
+        let new_pos_b = pos_b - offset * b_inverse_mass;
+        debug_assert!(!new_pos_b.x.is_nan());
+        debug_assert!(!new_pos_b.y.is_nan());
+        particle_data.set_pos_from_vec2(handle_b, &new_pos_b);
+    }
+}
+
+impl Default for StickConstraint {
+    fn default() -> Self {
+        Self {
+            particle_handles: [ParticleHandle::default(); 2],
+            length: 0.0,
+            compliance: 0.0,
+            lambda: 0.0,
+            exclude_collisions: false,
+            is_enabled: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::particle::Particle;
+
+    use super::*;
+
+    fn stretch(compliance: f32, delta_seconds: f32) -> f32 {
+        let mut particle_data = ParticleData::default();
+        let handles = particle_data.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)).set_static(true),
+            *Particle::default().set_position(vec2(2.0, 0.0)),
+        ]);
+
+        let mut constraint = StickConstraint::default();
+        constraint.set_particle_handles([handles[0], handles[1]]).set_length(1.0).set_compliance(compliance);
+        constraint.begin_step(delta_seconds);
+        constraint.update_constraint(&mut particle_data, delta_seconds);
+        particle_data.get_pos_vec2(handles[1]).x
+    }
+
+    // hang a particle from a static particle and let it settle, for the same wall clock duration however it is divided up.
+    // returns how far the stick has stretched
+    fn hanging_stretch(compliance: f32, hertz: f32, iterations: usize) -> f32 {
+        let mut particle_data = ParticleData::default();
+        let handles = particle_data.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)).set_static(true),
+            *Particle::default().set_position(vec2(0.0, -1.0)),
+        ]);
+
+        let mut constraint = StickConstraint::default();
+        constraint.set_particle_handles([handles[0], handles[1]]).set_length(1.0).set_compliance(compliance);
+
+        // a simple position based integrator with velocity damping, so the particle comes to rest
+        let delta_seconds = 1.0 / hertz;
+        let gravity = vec2(0.0, -10.0);
+        let mut velocity = Vec2::ZERO;
+        for _ in 0..(5.0 * hertz) as usize {
+            let pos = particle_data.get_pos_vec2(handles[1]);
+            velocity = velocity * (-5.0 * delta_seconds).exp() + gravity * delta_seconds;
+            particle_data.set_pos_from_vec2(handles[1], &(pos + velocity * delta_seconds));
+
+            constraint.begin_step(delta_seconds);
+            for _ in 0..iterations {
+                constraint.update_constraint(&mut particle_data, delta_seconds);
+            }
+            velocity = (particle_data.get_pos_vec2(handles[1]) - pos) / delta_seconds;
+        }
+
+        -particle_data.get_pos_vec2(handles[1]).y - 1.0
+    }
+
+    #[test]
+    fn rigid_stick_is_solved_in_one_update() {
+        assert!((stretch(0.0, 0.01) - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn compliant_stick_is_independent_of_hertz_and_iterations() {
+        // at rest the stick stretches by compliance * mass * gravity
+        let expected = 0.001 * 1.0 * 10.0;
+        for (hertz, iterations) in [(60.0, 1), (240.0, 1), (60.0, 4), (240.0, 4)] {
+            let stretch = hanging_stretch(0.001, hertz, iterations);
+            assert!((stretch - expected).abs() < 0.0005, "hertz {}, iterations {}: stretch {}", hertz, iterations, stretch);
+        }
+    }
+}
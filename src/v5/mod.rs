@@ -14,6 +14,16 @@ pub mod particle_system;
 pub mod shape_builder;
 pub mod simd_ext;
 
+pub mod constraints;
+pub mod constraint_container;
+pub mod constraint_solver;
+pub mod body_container;
+pub mod particle_manipulator;
+pub mod spatial_query;
+pub mod sensor_container;
+pub mod static_collider;
+pub mod world_bounds;
+
 pub mod aabb_simd;
 pub mod particle_data;
 
@@ -1,6 +1,6 @@
 use bevy::math::{vec2, Vec2};
 
-use super::particle_solver::compute_movement_weight;
+use super::particle_solver::{compute_movement_weight, should_collide};
 use super::particle_vec::SharedParticleVec;
 
 pub struct NaiveParticleSolver {
@@ -38,6 +38,10 @@ impl NaiveParticleSolver {
                     continue;
                 }
 
+                if !should_collide(particle_vec.collision_group[ai], particle_vec.collision_mask[ai], particle_vec.collision_group[bi], particle_vec.collision_mask[bi]) {
+                    continue;
+                }
+
                 let (a_movement_weight, b_movement_weight) = compute_movement_weight(particle_vec.is_static[ai], particle_vec.is_static[bi]);
                 
                 let collision_axis: Vec2;
@@ -1,5 +1,9 @@
 use bevy::{color::Color, math::{bounding::Aabb2d, vec2, Vec2}};
 
+pub const COLLISION_GROUP_DEFAULT: u32 = 0x1;
+pub const COLLISION_MASK_ALL: u32 = u32::MAX;
+pub const COLLISION_MASK_NONE: u32 = 0x0; // special no collisions mask, eg. for invisible anchor particles like wheel hubs
+
 #[derive(Debug, Copy, Clone)]
 pub struct Particle {
     pub pos: Vec2,
@@ -11,6 +15,12 @@ pub struct Particle {
     pub is_enabled: bool,
 
     pub force: Vec2, // should this be here? when we apply a force can we not just move the pos?
+
+    pub collision_group: u32, // bits for the groups this particle belongs to
+    pub collision_mask: u32, // bits for the groups this particle collides with
+
+    pub body_id: u32, // 0 = not part of a body
+    pub collides_with_self: bool, // if false, this particle does not collide with other particles with the same body_id
 }
 
 impl Particle {
@@ -18,7 +28,7 @@ impl Particle {
         debug_assert!(!pos.x.is_nan());
         debug_assert!(!pos.y.is_nan());
         
-        Self { pos, pos_prev: pos, radius, mass, is_static, color, is_enabled: true, force: vec2(0.0, 0.0) }
+        Self { pos, pos_prev: pos, radius, mass, is_static, color, is_enabled: true, force: vec2(0.0, 0.0), collision_group: COLLISION_GROUP_DEFAULT, collision_mask: COLLISION_MASK_ALL, body_id: 0, collides_with_self: true }
     }
 
     pub fn set_radius(&mut self, radius: f32) -> &mut Self {
@@ -52,6 +62,26 @@ impl Particle {
         self
     }
 
+    pub fn set_collision_group(&mut self, collision_group: u32) -> &mut Self {
+        self.collision_group = collision_group;
+        self
+    }
+
+    pub fn set_collision_mask(&mut self, collision_mask: u32) -> &mut Self {
+        self.collision_mask = collision_mask;
+        self
+    }
+
+    pub fn set_body_id(&mut self, body_id: u32) -> &mut Self {
+        self.body_id = body_id;
+        self
+    }
+
+    pub fn set_collides_with_self(&mut self, collides_with_self: bool) -> &mut Self {
+        self.collides_with_self = collides_with_self;
+        self
+    }
+
     pub fn get_aabb(&self) -> Aabb2d {
         debug_assert!(!self.pos.x.is_nan());
         debug_assert!(!self.pos.y.is_nan());
@@ -93,6 +123,10 @@ impl Default for Particle {
             color: Color::WHITE,
             is_enabled: true,
             force: vec2(0.0, 0.0),
+            collision_group: COLLISION_GROUP_DEFAULT,
+            collision_mask: COLLISION_MASK_ALL,
+            body_id: 0,
+            collides_with_self: true,
         }
     }
 }
\ No newline at end of file
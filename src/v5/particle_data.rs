@@ -1,5 +1,24 @@
+use std::simd::f32x2;
+
+use bevy::math::Vec2;
+
 use super::{particle::Particle, particle_handle::ParticleHandle, particle_vec::ParticleVec};
 
+/// Which ParticleVec inside ParticleData a particle currently lives in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleVecType {
+    Static,
+    Dynamic,
+    Disabled,
+}
+
+/// Where a ParticleHandle points to inside ParticleData
+#[derive(Debug, Clone, Copy)]
+pub struct ParticleLocation {
+    pub vec_type: ParticleVecType,
+    pub index: usize,
+}
+
 pub struct ParticleData {
     // this combines static and dynamic particles, as it seems seperating these isnt helpful
     pub enabled_particles: ParticleVec,
@@ -7,16 +26,24 @@ pub struct ParticleData {
     pub static_particles: ParticleVec,
     pub dynamic_particles: ParticleVec,
     pub disabled_particles: ParticleVec,
-    //pub particle_handles: ParticleHandle // todo: map from ParticleHandle to Particle
+
+    // map from ParticleHandle to the ParticleVec + index the particle is stored in
+    pub particle_locations: Vec<ParticleLocation>,
+
+    // when Some, set_pos_from_vec2 accumulates position corrections per particle handle instead of
+    // writing them, so every constraint sees the same positions (Jacobi ordering)
+    deferred_position_corrections: Option<Vec<(Vec2, u32)>>,
 }
 
 impl Default for ParticleData {
     fn default() -> Self {
-        Self { 
+        Self {
             enabled_particles: ParticleVec::default(),
             static_particles: ParticleVec::default(),
             dynamic_particles: ParticleVec::default(),
             disabled_particles: ParticleVec::default(),
+            particle_locations: vec![],
+            deferred_position_corrections: None,
         }
     }
 }
@@ -24,23 +51,324 @@ impl Default for ParticleData {
 impl ParticleData {
 
     pub fn add_particles(&mut self, particles: &Vec<Particle>) -> Vec<ParticleHandle> {
-        // todo: handles support
         let mut handles = Vec::new();
         for p in particles {
-            if !p.is_enabled {
-                self.disabled_particles.add(*p);
+            let vec_type = if !p.is_enabled {
+                ParticleVecType::Disabled
             }
             else if p.is_static {
-                self.static_particles.add(*p);
+                ParticleVecType::Static
             }
             else {
-                self.dynamic_particles.add(*p);
-            }
+                ParticleVecType::Dynamic
+            };
+
+            let handle = ParticleHandle::new(self.particle_locations.len());
+
+            let particle_vec = self.get_particle_vec_mut(vec_type);
+            let local_handle = particle_vec.add(*p);
+            particle_vec.particle_handle[local_handle.id()] = handle;
 
             if p.is_enabled {
-                self.enabled_particles.add(*p);
+                let enabled_local_handle = self.enabled_particles.add(*p);
+                self.enabled_particles.particle_handle[enabled_local_handle.id()] = handle;
             }
+
+            handles.push(handle);
+            self.particle_locations.push(ParticleLocation { vec_type, index: local_handle.id() });
         }
         handles
     }
-}
\ No newline at end of file
+
+    /// The number of particle handles that have been issued
+    pub fn len(&self) -> usize {
+        self.particle_locations.len()
+    }
+
+    #[inline(always)]
+    pub fn get_location(&self, particle_handle: ParticleHandle) -> ParticleLocation {
+        self.particle_locations[particle_handle.id()]
+    }
+
+    pub fn get_particle_vec(&self, vec_type: ParticleVecType) -> &ParticleVec {
+        match vec_type {
+            ParticleVecType::Static => &self.static_particles,
+            ParticleVecType::Dynamic => &self.dynamic_particles,
+            ParticleVecType::Disabled => &self.disabled_particles,
+        }
+    }
+
+    pub fn get_particle_vec_mut(&mut self, vec_type: ParticleVecType) -> &mut ParticleVec {
+        match vec_type {
+            ParticleVecType::Static => &mut self.static_particles,
+            ParticleVecType::Dynamic => &mut self.dynamic_particles,
+            ParticleVecType::Disabled => &mut self.disabled_particles,
+        }
+    }
+
+    /// Get a copy of the particle that the particle_handle refers to.
+    pub fn get(&self, particle_handle: ParticleHandle) -> Option<Particle> {
+        if particle_handle.id() >= self.len() {
+            return None;
+        }
+
+        let location = self.get_location(particle_handle);
+        self.get_particle_vec(location.vec_type).get(ParticleHandle::new(location.index))
+    }
+
+    #[inline(always)]
+    pub fn get_pos_vec2(&self, particle_handle: ParticleHandle) -> Vec2 {
+        let location = self.get_location(particle_handle);
+        self.get_particle_vec(location.vec_type).get_pos_vec2(location.index)
+    }
+
+    #[inline(always)]
+    pub fn get_pos_prev_vec2(&self, particle_handle: ParticleHandle) -> Vec2 {
+        let location = self.get_location(particle_handle);
+        let pos_prev = self.get_particle_vec(location.vec_type).pos_prev[location.index].as_array();
+        Vec2::new(pos_prev[0], pos_prev[1])
+    }
+
+    #[inline(always)]
+    pub fn set_pos_from_vec2(&mut self, particle_handle: ParticleHandle, pos: &Vec2) {
+        if self.deferred_position_corrections.is_some() {
+            let correction = *pos - self.get_pos_vec2(particle_handle);
+            let deferred = &mut self.deferred_position_corrections.as_mut().unwrap()[particle_handle.id()];
+            deferred.0 += correction;
+            deferred.1 += 1;
+            return;
+        }
+
+        let location = self.get_location(particle_handle);
+        self.get_particle_vec_mut(location.vec_type).set_pos_from_vec2(location.index, pos);
+    }
+
+    /// Start deferring position changes. Until apply_deferred_position_changes is called,
+    /// set_pos_from_vec2 records a correction instead of moving the particle.
+    pub fn begin_deferred_position_changes(&mut self) {
+        let mut corrections = self.deferred_position_corrections.take().unwrap_or_default();
+        corrections.clear();
+        corrections.resize(self.len(), (Vec2::new(0.0, 0.0), 0));
+        self.deferred_position_corrections = Some(corrections);
+    }
+
+    /// Stop deferring position changes and move each particle by the average of its corrections
+    /// scaled by relaxation.
+    pub fn apply_deferred_position_changes(&mut self, relaxation: f32) {
+        let Some(corrections) = self.deferred_position_corrections.take() else { return };
+
+        for (id, (correction, count)) in corrections.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+
+            let particle_handle = ParticleHandle::new(id);
+            let pos = self.get_pos_vec2(particle_handle) + *correction * (relaxation / *count as f32);
+            self.set_pos_from_vec2(particle_handle, &pos);
+        }
+    }
+
+    #[inline(always)]
+    pub fn set_pos_prev_from_vec2(&mut self, particle_handle: ParticleHandle, pos_prev: &Vec2) {
+        let location = self.get_location(particle_handle);
+        self.get_particle_vec_mut(location.vec_type).pos_prev[location.index] = f32x2::from_array([pos_prev.x, pos_prev.y]);
+    }
+
+    #[inline(always)]
+    pub fn add_force(&mut self, particle_handle: ParticleHandle, force: &Vec2) {
+        let location = self.get_location(particle_handle);
+        self.get_particle_vec_mut(location.vec_type).force[location.index] += f32x2::from_array([force.x, force.y]);
+    }
+
+    pub fn set_body_id(&mut self, particle_handle: ParticleHandle, body_id: u32) {
+        let location = self.get_location(particle_handle);
+        self.get_particle_vec_mut(location.vec_type).body_id[location.index] = body_id;
+    }
+
+    /// Enable or disable a particle. This moves the particle between the static/dynamic and disabled ParticleVecs,
+    /// so the solver needs to be notified afterwards. The particle handle remains valid.
+    pub fn set_enabled(&mut self, particle_handle: ParticleHandle, is_enabled: bool) {
+        let location = self.get_location(particle_handle);
+        let is_static = self.get_particle_vec(location.vec_type).is_static[location.index];
+
+        let vec_type = if !is_enabled {
+            ParticleVecType::Disabled
+        }
+        else if is_static {
+            ParticleVecType::Static
+        }
+        else {
+            ParticleVecType::Dynamic
+        };
+
+        self.move_particle(particle_handle, vec_type);
+    }
+
+    fn move_particle(&mut self, particle_handle: ParticleHandle, vec_type: ParticleVecType) {
+        let location = self.get_location(particle_handle);
+        if location.vec_type == vec_type {
+            return;
+        }
+
+        // remove from the old ParticleVec, fixing up the location of the particle that gets swapped into its place
+        let (mut particle, collision_exclusions, swapped_handle) = {
+            let particle_vec = self.get_particle_vec_mut(location.vec_type);
+            let particle = particle_vec.get(ParticleHandle::new(location.index)).unwrap();
+            let collision_exclusions = particle_vec.collision_exclusions[location.index].clone();
+            particle_vec.swap_remove(location.index);
+            let swapped_handle = if location.index < particle_vec.len() { Some(particle_vec.particle_handle[location.index]) } else { None };
+            (particle, collision_exclusions, swapped_handle)
+        };
+
+        if let Some(swapped_handle) = swapped_handle {
+            self.particle_locations[swapped_handle.id()].index = location.index;
+        }
+
+        particle.is_enabled = vec_type != ParticleVecType::Disabled;
+
+        let particle_vec = self.get_particle_vec_mut(vec_type);
+        let index = particle_vec.add(particle).id();
+        particle_vec.particle_handle[index] = particle_handle;
+        particle_vec.collision_exclusions[index] = collision_exclusions;
+
+        self.particle_locations[particle_handle.id()] = ParticleLocation { vec_type, index };
+    }
+
+    /// Stop 2 particles from colliding with each other, eg. because they are joined by a stick
+    pub fn add_collision_exclusion(&mut self, particle_handle_a: ParticleHandle, particle_handle_b: ParticleHandle) {
+        for (handle, other_handle) in [(particle_handle_a, particle_handle_b), (particle_handle_b, particle_handle_a)] {
+            let location = self.get_location(handle);
+            let exclusions = &mut self.get_particle_vec_mut(location.vec_type).collision_exclusions[location.index];
+            if !exclusions.contains(&other_handle) {
+                exclusions.push(other_handle);
+            }
+        }
+    }
+
+    /// Let 2 particles collide again, undoing add_collision_exclusion
+    pub fn remove_collision_exclusion(&mut self, particle_handle_a: ParticleHandle, particle_handle_b: ParticleHandle) {
+        for (handle, other_handle) in [(particle_handle_a, particle_handle_b), (particle_handle_b, particle_handle_a)] {
+            let location = self.get_location(handle);
+            let exclusions = &mut self.get_particle_vec_mut(location.vec_type).collision_exclusions[location.index];
+            exclusions.retain(|excluded_handle| *excluded_handle != other_handle);
+        }
+    }
+
+    /// Are 2 particles stopped from colliding with each other by add_collision_exclusion
+    pub fn has_collision_exclusion(&self, particle_handle_a: ParticleHandle, particle_handle_b: ParticleHandle) -> bool {
+        let location = self.get_location(particle_handle_a);
+        self.get_particle_vec(location.vec_type).collision_exclusions[location.index].contains(&particle_handle_b)
+    }
+
+    /// The inverse mass of a particle, where static particles have an inverse mass of 0 (infinite mass)
+    #[inline(always)]
+    pub fn get_inverse_mass(&self, particle_handle: ParticleHandle) -> f32 {
+        if self.is_static(particle_handle) {
+            return 0.0;
+        }
+
+        let mass = self.get_mass(particle_handle);
+        if mass <= 0.0 { 0.0 } else { 1.0 / mass }
+    }
+
+    #[inline(always)]
+    pub fn get_mass(&self, particle_handle: ParticleHandle) -> f32 {
+        let location = self.get_location(particle_handle);
+        self.get_particle_vec(location.vec_type).mass[location.index][0]
+    }
+
+    #[inline(always)]
+    pub fn get_radius(&self, particle_handle: ParticleHandle) -> f32 {
+        let location = self.get_location(particle_handle);
+        self.get_particle_vec(location.vec_type).radius[location.index][0]
+    }
+
+    #[inline(always)]
+    pub fn get_collision_group(&self, particle_handle: ParticleHandle) -> u32 {
+        let location = self.get_location(particle_handle);
+        self.get_particle_vec(location.vec_type).collision_group[location.index]
+    }
+
+    #[inline(always)]
+    pub fn get_body_id(&self, particle_handle: ParticleHandle) -> u32 {
+        let location = self.get_location(particle_handle);
+        self.get_particle_vec(location.vec_type).body_id[location.index]
+    }
+
+    #[inline(always)]
+    pub fn is_static(&self, particle_handle: ParticleHandle) -> bool {
+        self.get_location(particle_handle).vec_type == ParticleVecType::Static
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self, particle_handle: ParticleHandle) -> bool {
+        self.get_location(particle_handle).vec_type != ParticleVecType::Disabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+
+    #[test]
+    fn add_particles_returns_handles() {
+        let mut particle_data = ParticleData::default();
+        let handles = particle_data.add_particles(&vec![
+            *Particle::default().set_position(vec2(1.0, 0.0)),
+            *Particle::default().set_position(vec2(2.0, 0.0)).set_static(true),
+            *Particle::default().set_position(vec2(3.0, 0.0)),
+        ]);
+
+        assert_eq!(handles.len(), 3);
+        assert_eq!(particle_data.dynamic_particles.len(), 2);
+        assert_eq!(particle_data.static_particles.len(), 1);
+
+        assert!(particle_data.is_static(handles[1]));
+        assert_eq!(particle_data.get_pos_vec2(handles[2]), vec2(3.0, 0.0));
+        assert_eq!(particle_data.get(handles[1]).unwrap().pos, vec2(2.0, 0.0));
+    }
+
+    #[test]
+    fn disable_and_enable_keeps_handles_valid() {
+        let mut particle_data = ParticleData::default();
+        let handles = particle_data.add_particles(&vec![
+            *Particle::default().set_position(vec2(1.0, 0.0)),
+            *Particle::default().set_position(vec2(2.0, 0.0)),
+            *Particle::default().set_position(vec2(3.0, 0.0)),
+        ]);
+
+        particle_data.set_enabled(handles[0], false);
+        assert!(!particle_data.is_enabled(handles[0]));
+        assert_eq!(particle_data.dynamic_particles.len(), 2);
+        assert_eq!(particle_data.disabled_particles.len(), 1);
+
+        // the last particle was swapped into the removed particles place
+        assert_eq!(particle_data.get_pos_vec2(handles[2]), vec2(3.0, 0.0));
+        assert_eq!(particle_data.get_pos_vec2(handles[0]), vec2(1.0, 0.0));
+
+        particle_data.set_enabled(handles[0], true);
+        assert!(particle_data.is_enabled(handles[0]));
+        assert_eq!(particle_data.dynamic_particles.len(), 3);
+        for (handle, x) in handles.iter().zip([1.0, 2.0, 3.0]) {
+            assert_eq!(particle_data.get_pos_vec2(*handle), vec2(x, 0.0));
+        }
+    }
+
+    #[test]
+    fn deferred_position_changes_are_averaged() {
+        let mut particle_data = ParticleData::default();
+        let handles = particle_data.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+        ]);
+
+        particle_data.begin_deferred_position_changes();
+        particle_data.set_pos_from_vec2(handles[0], &vec2(1.0, 0.0));
+        particle_data.set_pos_from_vec2(handles[0], &vec2(0.0, 1.0));
+        assert_eq!(particle_data.get_pos_vec2(handles[0]), vec2(0.0, 0.0));
+
+        particle_data.apply_deferred_position_changes(1.0);
+        assert_eq!(particle_data.get_pos_vec2(handles[0]), vec2(0.5, 0.5));
+    }
+}
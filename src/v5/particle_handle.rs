@@ -1,6 +1,6 @@
 use std::usize;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ParticleHandle {
     id: usize,
 }
@@ -17,6 +17,17 @@ impl ParticleHandle {
     pub fn offset(&mut self, offset: u64) {
         self.id = (self.id as u64 + offset) as usize;
     }
+
+    /// Replace this handle with remap[id]. Returns false (leaving the handle unchanged) if that is None
+    pub fn remap(&mut self, remap: &Vec<Option<ParticleHandle>>) -> bool {
+        match remap.get(self.id).copied().flatten() {
+            Some(particle_handle) => {
+                *self = particle_handle;
+                true
+            },
+            None => false,
+        }
+    }
 }
 
 impl Default for ParticleHandle {
@@ -27,7 +38,10 @@ impl Default for ParticleHandle {
     }
 }
 
-//pub type ConstraintHandle = ParticleHandle;
+pub type ConstraintHandle = ParticleHandle;
+pub type BodyHandle = ParticleHandle;
+pub type SensorHandle = ParticleHandle;
+pub type StaticColliderHandle = ParticleHandle;
 /* 
 pub type StickHandle = ParticleHandle;
 pub type SpringHandle = ParticleHandle;
@@ -0,0 +1,314 @@
+use bevy::math::Vec2;
+
+use super::{particle::COLLISION_MASK_ALL, particle_handle::{ConstraintHandle, ParticleHandle}, particle_system::ParticleSystem};
+
+/// Do the line segments a1 -> a2 and b1 -> b2 cross each other
+pub fn line_segments_intersect(a1: Vec2, a2: Vec2, b1: Vec2, b2: Vec2) -> bool {
+    let a = a2 - a1;
+    let b = b2 - b1;
+    let denominator = a.perp_dot(b);
+    if denominator.abs() <= f32::EPSILON {
+        // parallel, treat as not crossing
+        return false;
+    }
+
+    let offset = b1 - a1;
+    let t = offset.perp_dot(b) / denominator;
+    let u = offset.perp_dot(a) / denominator;
+    t >= 0.0 && t <= 1.0 && u >= 0.0 && u <= 1.0
+}
+
+/**
+ * Utility class to help with particle manipulation.
+ *
+ * Tools either take a set of particle handles, or a region in which case they
+ * apply to all enabled dynamic particles in the region.
+ *
+ * Forces are reset each pre_update, so tools that add forces need to be applied every frame.
+ * Tools that change velocity (impulses, damping) only need to be applied once.
+ */
+pub struct ParticleManipulator {
+
+}
+
+impl ParticleManipulator {
+    pub fn new() -> Self {
+        Self{}
+    }
+
+    /// Find all enabled dynamic particles whose centre is within radius of pos, nearest first.
+    /// Uses the ParticleSystem spatial query, so particles added since the last update are found too.
+    pub fn find_dynamic_particles_in_circle(&self, particle_system: &mut ParticleSystem, pos: Vec2, radius: f32) -> Vec<ParticleHandle> {
+        particle_system.query_k_nearest(pos, usize::MAX, radius, COLLISION_MASK_ALL).into_iter()
+            .filter(|particle_handle| !particle_system.particle_data.is_static(*particle_handle))
+            .collect()
+    }
+
+    /// Find the enabled dynamic particle closest to pos, within max_distance of its centre
+    pub fn find_nearest_dynamic_particle(&self, particle_system: &mut ParticleSystem, pos: Vec2, max_distance: f32) -> Option<ParticleHandle> {
+        self.find_dynamic_particles_in_circle(particle_system, pos, max_distance).first().copied()
+    }
+
+    /// Push each particle around pos with a force at 90 degrees to the direction to pos.
+    /// Positive force_magnitude spins counter clockwise.
+    pub fn add_rotational_force_around_point(&self, particle_system: &mut ParticleSystem, particle_handles: &Vec<ParticleHandle>, pos: Vec2, force_magnitude: f32) {
+        for particle_handle in particle_handles.iter() {
+            let delta = particle_system.particle_data.get_pos_vec2(*particle_handle) - pos;
+            let force = delta.perp() * force_magnitude;
+            particle_system.particle_data.add_force(*particle_handle, &force);
+        }
+    }
+
+    /// Apply a torque (newton metres) to a set of particles around pos.
+    /// Unlike add_rotational_force_around_point, the total torque does not depend on the number of particles.
+    pub fn add_torque_around_point(&self, particle_system: &mut ParticleSystem, particle_handles: &Vec<ParticleHandle>, pos: Vec2, torque: f32) {
+        let length_squared_sum: f32 = particle_handles.iter()
+            .map(|handle| (particle_system.particle_data.get_pos_vec2(*handle) - pos).length_squared())
+            .sum();
+
+        if length_squared_sum <= f32::EPSILON {
+            return;
+        }
+
+        self.add_rotational_force_around_point(particle_system, particle_handles, pos, torque / length_squared_sum);
+    }
+
+    /// Instantly change the velocity of all dynamic particles within radius of pos, pushing them away from pos.
+    /// The impulse (newton seconds) falls off linearly to zero at radius.
+    /// A negative impulse pulls particles in (implode).
+    pub fn add_radial_impulse(&self, particle_system: &mut ParticleSystem, pos: Vec2, radius: f32, impulse: f32) {
+        let sub_step_seconds = particle_system.sub_step_seconds();
+
+        for particle_handle in self.find_dynamic_particles_in_circle(particle_system, pos, radius) {
+            let particle_pos = particle_system.particle_data.get_pos_vec2(particle_handle);
+            let delta = particle_pos - pos;
+            let distance = delta.length();
+            if distance <= f32::EPSILON {
+                continue;
+            }
+
+            let falloff = 1.0 - (distance / radius);
+            let delta_velocity = (delta / distance) * (impulse * falloff / particle_system.particle_data.get_mass(particle_handle));
+
+            let pos_prev = particle_system.particle_data.get_pos_prev_vec2(particle_handle) - delta_velocity * sub_step_seconds;
+            particle_system.particle_data.set_pos_prev_from_vec2(particle_handle, &pos_prev);
+        }
+    }
+
+    pub fn explode(&self, particle_system: &mut ParticleSystem, pos: Vec2, radius: f32, impulse: f32) {
+        debug_assert!(impulse >= 0.0);
+        self.add_radial_impulse(particle_system, pos, radius, impulse);
+    }
+
+    pub fn implode(&self, particle_system: &mut ParticleSystem, pos: Vec2, radius: f32, impulse: f32) {
+        debug_assert!(impulse >= 0.0);
+        self.add_radial_impulse(particle_system, pos, radius, -impulse);
+    }
+
+    /// Pull a particle towards target with a damped spring, eg. for grabbing particles with the mouse.
+    /// stiffness is in newtons per metre, damping in newton seconds per metre.
+    pub fn add_spring_force_to_target(&self, particle_system: &mut ParticleSystem, particle_handle: ParticleHandle, target: Vec2, stiffness: f32, damping: f32) {
+        if particle_system.particle_data.is_static(particle_handle) || !particle_system.particle_data.is_enabled(particle_handle) {
+            return;
+        }
+
+        let pos = particle_system.particle_data.get_pos_vec2(particle_handle);
+        let velocity = particle_system.compute_particle_velocity(particle_handle);
+        let force = (target - pos) * stiffness - velocity * damping;
+        particle_system.particle_data.add_force(particle_handle, &force);
+    }
+
+    /// Reduce the velocity of all dynamic particles within radius of pos.
+    /// damping is the fraction of velocity removed, 0 = no change, 1 = stop dead.
+    pub fn damp_velocity_in_circle(&self, particle_system: &mut ParticleSystem, pos: Vec2, radius: f32, damping: f32) {
+        debug_assert!(damping >= 0.0 && damping <= 1.0);
+
+        for particle_handle in self.find_dynamic_particles_in_circle(particle_system, pos, radius) {
+            let particle_pos = particle_system.particle_data.get_pos_vec2(particle_handle);
+            let movement = particle_pos - particle_system.particle_data.get_pos_prev_vec2(particle_handle);
+            let pos_prev = particle_pos - movement * (1.0 - damping);
+            particle_system.particle_data.set_pos_prev_from_vec2(particle_handle, &pos_prev);
+        }
+    }
+
+    /// Disable every enabled constraint that has a link crossing the line segment from a to b.
+    /// The cut particles collide with each other again. Returns the constraints that were cut.
+    pub fn cut_constraints_crossing_line_segment(&self, particle_system: &mut ParticleSystem, a: Vec2, b: Vec2) -> Vec<ConstraintHandle> {
+        let particle_data = &particle_system.particle_data;
+        let cut_constraint_handles = particle_system.constraint_container.constraints.iter().enumerate()
+            .filter(|(_, constraint)| constraint.is_enabled())
+            .filter(|(_, constraint)| constraint.particle_links().iter().any(|[handle_a, handle_b]| {
+                line_segments_intersect(a, b, particle_data.get_pos_vec2(*handle_a), particle_data.get_pos_vec2(*handle_b))
+            }))
+            .map(|(id, _)| ConstraintHandle::new(id))
+            .collect::<Vec<ConstraintHandle>>();
+
+        for constraint_handle in cut_constraint_handles.iter() {
+            particle_system.set_constraint_enabled(*constraint_handle, false);
+        }
+
+        cut_constraint_handles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::simd::f32x2;
+
+    use bevy::math::vec2;
+
+    use crate::v5::{constraints::{constraint::Constraint, stick_constraint::StickConstraint}, particle::Particle};
+
+    use super::*;
+
+    fn particle_system_without_gravity() -> ParticleSystem {
+        let mut particle_system = ParticleSystem::default();
+        particle_system.set_gravity(f32x2::splat(0.0));
+        particle_system
+    }
+
+    #[test]
+    fn segments_intersect() {
+        assert!(line_segments_intersect(vec2(-1.0, 0.0), vec2(1.0, 0.0), vec2(0.0, -1.0), vec2(0.0, 1.0)));
+        assert!(!line_segments_intersect(vec2(-1.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 0.5), vec2(0.0, 1.0)));
+        assert!(!line_segments_intersect(vec2(-1.0, 0.0), vec2(1.0, 0.0), vec2(-1.0, 1.0), vec2(1.0, 1.0)));
+    }
+
+    #[test]
+    fn find_nearest_dynamic_particle() {
+        let mut particle_system = particle_system_without_gravity();
+        let handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+            *Particle::default().set_position(vec2(3.0, 0.0)),
+        ]);
+
+        // found straight after being added, without waiting for a collision pass
+        let manipulator = ParticleManipulator::new();
+        assert_eq!(manipulator.find_nearest_dynamic_particle(&mut particle_system, vec2(2.5, 0.0), 1.0), Some(handles[1]));
+        assert_eq!(manipulator.find_nearest_dynamic_particle(&mut particle_system, vec2(0.2, 0.1), 1.0), Some(handles[0]));
+        assert_eq!(manipulator.find_nearest_dynamic_particle(&mut particle_system, vec2(1.5, 5.0), 1.0), None);
+
+        // static particles are skipped, even when closer
+        let static_handles = particle_system.add_particles(&vec![*Particle::default().set_position(vec2(2.4, 0.0)).set_static(true)]);
+        assert_eq!(manipulator.find_nearest_dynamic_particle(&mut particle_system, vec2(2.5, 0.0), 1.0), Some(handles[1]));
+        assert_eq!(manipulator.find_dynamic_particles_in_circle(&mut particle_system, vec2(2.5, 0.0), 3.0), vec![handles[1], handles[0]]);
+        assert!(!manipulator.find_dynamic_particles_in_circle(&mut particle_system, vec2(2.5, 0.0), 3.0).contains(&static_handles[0]));
+    }
+
+    #[test]
+    fn rotational_force_and_torque() {
+        let mut particle_system = particle_system_without_gravity();
+        let handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(1.0, 0.0)),
+            *Particle::default().set_position(vec2(-1.0, 0.0)),
+        ]);
+        particle_system.pre_update();
+
+        let manipulator = ParticleManipulator::new();
+        manipulator.add_torque_around_point(&mut particle_system, &handles, vec2(0.0, 0.0), 4.0);
+
+        // r x F summed over the particles equals the torque
+        let dynamic_particles = &particle_system.particle_data.dynamic_particles;
+        let torque: f32 = (0..2).map(|i| {
+            let force = dynamic_particles.force[i];
+            dynamic_particles.get_pos_vec2(i).perp_dot(vec2(force[0], force[1]))
+        }).sum();
+        assert!((torque - 4.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn explode_and_implode() {
+        let mut particle_system = particle_system_without_gravity();
+        let handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(1.0, 0.0)),
+            *Particle::default().set_position(vec2(10.0, 0.0)),
+        ]);
+
+        let manipulator = ParticleManipulator::new();
+        manipulator.explode(&mut particle_system, vec2(0.0, 0.0), 2.0, 1.0);
+        assert!(particle_system.compute_particle_velocity(handles[0]).x > 0.0);
+        assert_eq!(particle_system.compute_particle_velocity(handles[1]), vec2(0.0, 0.0));
+
+        manipulator.implode(&mut particle_system, vec2(0.0, 0.0), 2.0, 2.0);
+        assert!(particle_system.compute_particle_velocity(handles[0]).x < 0.0);
+    }
+
+    #[test]
+    fn spring_force_to_target() {
+        let mut particle_system = particle_system_without_gravity();
+        let handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+        ]);
+        particle_system.pre_update();
+
+        let manipulator = ParticleManipulator::new();
+        manipulator.add_spring_force_to_target(&mut particle_system, handles[0], vec2(2.0, 0.0), 10.0, 1.0);
+
+        let force = particle_system.particle_data.dynamic_particles.force[0];
+        assert_eq!(force[0], 20.0);
+        assert_eq!(force[1], 0.0);
+    }
+
+    #[test]
+    fn damp_velocity_in_circle() {
+        let mut particle_system = particle_system_without_gravity();
+        let handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+            *Particle::default().set_position(vec2(10.0, 0.0)),
+        ]);
+        for handle in handles.iter() {
+            particle_system.particle_data.set_pos_prev_from_vec2(*handle, &(particle_system.particle_data.get_pos_vec2(*handle) - vec2(0.1, 0.0)));
+        }
+
+        let manipulator = ParticleManipulator::new();
+        manipulator.damp_velocity_in_circle(&mut particle_system, vec2(0.0, 0.0), 1.0, 0.5);
+
+        let damped = particle_system.compute_particle_velocity(handles[0]);
+        let undamped = particle_system.compute_particle_velocity(handles[1]);
+        assert!((damped.x - undamped.x * 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn cut_constraints_crossing_line_segment() {
+        let mut particle_system = particle_system_without_gravity();
+        let handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+            *Particle::default().set_position(vec2(2.0, 0.0)),
+            *Particle::default().set_position(vec2(4.0, 0.0)),
+        ]);
+        particle_system.add_constraint(StickConstraint::default().set_particle_handles([handles[0], handles[1]]).set_length(2.0).box_clone());
+        particle_system.add_constraint(StickConstraint::default().set_particle_handles([handles[1], handles[2]]).set_length(2.0).box_clone());
+
+        let manipulator = ParticleManipulator::new();
+        let cut = manipulator.cut_constraints_crossing_line_segment(&mut particle_system, vec2(3.0, -1.0), vec2(3.0, 1.0));
+        assert_eq!(cut, vec![ConstraintHandle::new(1)]);
+        assert!(particle_system.constraint_container.constraints[0].is_enabled());
+        assert!(!particle_system.constraint_container.constraints[1].is_enabled());
+
+        // cutting again does nothing as the constraint is already disabled
+        let cut = manipulator.cut_constraints_crossing_line_segment(&mut particle_system, vec2(3.0, -1.0), vec2(3.0, 1.0));
+        assert!(cut.is_empty());
+    }
+
+    #[test]
+    fn cut_particles_collide_again() {
+        let mut particle_system = particle_system_without_gravity();
+        let handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+            *Particle::default().set_position(vec2(0.5, 0.0)),
+        ]);
+        particle_system.add_constraint(StickConstraint::default().set_particle_handles([handles[0], handles[1]]).set_length(0.5).set_exclude_collisions(true).box_clone());
+
+        // while joined the overlapping particles pass through each other
+        particle_system.solve_collisions();
+        assert_eq!(particle_system.particle_data.get_pos_vec2(handles[1]), vec2(0.5, 0.0));
+
+        let manipulator = ParticleManipulator::new();
+        let cut = manipulator.cut_constraints_crossing_line_segment(&mut particle_system, vec2(0.25, -1.0), vec2(0.25, 1.0));
+        assert_eq!(cut.len(), 1);
+
+        particle_system.solve_collisions();
+        let distance = (particle_system.particle_data.get_pos_vec2(handles[1]) - particle_system.particle_data.get_pos_vec2(handles[0])).length();
+        assert!(distance > 0.5);
+    }
+}
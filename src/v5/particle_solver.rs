@@ -5,4 +5,10 @@ pub fn compute_movement_weight(a_is_static: bool, b_is_static: bool) -> (f32, f3
     let a_movement_weight = if a_is_static { 0.0f32 } else if b_is_static { 1.0f32 } else { 0.5f32 };
     let b_movement_weight = 1.0f32 - a_movement_weight;
     (a_movement_weight, b_movement_weight)
+}
+
+/// Two particles collide only if each one's group is in the others mask
+#[inline(always)]
+pub fn should_collide(a_group: u32, a_mask: u32, b_group: u32, b_mask: u32) -> bool {
+    (a_group & b_mask) != 0 && (b_group & a_mask) != 0
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 use std::simd::f32x2;
 
-use super::{particle::Particle, particle_data::ParticleData, particle_handle::ParticleHandle, particle_vec::ParticleVec, spatial_hash_simd_particle_solver::SpatialHashSimdParticleSolver};
+use bevy::math::{bounding::Aabb2d, Vec2};
+
+use super::{body_container::{body_handle_to_body_id, Body, BodyContainer}, constraints::shape_matching_constraint::compute_centre_of_mass, particle_handle::BodyHandle, constraint_container::ConstraintContainer, constraint_solver::ConstraintSolver, constraints::constraint::Constraint, particle::Particle, particle_data::ParticleData, particle_handle::{ConstraintHandle, ParticleHandle}, particle_vec::ParticleVec, particle_handle::{SensorHandle, StaticColliderHandle}, static_collider::{StaticCollider, StaticColliderContainer}, sensor_container::{Sensor, SensorContainer, SensorEvent}, spatial_hash_simd_particle_solver::SpatialHashSimdParticleSolver, spatial_query::{RaycastHit, SpatialQuery}, world_bounds::{WorldBounds, WorldBoundsEvent}};
 
 
 // dt = last frame elapsed time
@@ -19,6 +21,15 @@ fn range_substeps(delta_seconds: f32, desired_hertz: f32) -> Vec<f32> {
 pub struct ParticleSystem {
     pub particle_data: ParticleData,
     pub solver: SpatialHashSimdParticleSolver,
+    pub constraint_container: ConstraintContainer,
+    pub constraint_solver: ConstraintSolver,
+    pub body_container: BodyContainer,
+    pub spatial_query: SpatialQuery,
+    is_spatial_query_dirty: bool, // the spatial query is rebuilt on the next query
+    pub sensor_container: SensorContainer,
+    pub static_collider_container: StaticColliderContainer,
+    pub world_bounds: Option<WorldBounds>,
+    world_bounds_events: Vec<WorldBoundsEvent>,
     desired_hertz: f32,
     gravity: f32x2
 }
@@ -28,12 +39,230 @@ impl ParticleSystem {
     pub fn add_particles(&mut self, particles: &Vec<Particle>) -> Vec<ParticleHandle>{
         let mut handles = self.particle_data.add_particles(particles);
         self.solver.notify_particle_data_changed(&mut self.particle_data);
+        self.mark_spatial_query_dirty();
         handles
     }
 
+    pub fn add_constraint(&mut self, constraint: Box<dyn Constraint + Send + Sync>) -> ConstraintHandle {
+        for [particle_handle_a, particle_handle_b] in constraint.collision_exclusions() {
+            self.particle_data.add_collision_exclusion(particle_handle_a, particle_handle_b);
+        }
+        self.constraint_container.add(constraint)
+    }
+
+    /// Enable or disable a constraint. Only enabled constraints stop particles colliding (see Constraint::collision_exclusions),
+    /// so use this instead of Constraint::set_enabled when the constraint excludes collisions
+    pub fn set_constraint_enabled(&mut self, constraint_handle: ConstraintHandle, is_enabled: bool) {
+        let constraint = &mut self.constraint_container.constraints[constraint_handle.id()];
+        if constraint.is_enabled() == is_enabled {
+            return;
+        }
+
+        constraint.set_enabled(is_enabled);
+        for [particle_handle_a, particle_handle_b] in constraint.collision_exclusions() {
+            if is_enabled {
+                self.particle_data.add_collision_exclusion(particle_handle_a, particle_handle_b);
+            } else if !self.is_collision_excluded_by_constraint(particle_handle_a, particle_handle_b) {
+                self.particle_data.remove_collision_exclusion(particle_handle_a, particle_handle_b);
+            }
+        }
+    }
+
+    /// Does any enabled constraint exclude collisions between the 2 particles
+    fn is_collision_excluded_by_constraint(&self, particle_handle_a: ParticleHandle, particle_handle_b: ParticleHandle) -> bool {
+        self.constraint_container.constraints.iter()
+            .filter(|constraint| constraint.is_enabled())
+            .any(|constraint| constraint.collision_exclusions().iter().any(|[handle_a, handle_b]| {
+                (*handle_a == particle_handle_a && *handle_b == particle_handle_b) || (*handle_a == particle_handle_b && *handle_b == particle_handle_a)
+            }))
+    }
+
+    /// Group particles and constraints into a body so they can be manipulated together.
+    /// Each particle is tagged with the body id, so Particle::collides_with_self applies to this body.
+    pub fn add_body(&mut self, particle_handles: Vec<ParticleHandle>, constraint_handles: Vec<ConstraintHandle>) -> BodyHandle {
+        let body_handle = self.body_container.add(Body::new(particle_handles, constraint_handles));
+        let body_id = body_handle_to_body_id(body_handle);
+        for particle_handle in self.body_container.bodies[body_handle.id()].particle_handles.iter() {
+            self.particle_data.set_body_id(*particle_handle, body_id);
+        }
+        body_handle
+    }
+
+    pub fn get_body(&self, body_handle: BodyHandle) -> Option<&Body> {
+        self.body_container.get(body_handle)
+    }
+
+    /// The time step of a single sub step. Particle velocities are stored as the movement over one of these
+    pub fn sub_step_seconds(&self) -> f32 {
+        1.0 / self.desired_hertz
+    }
+
+    fn body_particle_handles(&self, body_handle: BodyHandle) -> Vec<ParticleHandle> {
+        self.body_container.get(body_handle).map_or(vec![], |body| body.particle_handles.clone())
+    }
+
+    /// Move every particle in the body by offset, without changing its velocity
+    pub fn translate_body(&mut self, body_handle: BodyHandle, offset: Vec2) {
+        for particle_handle in self.body_particle_handles(body_handle) {
+            let pos = self.particle_data.get_pos_vec2(particle_handle) + offset;
+            let pos_prev = self.particle_data.get_pos_prev_vec2(particle_handle) + offset;
+            self.particle_data.set_pos_from_vec2(particle_handle, &pos);
+            self.particle_data.set_pos_prev_from_vec2(particle_handle, &pos_prev);
+        }
+        self.solver.notify_particle_data_changed(&mut self.particle_data);
+    }
+
+    /// Rotate every particle in the body by radians around its centre of mass, rotating the velocity with it
+    pub fn rotate_body(&mut self, body_handle: BodyHandle, radians: f32) {
+        let centre = self.compute_body_centre_of_mass(body_handle);
+        let rotation = Vec2::from_angle(radians);
+        for particle_handle in self.body_particle_handles(body_handle) {
+            let pos = centre + rotation.rotate(self.particle_data.get_pos_vec2(particle_handle) - centre);
+            let pos_prev = centre + rotation.rotate(self.particle_data.get_pos_prev_vec2(particle_handle) - centre);
+            self.particle_data.set_pos_from_vec2(particle_handle, &pos);
+            self.particle_data.set_pos_prev_from_vec2(particle_handle, &pos_prev);
+        }
+        self.solver.notify_particle_data_changed(&mut self.particle_data);
+    }
+
+    /// Set the velocity (in metres per second) of every dynamic particle in the body
+    pub fn set_body_velocity(&mut self, body_handle: BodyHandle, velocity: Vec2) {
+        let movement = velocity * self.sub_step_seconds();
+        for particle_handle in self.body_particle_handles(body_handle) {
+            if self.particle_data.is_static(particle_handle) {
+                continue;
+            }
+
+            let pos_prev = self.particle_data.get_pos_vec2(particle_handle) - movement;
+            self.particle_data.set_pos_prev_from_vec2(particle_handle, &pos_prev);
+        }
+    }
+
+    /// Apply a torque (newton metres, positive = counter clockwise) around the body's centre of mass.
+    /// Like gravity, forces are reset each pre_update so this needs to be called every frame.
+    pub fn apply_body_torque(&mut self, body_handle: BodyHandle, torque: f32) {
+        let centre = self.compute_body_centre_of_mass(body_handle);
+        let particle_handles = self.body_particle_handles(body_handle);
+
+        // spread the torque so the sum of r x F for all particles equals the torque
+        let mut length_squared_sum = 0.0;
+        for particle_handle in particle_handles.iter() {
+            if !self.particle_data.is_static(*particle_handle) {
+                length_squared_sum += (self.particle_data.get_pos_vec2(*particle_handle) - centre).length_squared();
+            }
+        }
+
+        if length_squared_sum <= f32::EPSILON {
+            return;
+        }
+
+        for particle_handle in particle_handles.iter() {
+            if self.particle_data.is_static(*particle_handle) {
+                continue;
+            }
+
+            let delta = self.particle_data.get_pos_vec2(*particle_handle) - centre;
+            let force = delta.perp() * (torque / length_squared_sum);
+            self.particle_data.add_force(*particle_handle, &force);
+        }
+    }
+
+    /// Enable or disable all the particles and constraints in the body
+    pub fn set_body_enabled(&mut self, body_handle: BodyHandle, is_enabled: bool) {
+        let Some(body) = self.body_container.get_mut(body_handle) else { return };
+        body.is_enabled = is_enabled;
+
+        for particle_handle in body.particle_handles.iter() {
+            self.particle_data.set_enabled(*particle_handle, is_enabled);
+        }
+
+        let constraint_handles = body.constraint_handles.clone();
+        for constraint_handle in constraint_handles {
+            self.set_constraint_enabled(constraint_handle, is_enabled);
+        }
+
+        self.solver.notify_particle_data_changed(&mut self.particle_data);
+        self.mark_spatial_query_dirty();
+    }
+
+    /// Remove a body from the simulation.
+    /// Its particles and constraints are disabled and the body no longer refers to them.
+    /// Handles stay valid (and are not reused), so anything else holding them will see disabled particles.
+    pub fn remove_body(&mut self, body_handle: BodyHandle) {
+        self.set_body_enabled(body_handle, false);
+
+        let Some(body) = self.body_container.get_mut(body_handle) else { return };
+        for particle_handle in body.particle_handles.iter() {
+            self.particle_data.set_body_id(*particle_handle, 0);
+        }
+        body.particle_handles.clear();
+        body.constraint_handles.clear();
+        body.is_removed = true;
+    }
+
+    pub fn compute_body_centre_of_mass(&self, body_handle: BodyHandle) -> Vec2 {
+        let particle_handles = self.body_particle_handles(body_handle);
+        let positions = particle_handles.iter().map(|handle| self.particle_data.get_pos_vec2(*handle)).collect::<Vec<Vec2>>();
+        let masses = particle_handles.iter().map(|handle| self.particle_data.get_mass(*handle)).collect::<Vec<f32>>();
+        compute_centre_of_mass(&positions, &masses)
+    }
+
+    /// The mass weighted average velocity of the body, in metres per second
+    pub fn compute_body_linear_velocity(&self, body_handle: BodyHandle) -> Vec2 {
+        let particle_handles = self.body_particle_handles(body_handle);
+        let velocities = particle_handles.iter().map(|handle| self.compute_particle_velocity(*handle)).collect::<Vec<Vec2>>();
+        let masses = particle_handles.iter().map(|handle| self.particle_data.get_mass(*handle)).collect::<Vec<f32>>();
+        compute_centre_of_mass(&velocities, &masses)
+    }
+
+    /// The angular velocity of the body around its centre of mass, in radians per second (positive = counter clockwise)
+    pub fn compute_body_angular_velocity(&self, body_handle: BodyHandle) -> f32 {
+        let centre = self.compute_body_centre_of_mass(body_handle);
+        let linear_velocity = self.compute_body_linear_velocity(body_handle);
+
+        let mut angular_momentum = 0.0;
+        let mut inertia = 0.0;
+        for particle_handle in self.body_particle_handles(body_handle) {
+            let mass = self.particle_data.get_mass(particle_handle);
+            let delta = self.particle_data.get_pos_vec2(particle_handle) - centre;
+            let relative_velocity = self.compute_particle_velocity(particle_handle) - linear_velocity;
+            angular_momentum += mass * delta.perp_dot(relative_velocity);
+            inertia += mass * delta.length_squared();
+        }
+
+        if inertia <= f32::EPSILON {
+            return 0.0;
+        }
+        angular_momentum / inertia
+    }
+
+    /// The bounding box of all particles in the body, including their radius
+    pub fn compute_body_aabb(&self, body_handle: BodyHandle) -> Option<Aabb2d> {
+        let mut aabb: Option<Aabb2d> = None;
+        for particle_handle in self.body_particle_handles(body_handle) {
+            let particle_aabb = self.particle_data.get(particle_handle)?.get_aabb();
+            aabb = Some(match aabb {
+                Some(aabb) => Aabb2d { min: aabb.min.min(particle_aabb.min), max: aabb.max.max(particle_aabb.max) },
+                None => particle_aabb,
+            });
+        }
+        aabb
+    }
+
+    /// The velocity of a particle in metres per second
+    pub fn compute_particle_velocity(&self, particle_handle: ParticleHandle) -> Vec2 {
+        (self.particle_data.get_pos_vec2(particle_handle) - self.particle_data.get_pos_prev_vec2(particle_handle)) / self.sub_step_seconds()
+    }
+
     pub fn solve_collisions(&mut self) {
         //self.solver.solve_collisions(&mut self.particle_data);
         self.solver.solve_collisions_6(&mut self.particle_data);
+        self.static_collider_container.solve_collisions(&mut self.particle_data);
+    }
+
+    pub fn set_gravity(&mut self, gravity: f32x2) -> &mut Self {
+        self.gravity = gravity;
+        self
     }
 
     pub fn pre_update(&mut self) {
@@ -41,6 +270,12 @@ impl ParticleSystem {
     }
 
     pub fn update(&mut self, delta_seconds: f32) {
+        self.update_with(delta_seconds, |_, _| {});
+    }
+
+    /// Like update, but calls before_sub_step with the sub step time before each sub step,
+    /// eg. to apply forces that depend on the particles' current positions and velocities
+    pub fn update_with<F: FnMut(&mut ParticleSystem, f32)>(&mut self, delta_seconds: f32, mut before_sub_step: F) {
         // disable sub steps for now, so we can see each frame
         //self.update_step(delta_seconds);
  
@@ -56,9 +291,119 @@ impl ParticleSystem {
             return;
         }
 
+        self.world_bounds_events.clear();
+
         for sub_dt in range.iter() {
+            before_sub_step(self, *sub_dt);
             self.update_step(*sub_dt);
         }
+
+        self.mark_spatial_query_dirty();
+        self.update_sensors();
+    }
+
+    /// Limit the simulation to the world bounds, or None to remove the limits
+    pub fn set_world_bounds(&mut self, world_bounds: Option<WorldBounds>) -> &mut Self {
+        self.world_bounds = world_bounds;
+        self
+    }
+
+    /// Particles that left the world during the last update, through a side with the Disable or EventOnly behaviour
+    pub fn world_bounds_events(&self) -> &Vec<WorldBoundsEvent> {
+        &self.world_bounds_events
+    }
+
+    /// Apply the world bounds to all dynamic particles. This happens automatically at the end of each sub step
+    pub fn apply_world_bounds(&mut self) {
+        let Some(world_bounds) = &self.world_bounds else { return };
+        world_bounds.apply(&mut self.particle_data, &mut self.world_bounds_events);
+    }
+
+    /// Add a non-particle static surface that dynamic particles collide with
+    pub fn add_static_collider(&mut self, static_collider: StaticCollider) -> StaticColliderHandle {
+        self.static_collider_container.add(static_collider)
+    }
+
+    /// Add a sensor region. Sensors do not collide, but report particles entering and exiting them each update
+    pub fn add_sensor(&mut self, sensor: Sensor) -> SensorHandle {
+        self.sensor_container.add(sensor)
+    }
+
+    /// Enter, stay and exit events from the last update
+    pub fn sensor_events(&self) -> &Vec<SensorEvent> {
+        &self.sensor_container.events
+    }
+
+    /// Check which particles are inside each sensor and generate events. This happens automatically at the end of each update
+    pub fn update_sensors(&mut self) {
+        if self.sensor_container.len() == 0 {
+            return;
+        }
+
+        self.refresh_spatial_query();
+        self.sensor_container.update(&self.particle_data, &self.spatial_query);
+    }
+
+    /// Rebuild the spatial hash used by the query_* functions on the next query.
+    /// This happens automatically after each update and when particles are added or enabled/disabled,
+    /// but not when particles are moved directly (eg. translate_body).
+    pub fn mark_spatial_query_dirty(&mut self) {
+        self.is_spatial_query_dirty = true;
+    }
+
+    /// Rebuild the spatial hash used by the query_* functions if it is out of date
+    pub fn refresh_spatial_query(&mut self) {
+        if self.is_spatial_query_dirty {
+            self.rebuild_spatial_query();
+        }
+    }
+
+    /// Rebuild the spatial hash used by the query_* functions now
+    pub fn rebuild_spatial_query(&mut self) {
+        self.spatial_query.rebuild(&self.particle_data);
+        self.is_spatial_query_dirty = false;
+    }
+
+    /// Enabled particles overlapping the aabb whose collision group matches layer_mask
+    pub fn query_aabb(&mut self, aabb: Aabb2d, layer_mask: u32) -> Vec<ParticleHandle> {
+        self.refresh_spatial_query();
+        self.spatial_query.query_aabb(&self.particle_data, aabb, layer_mask)
+    }
+
+    /// Enabled particles overlapping the circle whose collision group matches layer_mask
+    pub fn query_circle(&mut self, centre: Vec2, radius: f32, layer_mask: u32) -> Vec<ParticleHandle> {
+        self.refresh_spatial_query();
+        self.spatial_query.query_circle(&self.particle_data, centre, radius, layer_mask)
+    }
+
+    /// Enabled particles containing point whose collision group matches layer_mask
+    pub fn query_point(&mut self, point: Vec2, layer_mask: u32) -> Vec<ParticleHandle> {
+        self.refresh_spatial_query();
+        self.spatial_query.query_point(&self.particle_data, point, layer_mask)
+    }
+
+    /// Up to k enabled particles nearest to point (by centre, within max_distance) whose collision group matches layer_mask, nearest first
+    pub fn query_k_nearest(&mut self, point: Vec2, k: usize, max_distance: f32, layer_mask: u32) -> Vec<ParticleHandle> {
+        self.refresh_spatial_query();
+        self.spatial_query.query_k_nearest(&self.particle_data, point, k, max_distance, layer_mask)
+    }
+
+    /// The first enabled particle whose collision group matches layer_mask hit by a ray from origin along dir
+    pub fn raycast(&mut self, origin: Vec2, dir: Vec2, max_distance: f32, layer_mask: u32) -> Option<RaycastHit> {
+        self.refresh_spatial_query();
+        self.spatial_query.raycast(&self.particle_data, origin, dir, max_distance, layer_mask)
+    }
+
+    /// Every enabled particle whose collision group matches layer_mask hit by a ray from origin along dir, nearest first
+    pub fn raycast_all(&mut self, origin: Vec2, dir: Vec2, max_distance: f32, layer_mask: u32) -> Vec<RaycastHit> {
+        self.refresh_spatial_query();
+        self.spatial_query.raycast_all(&self.particle_data, origin, dir, max_distance, layer_mask)
+    }
+
+    /// The first enabled particle whose collision group matches layer_mask hit by a circle swept from origin along dir
+    pub fn circle_cast(&mut self, origin: Vec2, dir: Vec2, radius: f32, max_distance: f32, layer_mask: u32) -> Option<RaycastHit> {
+        self.refresh_spatial_query();
+        self.spatial_query.circle_cast(&self.particle_data, origin, dir, radius, max_distance, layer_mask)
     }
 
     pub fn update_step(&mut self, delta_seconds: f32) {
@@ -66,13 +411,11 @@ impl ParticleSystem {
 
         self.solve_collisions();
 
+        self.constraint_solver.update_constraints(&mut self.constraint_container, &mut self.particle_data, delta_seconds);
         self.particle_data.dynamic_particles.update_positions_3(delta_seconds);
         //self.particle_data.dynamic_particles.update_positions(delta_seconds);
-        /* 
-        self.constraint_solver.update_constraints(delta_seconds);
-        self.particle_solver.update_particle_positions(delta_seconds);
-        self.constraint_solver.post_update_constraints(delta_seconds);
-        */
+        self.constraint_solver.post_update_constraints(&mut self.constraint_container, &mut self.particle_data, delta_seconds);
+        self.apply_world_bounds();
     }
 }
 
@@ -81,8 +424,123 @@ impl Default for ParticleSystem {
         Self {
             particle_data: ParticleData::default(),
             solver: SpatialHashSimdParticleSolver::default(),
+            constraint_container: ConstraintContainer::default(),
+            constraint_solver: ConstraintSolver::default(),
+            body_container: BodyContainer::default(),
+            spatial_query: SpatialQuery::default(),
+            is_spatial_query_dirty: false,
+            sensor_container: SensorContainer::default(),
+            static_collider_container: StaticColliderContainer::default(),
+            world_bounds: None,
+            world_bounds_events: vec![],
             desired_hertz: 240.0,
             gravity: f32x2::from_array([0.0, -9.8]),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::constraints::{constraint::Constraint, stick_constraint::StickConstraint};
+
+    use super::*;
+
+    fn add_square_body(particle_system: &mut ParticleSystem) -> BodyHandle {
+        let particle_handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(-1.0, -1.0)),
+            *Particle::default().set_position(vec2(1.0, -1.0)),
+            *Particle::default().set_position(vec2(1.0, 1.0)),
+            *Particle::default().set_position(vec2(-1.0, 1.0)),
+        ]);
+        let constraint_handle = particle_system.add_constraint(StickConstraint::default().set_particle_handles([particle_handles[0], particle_handles[2]]).set_length(8.0_f32.sqrt()).box_clone());
+        particle_system.add_body(particle_handles, vec![constraint_handle])
+    }
+
+    #[test]
+    fn translate_and_rotate_body() {
+        let mut particle_system = ParticleSystem::default();
+        let body_handle = add_square_body(&mut particle_system);
+
+        particle_system.translate_body(body_handle, vec2(2.0, 0.0));
+        assert!((particle_system.compute_body_centre_of_mass(body_handle) - vec2(2.0, 0.0)).length() < 0.0001);
+
+        particle_system.rotate_body(body_handle, std::f32::consts::FRAC_PI_2);
+        let first_particle = particle_system.get_body(body_handle).unwrap().particle_handles[0];
+        assert!((particle_system.particle_data.get_pos_vec2(first_particle) - vec2(3.0, -1.0)).length() < 0.0001);
+
+        let aabb = particle_system.compute_body_aabb(body_handle).unwrap();
+        assert!((aabb.min - vec2(0.5, -1.5)).length() < 0.0001);
+        assert!((aabb.max - vec2(3.5, 1.5)).length() < 0.0001);
+
+        // moving the body does not give it any velocity
+        assert!(particle_system.compute_body_linear_velocity(body_handle).length() < 0.0001);
+    }
+
+    #[test]
+    fn set_body_velocity() {
+        let mut particle_system = ParticleSystem::default();
+        let body_handle = add_square_body(&mut particle_system);
+
+        particle_system.set_body_velocity(body_handle, vec2(3.0, 0.0));
+        assert!((particle_system.compute_body_linear_velocity(body_handle) - vec2(3.0, 0.0)).length() < 0.001);
+        assert!(particle_system.compute_body_angular_velocity(body_handle).abs() < 0.001);
+    }
+
+    #[test]
+    fn apply_body_torque() {
+        let mut particle_system = ParticleSystem::default();
+        particle_system.gravity = f32x2::splat(0.0);
+        let body_handle = add_square_body(&mut particle_system);
+
+        particle_system.pre_update();
+        particle_system.apply_body_torque(body_handle, 10.0);
+        particle_system.particle_data.dynamic_particles.update_positions_3(particle_system.sub_step_seconds());
+
+        assert!(particle_system.compute_body_angular_velocity(body_handle) > 0.0);
+        assert!(particle_system.compute_body_linear_velocity(body_handle).length() < 0.001);
+    }
+
+    #[test]
+    fn disable_and_remove_body() {
+        let mut particle_system = ParticleSystem::default();
+        let body_handle = add_square_body(&mut particle_system);
+
+        particle_system.set_body_enabled(body_handle, false);
+        assert_eq!(particle_system.particle_data.dynamic_particles.len(), 0);
+        assert_eq!(particle_system.particle_data.disabled_particles.len(), 4);
+        let stick = particle_system.constraint_container.get::<StickConstraint>(ConstraintHandle::new(0)).unwrap();
+        assert!(!stick.is_enabled);
+
+        particle_system.set_body_enabled(body_handle, true);
+        assert_eq!(particle_system.particle_data.dynamic_particles.len(), 4);
+
+        particle_system.remove_body(body_handle);
+        assert_eq!(particle_system.particle_data.dynamic_particles.len(), 0);
+        assert!(particle_system.get_body(body_handle).unwrap().is_removed);
+        assert!(particle_system.compute_body_aabb(body_handle).is_none());
+    }
+
+    #[test]
+    fn reenabled_body_keeps_collision_exclusions() {
+        let mut particle_system = ParticleSystem::default();
+        let particle_handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+            *Particle::default().set_position(vec2(0.5, 0.0)),
+        ]);
+        let constraint_handle = particle_system.add_constraint(StickConstraint::default().set_particle_handles([particle_handles[0], particle_handles[1]]).set_length(0.5).set_exclude_collisions(true).box_clone());
+        let body_handle = particle_system.add_body(particle_handles.clone(), vec![constraint_handle]);
+
+        particle_system.set_body_enabled(body_handle, false);
+        assert!(!particle_system.particle_data.has_collision_exclusion(particle_handles[0], particle_handles[1]));
+
+        particle_system.set_body_enabled(body_handle, true);
+        assert!(particle_system.particle_data.has_collision_exclusion(particle_handles[0], particle_handles[1]));
+        assert!(particle_system.particle_data.has_collision_exclusion(particle_handles[1], particle_handles[0]));
+
+        // the stick pair does not push apart once the body is back
+        particle_system.solve_collisions();
+        assert_eq!(particle_system.particle_data.get_pos_vec2(particle_handles[1]), vec2(0.5, 0.0));
+    }
+}
@@ -2,8 +2,9 @@ use std::{simd::{f32x2, f32x4, i32x2}, sync::{Arc, RwLock}};
 
 use std::simd::prelude::*;
 
-use super::{particle::Particle, particle_handle::ParticleHandle};
+use super::{particle::Particle, particle_handle::ParticleHandle, particle_solver::should_collide};
 use bevy::{color::Color, math::{vec2, Vec2}};
+use smallvec::SmallVec;
 
 
 /* 
@@ -51,6 +52,15 @@ pub struct ParticleVec {
     pub is_enabled: Vec<bool>,
 
     pub force: Vec<f32x2>, // should this be here? when we apply a force can we not just move the pos?
+
+    pub collision_group: Vec<u32>,
+    pub collision_mask: Vec<u32>,
+
+    pub body_id: Vec<u32>,
+    pub collides_with_self: Vec<bool>,
+    pub collision_exclusions: Vec<SmallVec<[ParticleHandle; 4]>>, // particles this particle never collides with, eg. particles joined by a stick
+
+    pub particle_handle: Vec<ParticleHandle>, // the handle that refers to each particle. ParticleData sets this to the global handle
 }
 
 impl ParticleVec {
@@ -67,10 +77,35 @@ impl ParticleVec {
         self.color.push(particle.color);
         self.is_enabled.push(particle.is_enabled);
         self.force.push(f32x2::from_array([particle.force.x, particle.force.y]));
+        self.collision_group.push(particle.collision_group);
+        self.collision_mask.push(particle.collision_mask);
+        self.body_id.push(particle.body_id);
+        self.collides_with_self.push(particle.collides_with_self);
+        self.collision_exclusions.push(SmallVec::new());
+        self.particle_handle.push(ParticleHandle::new(id));
 
         ParticleHandle::new(id) 
     }
 
+    /// Remove the particle at index id, replacing it with the last particle.
+    pub fn swap_remove(&mut self, id: usize) {
+        self.movement.swap_remove(id);
+        self.pos.swap_remove(id);
+        self.pos_prev.swap_remove(id);
+        self.radius.swap_remove(id);
+        self.mass.swap_remove(id);
+        self.is_static.swap_remove(id);
+        self.color.swap_remove(id);
+        self.is_enabled.swap_remove(id);
+        self.force.swap_remove(id);
+        self.collision_group.swap_remove(id);
+        self.collision_mask.swap_remove(id);
+        self.body_id.swap_remove(id);
+        self.collides_with_self.swap_remove(id);
+        self.collision_exclusions.swap_remove(id);
+        self.particle_handle.swap_remove(id);
+    }
+
     pub fn add_vec(&mut self, particles: &Vec<Particle>) -> Vec<ParticleHandle> {
         let mut handles = Vec::new();
         for p in particles {
@@ -113,6 +148,10 @@ impl ParticleVec {
             color: self.color[id], 
             is_enabled: self.is_enabled[id], 
             force: vec2(force[0], force[1]), 
+            collision_group: self.collision_group[id],
+            collision_mask: self.collision_mask[id],
+            body_id: self.body_id[id],
+            collides_with_self: self.collides_with_self[id],
         })
     }
 
@@ -120,6 +159,29 @@ impl ParticleVec {
         self.pos.len()
     }
 
+    /// Should the particle at index ai in this vec collide with the particle at index bi in other
+    #[inline(always)]
+    pub fn should_collide_with(&self, ai: usize, other: &ParticleVec, bi: usize) -> bool {
+        should_collide(self.collision_group[ai], self.collision_mask[ai], other.collision_group[bi], other.collision_mask[bi])
+    }
+
+    /// Is collision between the particle at index ai in this vec and the particle at index bi in other
+    /// excluded, either as they are in the same body and either has self collision disabled or they have an explicit exclusion.
+    /// The result does not depend on the order of the 2 particles
+    #[inline(always)]
+    pub fn is_collision_excluded(&self, ai: usize, other: &ParticleVec, bi: usize) -> bool {
+        let body_id = self.body_id[ai];
+        if body_id != 0 && body_id == other.body_id[bi] && (!self.collides_with_self[ai] || !other.collides_with_self[bi]) {
+            return true;
+        }
+
+        // ParticleData::add_collision_exclusion adds each exclusion to both particles, so one list is enough
+        let exclusions = &self.collision_exclusions[ai];
+        let is_excluded = !exclusions.is_empty() && exclusions.contains(&other.particle_handle[bi]);
+        debug_assert!(is_excluded == other.collision_exclusions[bi].contains(&self.particle_handle[ai]));
+        is_excluded
+    }
+
     /*
     pub fn update_positions(&mut self, delta_seconds: f32) {
         let delta_seconds_sqrd = delta_seconds * delta_seconds;
@@ -351,6 +413,15 @@ impl Default for ParticleVec {
             is_enabled: vec![],
 
             force: vec![],
+
+            collision_group: vec![],
+            collision_mask: vec![],
+
+            body_id: vec![],
+            collides_with_self: vec![],
+            collision_exclusions: vec![],
+
+            particle_handle: vec![],
         }
     }
 }
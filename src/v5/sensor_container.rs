@@ -0,0 +1,266 @@
+use bevy::{math::{bounding::{Aabb2d, BoundingVolume}, Vec2}, utils::HashSet};
+
+use super::{body_container::body_id_to_body_handle, particle_data::ParticleData, particle_handle::{BodyHandle, ParticleHandle, SensorHandle}, spatial_query::SpatialQuery};
+
+/// The region a sensor covers
+#[derive(Debug, Clone)]
+pub enum SensorShape {
+    Aabb(Aabb2d),
+    Circle { centre: Vec2, radius: f32 },
+    Polygon(Vec<Vec2>), // closed loop of points, either winding order
+}
+
+impl SensorShape {
+    pub fn compute_aabb(&self) -> Aabb2d {
+        match self {
+            SensorShape::Aabb(aabb) => *aabb,
+            SensorShape::Circle { centre, radius } => Aabb2d::new(*centre, Vec2::splat(*radius)),
+            SensorShape::Polygon(points) => {
+                let min = points.iter().fold(Vec2::splat(f32::MAX), |min, point| min.min(*point));
+                let max = points.iter().fold(Vec2::splat(f32::MIN), |max, point| max.max(*point));
+                Aabb2d { min, max }
+            },
+        }
+    }
+
+    /// Does a particle (a circle at pos with radius) overlap the shape
+    pub fn overlaps_particle(&self, pos: Vec2, radius: f32) -> bool {
+        match self {
+            SensorShape::Aabb(aabb) => (pos.clamp(aabb.min, aabb.max) - pos).length_squared() <= radius * radius,
+            SensorShape::Circle { centre, radius: sensor_radius } => (pos - *centre).length_squared() <= (radius + sensor_radius) * (radius + sensor_radius),
+            SensorShape::Polygon(points) => {
+                if points.len() < 3 {
+                    return false;
+                }
+
+                // even-odd rule for the centre, otherwise check if the particle overlaps an edge
+                let mut is_inside = false;
+                for i in 0..points.len() {
+                    let a = points[i];
+                    let b = points[(i + 1) % points.len()];
+
+                    if (a.y > pos.y) != (b.y > pos.y) && pos.x < a.x + (pos.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+                        is_inside = !is_inside;
+                    }
+
+                    let edge = b - a;
+                    let t = ((pos - a).dot(edge) / edge.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+                    if (a + edge * t - pos).length_squared() <= radius * radius {
+                        return true;
+                    }
+                }
+                is_inside
+            },
+        }
+    }
+}
+
+/// What a sensor reports events for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorReportMode {
+    /// One event per particle
+    Particles,
+
+    /// One event per body, where a body is in the sensor if any of its particles are.
+    /// Particles that are not in a body are reported individually.
+    Bodies,
+}
+
+/// The thing that entered, stayed in or exited a sensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SensorTarget {
+    Particle(ParticleHandle),
+    Body(BodyHandle),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorEventKind {
+    Enter, // was not in the sensor last update, is now
+    Stay, // was in the sensor last update and still is
+    Exit, // was in the sensor last update, is not now
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SensorEvent {
+    pub sensor_handle: SensorHandle,
+    pub target: SensorTarget,
+    pub kind: SensorEventKind,
+}
+
+/// A region that particles pass through without colliding, reporting when they enter and exit.
+/// Eg. finish lines, checkpoints, kill zones and water volumes.
+#[derive(Debug, Clone)]
+pub struct Sensor {
+    pub shape: SensorShape,
+    pub layer_mask: u32, // only particles with (collision_group & layer_mask) != 0 are detected
+    pub report_mode: SensorReportMode,
+    pub is_enabled: bool,
+    pub targets: HashSet<SensorTarget>, // what was inside the sensor at the last update
+}
+
+impl Sensor {
+    pub fn new(shape: SensorShape) -> Self {
+        Self {
+            shape,
+            layer_mask: u32::MAX,
+            report_mode: SensorReportMode::Particles,
+            is_enabled: true,
+            targets: HashSet::default(),
+        }
+    }
+
+    pub fn set_layer_mask(&mut self, layer_mask: u32) -> &mut Self {
+        self.layer_mask = layer_mask;
+        self
+    }
+
+    pub fn set_report_mode(&mut self, report_mode: SensorReportMode) -> &mut Self {
+        self.report_mode = report_mode;
+        self
+    }
+
+    pub fn set_enabled(&mut self, is_enabled: bool) -> &mut Self {
+        self.is_enabled = is_enabled;
+        self
+    }
+
+    fn find_targets(&self, particle_data: &ParticleData, spatial_query: &SpatialQuery) -> HashSet<SensorTarget> {
+        let aabb = self.shape.compute_aabb().grow(Vec2::splat(0.001));
+
+        spatial_query.candidates(particle_data, aabb, self.layer_mask).into_iter()
+            .filter(|particle| self.shape.overlaps_particle(particle.pos, particle.radius))
+            .map(|particle| {
+                let body_handle = match self.report_mode {
+                    SensorReportMode::Particles => None,
+                    SensorReportMode::Bodies => body_id_to_body_handle(particle_data.get_body_id(particle.particle_handle)),
+                };
+                body_handle.map_or(SensorTarget::Particle(particle.particle_handle), SensorTarget::Body)
+            })
+            .collect()
+    }
+}
+
+pub struct SensorContainer {
+    pub sensors: Vec<Sensor>,
+    pub events: Vec<SensorEvent>, // events from the last update
+}
+
+impl SensorContainer {
+    pub fn add(&mut self, sensor: Sensor) -> SensorHandle {
+        let id = self.sensors.len();
+        self.sensors.push(sensor);
+        SensorHandle::new(id)
+    }
+
+    pub fn get(&self, sensor_handle: SensorHandle) -> Option<&Sensor> {
+        self.sensors.get(sensor_handle.id())
+    }
+
+    pub fn get_mut(&mut self, sensor_handle: SensorHandle) -> Option<&mut Sensor> {
+        self.sensors.get_mut(sensor_handle.id())
+    }
+
+    pub fn len(&self) -> usize {
+        self.sensors.len()
+    }
+
+    /// Replace events with the changes since the last update.
+    /// A disabled sensor reports everything inside it as exiting, and reports nothing until it is enabled again.
+    pub fn update(&mut self, particle_data: &ParticleData, spatial_query: &SpatialQuery) {
+        self.events.clear();
+
+        for (id, sensor) in self.sensors.iter_mut().enumerate() {
+            let sensor_handle = SensorHandle::new(id);
+            let targets = if sensor.is_enabled { sensor.find_targets(particle_data, spatial_query) } else { HashSet::default() };
+
+            for target in targets.iter() {
+                let kind = if sensor.targets.contains(target) { SensorEventKind::Stay } else { SensorEventKind::Enter };
+                self.events.push(SensorEvent { sensor_handle, target: *target, kind });
+            }
+
+            for target in sensor.targets.difference(&targets) {
+                self.events.push(SensorEvent { sensor_handle, target: *target, kind: SensorEventKind::Exit });
+            }
+
+            sensor.targets = targets;
+        }
+    }
+}
+
+impl Default for SensorContainer {
+    fn default() -> Self {
+        Self {
+            sensors: vec![],
+            events: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::{particle::Particle, particle_system::ParticleSystem};
+
+    use super::*;
+
+    fn events_for(particle_system: &ParticleSystem, target: SensorTarget) -> Vec<SensorEventKind> {
+        particle_system.sensor_events().iter().filter(|event| event.target == target).map(|event| event.kind).collect()
+    }
+
+    #[test]
+    fn polygon_overlaps_particle() {
+        let triangle = SensorShape::Polygon(vec![vec2(0.0, 0.0), vec2(4.0, 0.0), vec2(0.0, 4.0)]);
+        assert!(triangle.overlaps_particle(vec2(1.0, 1.0), 0.1));
+        assert!(triangle.overlaps_particle(vec2(2.0, -0.4), 0.5));
+        assert!(!triangle.overlaps_particle(vec2(3.0, 3.0), 0.5));
+    }
+
+    #[test]
+    fn enter_stay_exit() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(-5.0, 0.0)),
+            *Particle::default().set_position(vec2(-5.0, 3.0)).set_collision_group(0x2),
+        ]);
+        particle_system.add_sensor(Sensor::new(SensorShape::Aabb(Aabb2d { min: vec2(-1.0, -10.0), max: vec2(1.0, 10.0) })).set_layer_mask(0x1).clone());
+
+        let target = SensorTarget::Particle(handles[0]);
+        let offsets = [vec2(0.0, 0.0), vec2(5.0, 0.0), vec2(0.5, 0.0), vec2(5.0, 0.0)];
+        let expected = [vec![], vec![SensorEventKind::Enter], vec![SensorEventKind::Stay], vec![SensorEventKind::Exit]];
+
+        for (offset, expected) in offsets.iter().zip(expected.iter()) {
+            for handle in handles.iter() {
+                let pos = particle_system.particle_data.get_pos_vec2(*handle) + *offset;
+                particle_system.particle_data.set_pos_from_vec2(*handle, &pos);
+                particle_system.particle_data.set_pos_prev_from_vec2(*handle, &pos);
+            }
+            particle_system.rebuild_spatial_query();
+            particle_system.update_sensors();
+
+            assert_eq!(&events_for(&particle_system, target), expected);
+
+            // the other particle is filtered out by the layer mask
+            assert!(events_for(&particle_system, SensorTarget::Particle(handles[1])).is_empty());
+        }
+    }
+
+    #[test]
+    fn report_bodies() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+            *Particle::default().set_position(vec2(0.5, 0.0)),
+            *Particle::default().set_position(vec2(20.0, 0.0)),
+        ]);
+        let body_handle = particle_system.add_body(vec![handles[0], handles[1]], vec![]);
+        particle_system.add_sensor(Sensor::new(SensorShape::Circle { centre: vec2(0.0, 0.0), radius: 2.0 }).set_report_mode(SensorReportMode::Bodies).clone());
+
+        particle_system.update_sensors();
+        assert_eq!(particle_system.sensor_events(), &vec![SensorEvent {
+            sensor_handle: SensorHandle::new(0),
+            target: SensorTarget::Body(body_handle),
+            kind: SensorEventKind::Enter,
+        }]);
+    }
+}
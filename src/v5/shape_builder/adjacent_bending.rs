@@ -0,0 +1,100 @@
+use crate::v5::{constraints::{angle_constraint::{compute_bend_angle, AngleConstraint}, constraint::Constraint}, particle_handle::ParticleHandle};
+
+use super::shape_builder::{ShapeBuilder, ShapeBuilderOperation};
+
+/// Takes a chain of particles (like LineSegment or Circle output) and creates angle constraints
+/// between each run of 3 particles so the chain resists bending.
+/// The rest angle of each constraint is taken from the particle positions at the time this operation is applied.
+pub struct AdjacentBending {
+    constraint_template: AngleConstraint,
+    stride: usize,
+    wrap_around: bool
+}
+
+impl AdjacentBending {
+    pub fn new(constraint_template: AngleConstraint, stride: usize, wrap_around: bool) -> Self {
+        Self {
+            constraint_template,
+            stride,
+            wrap_around
+        }
+    }
+
+    fn add_constraint_to_shape_builder_from_particle_handles(&self, shape_builder: &mut ShapeBuilder, particle_handles: [ParticleHandle; 3]) {
+        let particle_a = shape_builder.particles[particle_handles[0].id()];
+        let particle_b = shape_builder.particles[particle_handles[1].id()];
+        let particle_c = shape_builder.particles[particle_handles[2].id()];
+        let rest_angle = compute_bend_angle(particle_a.pos, particle_b.pos, particle_c.pos);
+        let mut constraint = self.constraint_template;
+        constraint.set_particle_handles(particle_handles).set_rest_angle(rest_angle);
+        shape_builder.add_constraint(constraint.box_clone());
+    }
+}
+
+impl ShapeBuilderOperation for AdjacentBending {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let particle_count = shape_builder.particles.len();
+
+        // an angle needs 3 different particles, which wrapping a shorter chain cannot give
+        if particle_count < 3 {
+            return;
+        }
+
+        for pi in 0..particle_count {
+            let mut pi_mid = pi + self.stride;
+            let mut pi_next = pi + self.stride * 2;
+            if pi_next >= particle_count {
+                if !self.wrap_around {
+                    continue;
+                }
+
+                pi_mid %= particle_count;
+                pi_next %= particle_count;
+
+                // the stride wrapped back onto a particle already in this run
+                if pi_mid == pi || pi_next == pi || pi_next == pi_mid {
+                    continue;
+                }
+            }
+
+            let particle_handles = [
+                ParticleHandle::new(pi),
+                ParticleHandle::new(pi_mid),
+                ParticleHandle::new(pi_next)
+            ];
+            self.add_constraint_to_shape_builder_from_particle_handles(shape_builder, particle_handles);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::{constraints::angle_constraint::AngleConstraint, shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
+
+    use super::*;
+
+    #[test]
+    fn chain() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(LineSegment::new(vec2(0.0, 0.0), vec2(10.0, 0.0)))
+            .apply_operation(AdjacentBending::new(AngleConstraint::default(), 1, false));
+
+        assert_eq!(b.constraints.len(), 8);
+
+        let angle_constraint = b.constraints[0].as_any().downcast_ref::<AngleConstraint>().unwrap();
+        assert_eq!(angle_constraint.rest_angle, 0.0);
+    }
+
+    #[test]
+    fn wrap_around_short_chain() {
+        // 2 particles cannot make an angle, even when wrapping around
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(LineSegment::new(vec2(0.0, 0.0), vec2(2.0, 0.0)))
+            .apply_operation(AdjacentBending::new(AngleConstraint::default(), 1, true));
+
+        assert_eq!(b.particles.len(), 2);
+        assert_eq!(b.constraints.len(), 0);
+    }
+}
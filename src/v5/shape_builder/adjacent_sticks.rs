@@ -1,4 +1,4 @@
-use crate::v4::{constraints::{constraint::Constraint, stick_constraint::StickConstraint}, particle_handle::{self, ParticleHandle}};
+use crate::v5::{constraints::{constraint::Constraint, stick_constraint::StickConstraint}, particle_handle::ParticleHandle};
 
 use super::{circle::Circle, shape_builder::{ShapeBuilder, ShapeBuilderOperation}};
 
@@ -0,0 +1,226 @@
+use bevy::{color::Color, math::{bounding::Aabb2d, Vec2}};
+use sdl2::{image::LoadSurface, pixels::PixelFormatEnum, surface::Surface};
+
+use crate::v5::particle::Particle;
+
+use super::{packing::{pack_points_in_aabb, Packing}, shape_builder::{ShapeBuilder, ShapeBuilderOperation}};
+
+/// RGBA pixels, row by row starting from the top row, as stored in image files
+#[derive(Debug, Clone)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Bitmap {
+    pub fn new(width: usize, height: usize, pixels: Vec<[u8; 4]>) -> Self {
+        debug_assert!(pixels.len() == width * height);
+        Self { width, height, pixels }
+    }
+
+    /// Load an image file, eg. "assets/bitmaps/terrain_example.png", using SDL2_image
+    pub fn load(path: &str) -> Result<Self, String> {
+        let surface = Surface::from_file(path)?.convert_format(PixelFormatEnum::RGBA32)?;
+        let width = surface.width() as usize;
+        let height = surface.height() as usize;
+        let pitch = surface.pitch() as usize; // bytes per row, which may include padding
+
+        let pixels = surface.with_lock(|bytes: &[u8]| {
+            let mut pixels = Vec::with_capacity(width * height);
+            for y in 0..height {
+                for x in 0..width {
+                    let i = y * pitch + x * 4;
+                    pixels.push([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+                }
+            }
+            pixels
+        });
+
+        Ok(Self::new(width, height, pixels))
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// Pixels close to colour become particles made from particle_template
+#[derive(Debug, Clone)]
+pub struct BitmapColourMapping {
+    pub colour: Color,
+    pub particle_template: Particle,
+}
+
+/// Stamps particles where a bitmap is painted, so terrain and fluid pools can be drawn in a paint program.
+/// Particles are packed at the ShapeBuilder particle template radius, and each takes the pixel under its centre.
+///
+/// By default any pixel with an alpha of at least 128 becomes a particle made from the ShapeBuilder particle template.
+/// Adding colour mappings instead maps each pixel to the particle template of the closest mapped colour,
+/// eg. black to static terrain and blue to dynamic liquid. Pixels not close to any mapped colour are skipped.
+pub struct BitmapShape {
+    bitmap: Bitmap,
+    origin: Vec2, // position of the bottom left corner of the bitmap
+    scale: f32, // size of a pixel
+    packing: Packing,
+    alpha_threshold: u8,
+    colour_mappings: Vec<BitmapColourMapping>,
+    colour_tolerance: f32, // maximum distance between 2 colours in RGB (0 to 1 for each channel) for them to match
+    use_pixel_colour: bool,
+}
+
+impl BitmapShape {
+    pub fn new(bitmap: Bitmap, origin: Vec2, scale: f32) -> Self {
+        debug_assert!(scale > 0.0);
+        Self {
+            bitmap,
+            origin,
+            scale,
+            packing: Packing::Square,
+            alpha_threshold: 128,
+            colour_mappings: vec![],
+            colour_tolerance: 0.1,
+            use_pixel_colour: false,
+        }
+    }
+
+    pub fn with_packing(mut self, packing: Packing) -> Self {
+        self.packing = packing;
+        self
+    }
+
+    /// Pixels with a lower alpha are empty space
+    pub fn with_alpha_threshold(mut self, alpha_threshold: u8) -> Self {
+        self.alpha_threshold = alpha_threshold;
+        self
+    }
+
+    pub fn with_colour_mapping(mut self, colour: Color, particle_template: Particle) -> Self {
+        self.colour_mappings.push(BitmapColourMapping { colour, particle_template });
+        self
+    }
+
+    pub fn with_colour_tolerance(mut self, colour_tolerance: f32) -> Self {
+        self.colour_tolerance = colour_tolerance;
+        self
+    }
+
+    /// Colour each particle with the pixel under it, instead of the particle template colour
+    pub fn with_pixel_colour(mut self, use_pixel_colour: bool) -> Self {
+        self.use_pixel_colour = use_pixel_colour;
+        self
+    }
+
+    /// The pixel under pos, if pos is on the bitmap
+    fn get_pixel_at_position(&self, pos: Vec2) -> Option<[u8; 4]> {
+        let pixel_pos = ((pos - self.origin) / self.scale).floor();
+        if pixel_pos.x < 0.0 || pixel_pos.y < 0.0 || pixel_pos.x >= self.bitmap.width as f32 || pixel_pos.y >= self.bitmap.height as f32 {
+            return None;
+        }
+
+        // the bitmap starts from the top row, but y is up
+        Some(self.bitmap.get_pixel(pixel_pos.x as usize, self.bitmap.height - 1 - pixel_pos.y as usize))
+    }
+
+    /// The particle to place on a pixel, if any
+    fn get_particle_for_pixel(&self, pixel: [u8; 4], particle_template: &Particle) -> Option<Particle> {
+        if pixel[3] < self.alpha_threshold {
+            return None;
+        }
+
+        let pixel_colour = Color::srgba_u8(pixel[0], pixel[1], pixel[2], pixel[3]);
+
+        let mut particle = if self.colour_mappings.is_empty() {
+            *particle_template
+        } else {
+            let rgb = pixel_colour.to_srgba();
+            let (distance, mapping) = self.colour_mappings.iter()
+                .map(|mapping| {
+                    let mapping_rgb = mapping.colour.to_srgba();
+                    let distance = ((rgb.red - mapping_rgb.red).powi(2) + (rgb.green - mapping_rgb.green).powi(2) + (rgb.blue - mapping_rgb.blue).powi(2)).sqrt();
+                    (distance, mapping)
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0))?;
+
+            if distance > self.colour_tolerance {
+                return None;
+            }
+            mapping.particle_template
+        };
+
+        if self.use_pixel_colour {
+            particle.set_color(pixel_colour);
+        }
+        Some(particle)
+    }
+}
+
+impl ShapeBuilderOperation for BitmapShape {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let radius = shape_builder.particle_radius();
+        let particle_template = shape_builder.particle_template;
+
+        let size = Vec2::new(self.bitmap.width as f32, self.bitmap.height as f32) * self.scale;
+        let points = pack_points_in_aabb(Aabb2d { min: self.origin, max: self.origin + size }, radius, self.packing);
+
+        for pos in points {
+            let Some(pixel) = self.get_pixel_at_position(pos) else { continue };
+            if let Some(mut particle) = self.get_particle_for_pixel(pixel, &particle_template) {
+                particle.set_position(pos);
+                shape_builder.add_particle(particle);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{color::LinearRgba, math::vec2};
+
+    use super::*;
+
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const RED: [u8; 4] = [255, 0, 0, 255];
+
+    // 4 x 2 pixels. Top row: clear, blue, blue, red. Bottom row: black
+    fn bitmap() -> Bitmap {
+        Bitmap::new(4, 2, vec![CLEAR, BLUE, BLUE, RED, BLACK, BLACK, BLACK, BLACK])
+    }
+
+    #[test]
+    fn threshold() {
+        // 1 particle per pixel
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(BitmapShape::new(bitmap(), vec2(10.0, 0.0), 1.0).with_pixel_colour(true));
+
+        assert_eq!(b.particles.len(), 7);
+        assert_eq!(b.particles[0].pos, vec2(10.5, 0.5));
+        assert_eq!(b.particles[4].pos, vec2(11.5, 1.5));
+        assert_eq!(b.particles[4].color, Color::srgba_u8(0, 0, 255, 255));
+    }
+
+    #[test]
+    fn colour_mapping_and_scale() {
+        // 2 x 2 particles per pixel
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(BitmapShape::new(bitmap(), vec2(0.0, 0.0), 2.0)
+            .with_colour_mapping(Color::BLACK, *Particle::default().set_static(true))
+            .with_colour_mapping(Color::from(LinearRgba::BLUE), *Particle::default().set_mass(0.02)));
+
+        // the red pixel is not mapped
+        assert_eq!(b.particles.len(), 4 * 4 + 2 * 4);
+        assert_eq!(b.particles.iter().filter(|particle| particle.is_static).count(), 16);
+        assert_eq!(b.particles.iter().filter(|particle| particle.mass == 0.02).count(), 8);
+        assert!(b.particles.iter().filter(|particle| particle.mass == 0.02).all(|particle| particle.pos.y > 2.0 && particle.pos.x > 2.0 && particle.pos.x < 6.0));
+    }
+
+    #[test]
+    fn load_png() {
+        let bitmap = Bitmap::load("assets/bitmaps/terrain_example.png").unwrap();
+        assert_eq!((bitmap.width, bitmap.height), (16, 8));
+        assert_eq!(bitmap.get_pixel(0, 0)[3], 0);
+        assert_eq!(bitmap.get_pixel(0, 7), BLACK);
+    }
+}
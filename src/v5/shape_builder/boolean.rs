@@ -0,0 +1,101 @@
+use bevy::math::{Rect, Vec2};
+
+use super::{polygon::is_point_in_polygon, shape_builder::{ShapeBuilder, ShapeBuilderOperation}};
+
+/// A shape used to cut particles out of a ShapeBuilder
+#[derive(Debug, Clone)]
+pub enum BooleanShape {
+    Circle { centre: Vec2, radius: f32 },
+    Rectangle(Rect),
+
+    /// A simple polygon, concave is fine
+    Polygon(Vec<Vec2>),
+}
+
+impl BooleanShape {
+    pub fn contains_point(&self, pos: Vec2) -> bool {
+        match self {
+            BooleanShape::Circle { centre, radius } => (pos - *centre).length() <= *radius,
+            BooleanShape::Rectangle(rect) => rect.contains(pos),
+            BooleanShape::Polygon(points) => is_point_in_polygon(points, pos),
+        }
+    }
+}
+
+/// Removes the particles whose centre is inside (or outside) a shape, along with any constraints using them.
+/// eg. carve the mouth of a funnel out of a filled rectangle, or remove the liquid that is outside of its container.
+/// The shape is in the local space of the ShapeBuilder transform stack.
+pub struct RemoveParticles {
+    shape: BooleanShape,
+    remove_inside: bool,
+}
+
+impl RemoveParticles {
+    /// Subtract the shape
+    pub fn inside(shape: BooleanShape) -> Self {
+        Self { shape, remove_inside: true }
+    }
+
+    /// Intersect with the shape
+    pub fn outside(shape: BooleanShape) -> Self {
+        Self { shape, remove_inside: false }
+    }
+}
+
+impl ShapeBuilderOperation for RemoveParticles {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let world_to_local = shape_builder.transform().inverse();
+        shape_builder.remove_particles(|particle| self.shape.contains_point(world_to_local.transform_point2(particle.pos)) == self.remove_inside);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::{constraints::stick_constraint::StickConstraint, particle_handle::ParticleHandle, shape_builder::{adjacent_sticks::AdjacentSticks, line_segment::LineSegment, packing::Packing, rectangle::Rectangle}};
+
+    use super::*;
+
+    fn filled_square() -> ShapeBuilder {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Rectangle::from_corners(vec2(0.0, 0.0), vec2(4.0, 4.0)).with_packing(Packing::Square));
+        b
+    }
+
+    #[test]
+    fn subtract_circle() {
+        let mut b = filled_square();
+        b.apply_operation(RemoveParticles::inside(BooleanShape::Circle { centre: vec2(2.0, 2.0), radius: 1.0 }));
+
+        // the 4 middle particles are 0.707 from the centre
+        assert_eq!(b.particles.len(), 12);
+        assert!(b.particles.iter().all(|particle| (particle.pos - vec2(2.0, 2.0)).length() > 1.0));
+    }
+
+    #[test]
+    fn intersect_rectangle_and_polygon() {
+        let mut b = filled_square();
+        b.apply_operation(RemoveParticles::outside(BooleanShape::Rectangle(Rect::new(0.0, 0.0, 4.0, 2.0))));
+        assert_eq!(b.particles.len(), 8);
+
+        // a triangle covering the bottom left half
+        b.apply_operation(RemoveParticles::outside(BooleanShape::Polygon(vec![vec2(0.0, 0.0), vec2(4.5, 0.0), vec2(0.0, 4.5)])));
+        let positions = b.particles.iter().map(|particle| particle.pos).collect::<Vec<Vec2>>();
+        assert_eq!(positions, vec![vec2(0.5, 0.5), vec2(1.5, 0.5), vec2(2.5, 0.5), vec2(3.5, 0.5), vec2(0.5, 1.5), vec2(1.5, 1.5), vec2(2.5, 1.5)]);
+    }
+
+    #[test]
+    fn constraints_follow_remaining_particles() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(LineSegment::new(vec2(0.0, 0.0), vec2(5.0, 0.0)))
+            .apply_operation(AdjacentSticks::new(StickConstraint::default(), 1, false))
+            .apply_operation(RemoveParticles::inside(BooleanShape::Circle { centre: vec2(1.0, 0.0), radius: 0.1 }));
+
+        // particles at 0, 2, 3, 4 remain. The sticks 0-1 and 1-2 are gone, and the others point at the new indices
+        assert_eq!(b.particles.len(), 4);
+        assert_eq!(b.constraints.len(), 2);
+        let stick = b.constraints[0].as_any().downcast_ref::<StickConstraint>().unwrap();
+        assert_eq!(stick.particle_handles, [ParticleHandle::new(1), ParticleHandle::new(2)]);
+    }
+}
@@ -0,0 +1,186 @@
+use bevy::math::{Rect, Vec2};
+
+use crate::v5::{constraints::stick_constraint::StickConstraint, particle_handle::ParticleHandle};
+
+use super::shape_builder::{ShapeBuilder, ShapeBuilderOperation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClothCorner {
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight,
+}
+
+/// A sheet of cloth: a grid of particles with a particle on every corner of the rect, joined by
+/// structural sticks (to the right and up), shear sticks (across both diagonals of each cell) and
+/// bend sticks (to the particle 2 along and 2 up). The spacing is stretched slightly so the grid fills the rect,
+/// so it is never closer than a particle diameter.
+/// Corners can be pinned (made static) or attached to particles already in the particle system, eg. a flag on a pole.
+pub struct ClothGrid {
+    rect: Rect,
+    stick_template: StickConstraint,
+    shear_stick_template: Option<StickConstraint>,
+    bend_stick_template: Option<StickConstraint>,
+    pinned_corners: Vec<ClothCorner>,
+    corner_attachments: Vec<(ClothCorner, ParticleHandle)>,
+}
+
+impl ClothGrid {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            stick_template: StickConstraint::default(),
+            shear_stick_template: Some(StickConstraint::default()),
+            // soft, so the cloth folds and drapes instead of acting like a plate
+            bend_stick_template: Some(*StickConstraint::default().set_compliance(0.001)),
+            pinned_corners: vec![],
+            corner_attachments: vec![],
+        }
+    }
+
+    pub fn from_center_size(centre: Vec2, size: Vec2) -> Self {
+        Self::new(Rect::from_center_size(centre, size))
+    }
+
+    pub fn from_corners(p0: Vec2, p1: Vec2) -> Self {
+        Self::new(Rect::from_corners(p0, p1))
+    }
+
+    /// The structural sticks between neighbouring particles, and any attachments. Use compliance to make the cloth stretchy
+    pub fn with_sticks(mut self, stick_template: StickConstraint) -> Self {
+        self.stick_template = stick_template;
+        self
+    }
+
+    /// The sticks across the diagonals of each cell that stop it collapsing sideways. None = no shear sticks
+    pub fn with_shear_sticks(mut self, shear_stick_template: Option<StickConstraint>) -> Self {
+        self.shear_stick_template = shear_stick_template;
+        self
+    }
+
+    /// The sticks between every other particle that resist folding. None = no bend sticks
+    pub fn with_bend_sticks(mut self, bend_stick_template: Option<StickConstraint>) -> Self {
+        self.bend_stick_template = bend_stick_template;
+        self
+    }
+
+    pub fn with_pinned_corners(mut self, corners: &[ClothCorner]) -> Self {
+        self.pinned_corners.extend_from_slice(corners);
+        self
+    }
+
+    /// Join the corner to an existing particle with a structural stick, at their distance apart when created
+    pub fn with_attached_corner(mut self, corner: ClothCorner, particle_handle: ParticleHandle) -> Self {
+        self.corner_attachments.push((corner, particle_handle));
+        self
+    }
+}
+
+impl ShapeBuilderOperation for ClothGrid {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let diameter = shape_builder.particle_radius() * 2.0;
+
+        // allow for rounding errors, so a size of exactly n diameters fits n + 1 particles
+        let columns = ((self.rect.width() / diameter + 0.001) as usize).max(1) + 1;
+        let rows = ((self.rect.height() / diameter + 0.001) as usize).max(1) + 1;
+        let spacing = self.rect.size() / Vec2::new((columns - 1) as f32, (rows - 1) as f32);
+
+        let first = shape_builder.particles.len();
+        for y in 0..rows {
+            for x in 0..columns {
+                shape_builder.add_particle_at_position(self.rect.min + Vec2::new(x as f32, y as f32) * spacing);
+            }
+        }
+
+        // the particle index at each grid position, from the bottom left
+        let index = |x: usize, y: usize| first + y * columns + x;
+        let corner_index = |corner: ClothCorner| match corner {
+            ClothCorner::BottomLeft => index(0, 0),
+            ClothCorner::BottomRight => index(columns - 1, 0),
+            ClothCorner::TopLeft => index(0, rows - 1),
+            ClothCorner::TopRight => index(columns - 1, rows - 1),
+        };
+
+        for corner in self.pinned_corners.iter() {
+            shape_builder.particles[corner_index(*corner)].set_static(true);
+        }
+
+        for y in 0..rows {
+            for x in 0..columns {
+                if x + 1 < columns {
+                    shape_builder.add_stick(self.stick_template, [index(x, y), index(x + 1, y)]);
+                }
+                if y + 1 < rows {
+                    shape_builder.add_stick(self.stick_template, [index(x, y), index(x, y + 1)]);
+                }
+            }
+        }
+
+        if let Some(shear_stick_template) = self.shear_stick_template {
+            for y in 0..rows - 1 {
+                for x in 0..columns - 1 {
+                    shape_builder.add_stick(shear_stick_template, [index(x, y), index(x + 1, y + 1)]);
+                    shape_builder.add_stick(shear_stick_template, [index(x + 1, y), index(x, y + 1)]);
+                }
+            }
+        }
+
+        if let Some(bend_stick_template) = self.bend_stick_template {
+            for y in 0..rows {
+                for x in 0..columns {
+                    if x + 2 < columns {
+                        shape_builder.add_stick(bend_stick_template, [index(x, y), index(x + 2, y)]);
+                    }
+                    if y + 2 < rows {
+                        shape_builder.add_stick(bend_stick_template, [index(x, y), index(x, y + 2)]);
+                    }
+                }
+            }
+        }
+
+        for (corner, particle_handle) in self.corner_attachments.iter() {
+            shape_builder.add_attachment(corner_index(*corner), *particle_handle, self.stick_template);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+
+    #[test]
+    fn cloth_sticks() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(ClothGrid::from_corners(vec2(0.0, 0.0), vec2(3.0, 2.0)).with_pinned_corners(&[ClothCorner::TopLeft, ClothCorner::TopRight]));
+
+        // 4 x 3 particles with one on each corner
+        assert_eq!(b.particles.len(), 12);
+        assert_eq!(b.particles[11].pos, vec2(3.0, 2.0));
+
+        // structural: 3 rows of 3 and 4 columns of 2. shear: 2 per cell. bend: 3 rows of 2 and 4 columns of 1
+        assert_eq!(b.constraints.len(), (9 + 8) + (6 * 2) + (6 + 4));
+
+        let static_positions = b.particles.iter().filter(|particle| particle.is_static).map(|particle| particle.pos).collect::<Vec<Vec2>>();
+        assert_eq!(static_positions, vec![vec2(0.0, 2.0), vec2(3.0, 2.0)]);
+    }
+
+    #[test]
+    fn stretch_to_fill() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(ClothGrid::from_corners(vec2(0.0, 0.0), vec2(2.5, 1.0))
+            .with_shear_sticks(None)
+            .with_bend_sticks(None)
+            .with_attached_corner(ClothCorner::BottomRight, ParticleHandle::new(100)));
+
+        // 2.5 only fits 2 diameters, so the 3 particles across are 1.25 apart
+        assert_eq!(b.particles.len(), 3 * 2);
+        assert_eq!(b.particles[1].pos, vec2(1.25, 0.0));
+        assert_eq!(b.constraints.len(), 2 * 2 + 3);
+
+        assert_eq!(b.attachments.len(), 1);
+        assert_eq!(b.attachments[0].particle_index, 2);
+    }
+}
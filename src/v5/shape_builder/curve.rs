@@ -0,0 +1,285 @@
+use bevy::math::Vec2;
+
+use crate::v5::constraints::stick_constraint::StickConstraint;
+
+use super::shape_builder::{ShapeBuilder, ShapeBuilderOperation};
+
+// how many straight pieces each curve segment is broken into before particles are placed along it
+const SAMPLES_PER_SEGMENT: usize = 64;
+
+pub fn quadratic_bezier_point(p0: Vec2, p1: Vec2, p2: Vec2, t: f32) -> Vec2 {
+    let u = 1.0 - t;
+    p0 * (u * u) + p1 * (2.0 * u * t) + p2 * (t * t)
+}
+
+pub fn cubic_bezier_point(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, t: f32) -> Vec2 {
+    let u = 1.0 - t;
+    p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t)
+}
+
+/// Walk along the polyline from its start, placing a point each time the straight line distance from the previous point reaches spacing.
+/// Returns the start plus up to max_steps points, stopping early at the end of the polyline
+fn walk_polyline_by_chord(polyline: &Vec<Vec2>, spacing: f32, max_steps: usize) -> Vec<Vec2> {
+    let mut points = vec![polyline[0]];
+    let mut pos = polyline[0];
+    let mut index = 1;
+    while index < polyline.len() && points.len() <= max_steps {
+        let last = points[points.len() - 1];
+        let delta = polyline[index] - pos;
+        let a = delta.length_squared();
+        if a <= 0.0 {
+            index += 1;
+            continue;
+        }
+
+        // pos is inside the circle of radius spacing around last, so find where the piece from pos leaves it
+        let offset = pos - last;
+        let b = 2.0 * offset.dot(delta);
+        let c = offset.length_squared() - spacing * spacing;
+        let t = (-b + (b * b - 4.0 * a * c).max(0.0).sqrt()) / (2.0 * a);
+        if t.is_nan() || t > 1.0 {
+            pos = polyline[index];
+            index += 1;
+            continue;
+        }
+
+        pos += delta * t;
+        points.push(pos);
+    }
+    points
+}
+
+/// Points along a polyline where each point is the same straight line distance from the previous one, at least one particle
+/// diameter, so neighbouring particles are as close to touching as possible without overlapping, even on a bend.
+/// The spacing is stretched slightly so the last point lands on the end of the polyline. The first and last points of the polyline always get a point.
+/// Corners are not kept, so this suits a densely sampled curve better than a few straight lines (see Polyline).
+pub fn points_along_polyline(polyline: &Vec<Vec2>, radius: f32) -> Vec<Vec2> {
+    if polyline.len() < 2 {
+        return polyline.clone();
+    }
+
+    let diameter = radius * 2.0;
+    let end = polyline[polyline.len() - 1];
+    let total_length = (1..polyline.len()).map(|i| (polyline[i] - polyline[i - 1]).length()).sum::<f32>();
+
+    // allow for rounding errors, so a length of exactly n * diameter fits n steps
+    let tolerance = radius * 0.001;
+    let step_count = walk_polyline_by_chord(polyline, diameter - tolerance, usize::MAX).len() - 1;
+
+    // how much further the end is from the last point than the spacing, after steps - 1 points. Negative if the spacing is too big
+    let end_gap = |spacing: f32, steps: usize| -> f32 {
+        let points = walk_polyline_by_chord(polyline, spacing, steps - 1);
+        if points.len() < steps {
+            return -1.0;
+        }
+        (end - points[points.len() - 1]).length() - spacing
+    };
+
+    // the end may be closer than a diameter to the last step, eg. when the polyline turns back on itself, so try fewer steps
+    for steps in (1..=step_count).rev() {
+        if end_gap(diameter, steps) < -tolerance {
+            continue;
+        }
+
+        // stretch the spacing until the end is one spacing from the last point
+        let mut low = diameter;
+        let mut high = total_length.max(diameter);
+        if end_gap(low, steps) > tolerance {
+            for _ in 0..32 {
+                let mid = (low + high) * 0.5;
+                if end_gap(mid, steps) >= 0.0 {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+        }
+
+        let mut points = walk_polyline_by_chord(polyline, low, steps - 1);
+        points.push(end);
+        return points;
+    }
+
+    vec![polyline[0]]
+}
+
+#[derive(Debug, Clone)]
+pub enum CurveKind {
+    QuadraticBezier([Vec2; 3]),
+    CubicBezier([Vec2; 4]),
+
+    /// Uniform Catmull-Rom spline passing through every point
+    CatmullRom(Vec<Vec2>),
+}
+
+/// Particles placed along a smooth curve at equal spacing, for smooth hills, ramps and loops.
+/// Particles are as close to touching as possible without overlapping, and both ends of the curve get a particle.
+pub struct Curve {
+    kind: CurveKind,
+    stick_template: Option<StickConstraint>,
+}
+
+impl Curve {
+    pub fn new(kind: CurveKind) -> Self {
+        Self { kind, stick_template: None }
+    }
+
+    pub fn quadratic_bezier(p0: Vec2, p1: Vec2, p2: Vec2) -> Self {
+        Self::new(CurveKind::QuadraticBezier([p0, p1, p2]))
+    }
+
+    pub fn cubic_bezier(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2) -> Self {
+        Self::new(CurveKind::CubicBezier([p0, p1, p2, p3]))
+    }
+
+    pub fn catmull_rom(points: Vec<Vec2>) -> Self {
+        Self::new(CurveKind::CatmullRom(points))
+    }
+
+    /// Also join each particle to the next along the curve with a stick made from the stick template
+    pub fn with_sticks(mut self, stick_template: StickConstraint) -> Self {
+        self.stick_template = Some(stick_template);
+        self
+    }
+
+    fn segment_count(&self) -> usize {
+        match &self.kind {
+            CurveKind::QuadraticBezier(_) | CurveKind::CubicBezier(_) => 1,
+            CurveKind::CatmullRom(points) => points.len().saturating_sub(1),
+        }
+    }
+
+    /// Position on the given segment of the curve, with t from 0 to 1
+    fn sample(&self, segment: usize, t: f32) -> Vec2 {
+        match &self.kind {
+            CurveKind::QuadraticBezier([p0, p1, p2]) => quadratic_bezier_point(*p0, *p1, *p2, t),
+            CurveKind::CubicBezier([p0, p1, p2, p3]) => cubic_bezier_point(*p0, *p1, *p2, *p3, t),
+            CurveKind::CatmullRom(points) => {
+                // the end points are repeated so the curve reaches them
+                let last = points.len() - 1;
+                let p0 = points[segment.saturating_sub(1)];
+                let p1 = points[segment];
+                let p2 = points[(segment + 1).min(last)];
+                let p3 = points[(segment + 2).min(last)];
+
+                let t2 = t * t;
+                let t3 = t2 * t;
+                (p1 * 2.0 + (p2 - p0) * t + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2 + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+            },
+        }
+    }
+
+    fn get_points_for_radius(&self, radius: f32) -> Vec::<Vec2> {
+        let segment_count = self.segment_count();
+        if segment_count == 0 {
+            return match &self.kind {
+                CurveKind::CatmullRom(points) => points.clone(),
+                _ => vec![],
+            };
+        }
+
+        // approximate the curve with a dense polyline, then walk along it
+        let mut samples = vec![self.sample(0, 0.0)];
+        for segment in 0..segment_count {
+            for i in 1..=SAMPLES_PER_SEGMENT {
+                samples.push(self.sample(segment, i as f32 / SAMPLES_PER_SEGMENT as f32));
+            }
+        }
+
+        points_along_polyline(&samples, radius)
+    }
+}
+
+impl ShapeBuilderOperation for Curve {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let radius = shape_builder.particle_radius();
+        let points = self.get_points_for_radius(radius);
+
+        let first_index = shape_builder.particles.len();
+        shape_builder.add_particles_from_points(&points);
+
+        if let Some(stick_template) = self.stick_template {
+            for i in first_index + 1..shape_builder.particles.len() {
+                shape_builder.add_stick(stick_template, [i - 1, i]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::particle_handle::ParticleHandle;
+
+    use super::*;
+
+    fn assert_evenly_spaced(b: &ShapeBuilder, diameter: f32) {
+        for i in 1..b.particles.len() {
+            let distance = (b.particles[i].pos - b.particles[i - 1].pos).length();
+            assert!(distance <= diameter * 1.1, "particles {} and {} are {} apart", i - 1, i, distance);
+            assert!(distance >= diameter - 0.001, "particles {} and {} are {} apart", i - 1, i, distance);
+        }
+    }
+
+    #[test]
+    fn quadratic_bezier() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Curve::quadratic_bezier(vec2(0.0, 0.0), vec2(5.0, 5.0), vec2(10.0, 0.0)));
+
+        assert_eq!(b.particles[0].pos, vec2(0.0, 0.0));
+        assert!((b.particles[b.particles.len() - 1].pos - vec2(10.0, 0.0)).length() < 0.001);
+        assert_evenly_spaced(&b, 1.0);
+    }
+
+    #[test]
+    fn tight_bend_does_not_overlap() {
+        // the arc between neighbours around the tip is longer than the straight line between them
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Curve::quadratic_bezier(vec2(0.0, 0.0), vec2(1.5, 8.0), vec2(3.0, 0.0)));
+
+        assert!((b.particles[b.particles.len() - 1].pos - vec2(3.0, 0.0)).length() < 0.001);
+        for i in 1..b.particles.len() {
+            let distance = (b.particles[i].pos - b.particles[i - 1].pos).length();
+            assert!(distance >= 1.0 - 0.001, "particles {} and {} are {} apart", i - 1, i, distance);
+        }
+    }
+
+    #[test]
+    fn straight_cubic_bezier() {
+        // control points on the line, but bunched up at the start, so t is far from uniform along the line
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Curve::cubic_bezier(vec2(0.0, 0.0), vec2(0.5, 0.0), vec2(1.0, 0.0), vec2(10.0, 0.0)));
+
+        assert_eq!(b.particles.len(), 11);
+        for (i, particle) in b.particles.iter().enumerate() {
+            assert!((particle.pos - vec2(i as f32, 0.0)).length() < 0.01);
+        }
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_points() {
+        let points = vec![vec2(0.0, 0.0), vec2(4.0, 2.0), vec2(8.0, 0.0), vec2(12.0, 2.0)];
+        let curve = Curve::catmull_rom(points.clone());
+        assert_eq!(curve.sample(1, 0.0), points[1]);
+        assert_eq!(curve.sample(1, 1.0), points[2]);
+        assert_eq!(curve.sample(2, 1.0), points[3]);
+
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(curve);
+        assert_evenly_spaced(&b, 1.0);
+    }
+
+    #[test]
+    fn sticks() {
+        let mut b = ShapeBuilder::new();
+        b.add_particle_at_position(vec2(-5.0, 0.0));
+        b.apply_operation(Curve::quadratic_bezier(vec2(0.0, 0.0), vec2(5.0, 5.0), vec2(10.0, 0.0)).with_sticks(StickConstraint::default()));
+
+        // the sticks only join the particles on the curve
+        assert_eq!(b.constraints.len(), b.particles.len() - 2);
+        let stick = b.constraints[0].as_any().downcast_ref::<StickConstraint>().unwrap();
+        assert_eq!(stick.particle_handles, [ParticleHandle::new(1), ParticleHandle::new(2)]);
+        assert_eq!(stick.length, (b.particles[2].pos - b.particles[1].pos).length());
+    }
+}
@@ -0,0 +1,61 @@
+use bevy::math::{bounding::Aabb2d, Vec2};
+
+use super::{packing::{pack_points_in_aabb, Packing}, shape_builder::{ShapeBuilder, ShapeBuilderOperation}};
+
+/// A filled circle of particles, so that no particle pokes out of it.
+/// Use Circle for just the ring.
+pub struct Disc {
+    centre: Vec2,
+    radius: f32,
+    packing: Packing,
+}
+
+impl Disc {
+    pub fn new(centre: Vec2, radius: f32, packing: Packing) -> Self {
+        Self { centre, radius, packing }
+    }
+
+    fn get_points_for_radius(&self, radius: f32) -> Vec::<Vec2> {
+        let aabb = Aabb2d { min: self.centre - Vec2::splat(self.radius), max: self.centre + Vec2::splat(self.radius) };
+
+        // allow for rounding errors, so particles touching the edge are kept
+        let max_distance = self.radius - radius * 0.999;
+
+        pack_points_in_aabb(aabb, radius, self.packing).into_iter()
+            .filter(|pos| (*pos - self.centre).length() <= max_distance)
+            .collect()
+    }
+}
+
+impl ShapeBuilderOperation for Disc {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let radius = shape_builder.particle_radius();
+        let points = self.get_points_for_radius(radius);
+        shape_builder.add_particles_from_points(&points);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+
+    #[test]
+    fn filled_disc() {
+        let centre = vec2(10.0, -5.0);
+        for packing in [Packing::Square, Packing::Hexagonal, Packing::PoissonDisk { seed: 1 }] {
+            let mut b = ShapeBuilder::new();
+            b.apply_operation(Disc::new(centre, 3.0, packing));
+
+            assert!(b.particles.len() > 5);
+            assert!(b.particles.iter().all(|particle| (particle.pos - centre).length() <= 2.501));
+        }
+
+        // a disc exactly one particle wide holds a single particle
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Disc::new(centre, 0.5, Packing::Hexagonal));
+        assert_eq!(b.particles.len(), 1);
+        assert_eq!(b.particles[0].pos, centre);
+    }
+}
@@ -0,0 +1,66 @@
+use bevy::math::Vec2;
+
+use crate::v5::{constraints::{area_constraint::AreaConstraint, constraint::Constraint}, particle_handle::ParticleHandle};
+
+use super::shape_builder::{ShapeBuilder, ShapeBuilderOperation};
+
+/// Takes all the particles currently in the ShapeBuilder as an ordered closed loop (eg. from a Circle)
+/// and adds an area constraint to keep the loop inflated.
+///
+/// eg. an inflated tyre:
+///     .apply_operation(Circle::new(origin, 0.5))
+///     .apply_operation(AdjacentSticks::new(StickConstraint::default(), 1, true))
+///     .apply_operation(EnclosedArea::new(AreaConstraint::default().set_pressure(1.2).clone()))
+///
+/// The constraint handle ends up in shape_builder.constraint_handles, so the pressure can be
+/// changed later with particle_system.constraint_container.get_mut::<AreaConstraint>(handle)
+pub struct EnclosedArea {
+    constraint_template: AreaConstraint,
+}
+
+impl EnclosedArea {
+    pub fn new(constraint_template: AreaConstraint) -> Self {
+        Self {
+            constraint_template
+        }
+    }
+}
+
+impl ShapeBuilderOperation for EnclosedArea {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let particle_handles = (0..shape_builder.particles.len()).map(|pi| ParticleHandle::new(pi)).collect::<Vec<ParticleHandle>>();
+        let positions = shape_builder.particles.iter().map(|particle| particle.pos).collect::<Vec<Vec2>>();
+
+        let constraint = self.constraint_template.clone().set_particles(particle_handles, &positions).box_clone();
+        shape_builder.add_constraint(constraint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::{particle::Particle, particle_system::ParticleSystem, shape_builder::{circle::Circle, shape_builder::ShapeBuilder}};
+
+    use super::*;
+
+    #[test]
+    fn inflate_circle_at_runtime() {
+        let mut particle_system = ParticleSystem::default();
+
+        let mut b = ShapeBuilder::new();
+        b.set_particle_template(*Particle::default().set_radius(0.1))
+            .apply_operation(Circle::new(vec2(0.0, 0.0), 1.0))
+            .apply_operation(EnclosedArea::new(AreaConstraint::default()))
+            .create_in_particle_system(&mut particle_system);
+
+        assert_eq!(b.constraint_handles.len(), 1);
+
+        let area_constraint = particle_system.constraint_container.get_mut::<AreaConstraint>(b.constraint_handles[0]).unwrap();
+        assert_eq!(area_constraint.particle_handles.len(), b.particles.len());
+        assert!(area_constraint.rest_area.abs() > 3.0);
+
+        area_constraint.set_pressure(1.5);
+        assert_eq!(area_constraint.compute_target_area(), area_constraint.rest_area * 1.5);
+    }
+}
@@ -0,0 +1,27 @@
+use bevy::math::Vec2;
+
+use crate::v5::static_collider::StaticCollider;
+
+use super::shape_builder::{ShapeBuilder, ShapeBuilderOperation};
+
+/// A single capsule static collider (with the particle template radius and collision filtering)
+/// instead of a LineSegment of particles. This gives a smooth surface and costs no particles.
+pub struct LineSegmentCollider {
+    p1: Vec2,
+    p2: Vec2,
+}
+
+impl LineSegmentCollider {
+    pub fn new(p1: Vec2, p2: Vec2) -> Self {
+        Self { p1, p2 }
+    }
+}
+
+impl ShapeBuilderOperation for LineSegmentCollider {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let particle_template = shape_builder.particle_template;
+        let mut static_collider = StaticCollider::capsule(self.p1, self.p2, shape_builder.particle_radius());
+        static_collider.set_collision_group(particle_template.collision_group).set_collision_mask(particle_template.collision_mask);
+        shape_builder.add_static_collider(static_collider);
+    }
+}
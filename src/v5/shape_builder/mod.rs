@@ -1,5 +1,24 @@
 pub mod shape_builder;
 pub mod circle;
 pub mod line_segment;
+pub mod line_segment_collider;
 pub mod rectangle;
+pub mod rectangle_stick_grid;
+pub mod adjacent_sticks;
+pub mod adjacent_bending;
+pub mod shape_matching;
+pub mod enclosed_area;
+pub mod packing;
+pub mod polyline;
+pub mod polygon;
+pub mod disc;
+pub mod curve;
+pub mod transform;
+pub mod boolean;
+pub mod selection;
+pub mod bitmap;
+pub mod svg;
+pub mod text;
+pub mod rope;
+pub mod cloth_grid;
 pub mod tests;
\ No newline at end of file
@@ -0,0 +1,157 @@
+use bevy::math::{bounding::Aabb2d, IVec2, Vec2};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+
+/// How fill operations lay out particles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Packing {
+    /// Rows and columns 2 * radius apart
+    Square,
+
+    /// Every other row is offset by radius and rows are sqrt(3) * radius apart.
+    /// This is the densest packing, so fluids start close to rest density instead of collapsing
+    Hexagonal,
+
+    /// Randomly placed (jittered) particles that are never closer than 2 * radius, using Poisson-disk sampling.
+    /// Avoids the regular rows that make granular materials stack and slide in straight lines.
+    /// The same seed always gives the same points
+    PoissonDisk { seed: u64 },
+}
+
+/// Generate particle centres packed inside the aabb, so that particles of the given radius do not poke out of it
+pub fn pack_points_in_aabb(aabb: Aabb2d, radius: f32, packing: Packing) -> Vec<Vec2> {
+    debug_assert!(radius > 0.0);
+    let mut points = vec![];
+
+    // allow for rounding errors, so a size of exactly n * diameter fits n particles
+    let tolerance = radius * 0.001;
+    let min = aabb.min + Vec2::splat(radius);
+    let max = aabb.max - Vec2::splat(radius - tolerance);
+
+    let (row_spacing, row_offset) = match packing {
+        Packing::Square => (radius * 2.0, 0.0),
+        Packing::Hexagonal => (radius * 3.0_f32.sqrt(), radius),
+        Packing::PoissonDisk { seed } => return poisson_disk_points(min, max, radius * 2.0, seed),
+    };
+
+    let mut row = 0;
+    let mut y = min.y;
+    while y <= max.y {
+        let mut x = min.x + if row % 2 == 1 { row_offset } else { 0.0 };
+        while x <= max.x {
+            points.push(Vec2::new(x, y));
+            x += radius * 2.0;
+        }
+
+        row += 1;
+        y = min.y + row as f32 * row_spacing;
+    }
+
+    points
+}
+
+/// Bridson's Poisson-disk sampling: points between min and max (inclusive) that are at least min_distance apart
+fn poisson_disk_points(min: Vec2, max: Vec2, min_distance: f32, seed: u64) -> Vec<Vec2> {
+    // number of candidates tried around each active point before giving up on it
+    const CANDIDATE_COUNT: usize = 30;
+
+    if max.x < min.x || max.y < min.y {
+        return vec![];
+    }
+
+    let mut rng = Pcg64::seed_from_u64(seed);
+
+    // a cell this size can hold at most one point, so only the 5x5 block of cells around a candidate needs checking
+    let cell_size = min_distance / 2.0_f32.sqrt();
+    let grid_size = ((max - min) / cell_size).as_ivec2() + IVec2::ONE;
+    let mut grid: Vec<Option<usize>> = vec![None; (grid_size.x * grid_size.y) as usize];
+    let cell_for_pos = |pos: Vec2| ((pos - min) / cell_size).as_ivec2().clamp(IVec2::ZERO, grid_size - IVec2::ONE);
+
+    let mut points = vec![];
+    let mut active = vec![];
+
+    let first = Vec2::new(rng.gen_range(min.x..=max.x), rng.gen_range(min.y..=max.y));
+    let first_cell = cell_for_pos(first);
+    grid[(first_cell.y * grid_size.x + first_cell.x) as usize] = Some(0);
+    points.push(first);
+    active.push(0);
+
+    while !active.is_empty() {
+        let active_index = rng.gen_range(0..active.len());
+        let centre = points[active[active_index]];
+
+        let mut found = false;
+        for _ in 0..CANDIDATE_COUNT {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let distance = rng.gen_range(min_distance..min_distance * 2.0);
+            let candidate = centre + Vec2::from_angle(angle) * distance;
+            if candidate.x < min.x || candidate.y < min.y || candidate.x > max.x || candidate.y > max.y {
+                continue;
+            }
+
+            let cell = cell_for_pos(candidate);
+            let cell_min = (cell - IVec2::splat(2)).max(IVec2::ZERO);
+            let cell_max = (cell + IVec2::splat(2)).min(grid_size - IVec2::ONE);
+            let is_too_close = (cell_min.y..=cell_max.y).any(|y| (cell_min.x..=cell_max.x).any(|x| {
+                grid[(y * grid_size.x + x) as usize].map_or(false, |i| (points[i] - candidate).length() < min_distance)
+            }));
+            if is_too_close {
+                continue;
+            }
+
+            grid[(cell.y * grid_size.x + cell.x) as usize] = Some(points.len());
+            active.push(points.len());
+            points.push(candidate);
+            found = true;
+            break;
+        }
+
+        if !found {
+            active.swap_remove(active_index);
+        }
+    }
+
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+
+    #[test]
+    fn square_and_hexagonal() {
+        let aabb = Aabb2d { min: vec2(0.0, 0.0), max: vec2(4.0, 4.0) };
+
+        let square = pack_points_in_aabb(aabb, 0.5, Packing::Square);
+        assert_eq!(square.len(), 16);
+        assert_eq!(square[0], vec2(0.5, 0.5));
+        assert_eq!(square[15], vec2(3.5, 3.5));
+
+        // 4 rows fit in the height, odd rows are offset and so fit one less particle
+        let hexagonal = pack_points_in_aabb(aabb, 0.5, Packing::Hexagonal);
+        assert_eq!(hexagonal.len(), 4 + 3 + 4 + 3);
+        assert_eq!(hexagonal[4], vec2(1.0, 0.5 + 3.0_f32.sqrt() * 0.5));
+    }
+
+    #[test]
+    fn poisson_disk() {
+        let aabb = Aabb2d { min: vec2(0.0, 0.0), max: vec2(10.0, 10.0) };
+        let points = pack_points_in_aabb(aabb, 0.5, Packing::PoissonDisk { seed: 7 });
+
+        // reasonably dense, but never as dense as hexagonal packing (105 particles)
+        assert!(points.len() > 40 && points.len() < 105);
+
+        for (i, a) in points.iter().enumerate() {
+            assert!(a.x >= 0.5 && a.y >= 0.5 && a.x <= 9.501 && a.y <= 9.501);
+            for b in points.iter().skip(i + 1) {
+                assert!((*a - *b).length() >= 1.0);
+            }
+        }
+
+        // deterministic for a given seed
+        assert_eq!(points, pack_points_in_aabb(aabb, 0.5, Packing::PoissonDisk { seed: 7 }));
+        assert_ne!(points, pack_points_in_aabb(aabb, 0.5, Packing::PoissonDisk { seed: 8 }));
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::math::{bounding::Aabb2d, Vec2};
+
+use crate::v5::static_collider::closest_point_on_line_segment;
+
+use super::{packing::{pack_points_in_aabb, Packing}, shape_builder::{ShapeBuilder, ShapeBuilderOperation}};
+
+/// Is pos inside the simple polygon (concave is fine), using the even-odd rule
+pub fn is_point_in_polygon(points: &Vec<Vec2>, pos: Vec2) -> bool {
+    let count = points.len();
+    let mut is_inside = false;
+    for i in 0..count {
+        let a = points[i];
+        let b = points[(i + 1) % count];
+        if (a.y > pos.y) != (b.y > pos.y) && pos.x < a.x + (pos.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            is_inside = !is_inside;
+        }
+    }
+    is_inside
+}
+
+/// Distance from pos to the nearest edge of the polygon
+pub fn distance_to_polygon_edge(points: &Vec<Vec2>, pos: Vec2) -> f32 {
+    let count = points.len();
+    (0..count)
+        .map(|i| (closest_point_on_line_segment(points[i], points[(i + 1) % count], pos) - pos).length())
+        .fold(f32::MAX, f32::min)
+}
+
+/// Fills a simple polygon (concave is fine) with particles, so that no particle pokes out of the outline.
+/// Use a closed Polyline for just the outline.
+pub struct Polygon {
+    points: Vec<Vec2>,
+    packing: Packing,
+}
+
+impl Polygon {
+    pub fn new(points: Vec<Vec2>, packing: Packing) -> Self {
+        Self { points, packing }
+    }
+
+    fn get_points_for_radius(&self, radius: f32) -> Vec::<Vec2> {
+        if self.points.len() < 3 {
+            return vec![];
+        }
+
+        let min = self.points.iter().fold(Vec2::splat(f32::MAX), |min, point| min.min(*point));
+        let max = self.points.iter().fold(Vec2::splat(f32::MIN), |max, point| max.max(*point));
+
+        // allow for rounding errors, so particles touching an edge are kept
+        let min_edge_distance = radius * 0.999;
+
+        pack_points_in_aabb(Aabb2d { min, max }, radius, self.packing).into_iter()
+            .filter(|pos| is_point_in_polygon(&self.points, *pos) && distance_to_polygon_edge(&self.points, *pos) >= min_edge_distance)
+            .collect()
+    }
+}
+
+impl ShapeBuilderOperation for Polygon {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let radius = shape_builder.particle_radius();
+        let points = self.get_points_for_radius(radius);
+        shape_builder.add_particles_from_points(&points);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+
+    // an L shape, 4 x 4 with the top right 2 x 2 removed
+    fn l_shape() -> Vec<Vec2> {
+        vec![vec2(0.0, 0.0), vec2(4.0, 0.0), vec2(4.0, 2.0), vec2(2.0, 2.0), vec2(2.0, 4.0), vec2(0.0, 4.0)]
+    }
+
+    #[test]
+    fn point_in_concave_polygon() {
+        assert!(is_point_in_polygon(&l_shape(), vec2(1.0, 3.0)));
+        assert!(is_point_in_polygon(&l_shape(), vec2(3.0, 1.0)));
+        assert!(!is_point_in_polygon(&l_shape(), vec2(3.0, 3.0)));
+        assert!(!is_point_in_polygon(&l_shape(), vec2(5.0, 1.0)));
+    }
+
+    #[test]
+    fn fill_concave_polygon() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Polygon::new(l_shape(), Packing::Square));
+
+        // 16 particles fit in the 4 x 4 square, minus the 4 in the missing corner
+        assert_eq!(b.particles.len(), 12);
+        assert!(b.particles.iter().all(|particle| !(particle.pos.x > 2.0 && particle.pos.y > 2.0)));
+    }
+
+    #[test]
+    fn fill_hexagonal() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Polygon::new(l_shape(), Packing::Hexagonal));
+
+        for particle in b.particles.iter() {
+            assert!(is_point_in_polygon(&l_shape(), particle.pos));
+            assert!(distance_to_polygon_edge(&l_shape(), particle.pos) >= 0.499);
+        }
+
+        // rows alternate between 4 and 3 particles below y = 2, and 2 and 1 above it
+        assert_eq!(b.particles.len(), 4 + 3 + 2 + 1);
+    }
+}
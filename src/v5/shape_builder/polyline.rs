@@ -0,0 +1,118 @@
+use bevy::math::Vec2;
+
+use super::shape_builder::{radius_divisions_between_points, ShapeBuilder, ShapeBuilderOperation};
+
+/// A chain of line segments through a list of points, like many LineSegments joined end to end.
+/// Each corner gets a single particle, except a corner closer than radius to the previous corner
+/// (a repeated point or a segment shorter than radius), which is merged into the previous corner
+/// so particles do not stack on top of each other.
+/// Particles between corners are at least a diameter apart, so they never cause a corner to be dropped.
+pub struct Polyline {
+    points: Vec<Vec2>,
+    is_closed: bool, // join the last point back to the first
+}
+
+impl Polyline {
+    pub fn new(points: Vec<Vec2>, is_closed: bool) -> Self {
+        Self { points, is_closed }
+    }
+
+    pub fn open(points: Vec<Vec2>) -> Self {
+        Self::new(points, false)
+    }
+
+    pub fn closed(points: Vec<Vec2>) -> Self {
+        Self::new(points, true)
+    }
+
+    pub(super) fn get_points_for_radius(&self, radius: f32) -> Vec::<Vec2> {
+        let mut points: Vec<Vec2> = vec![];
+
+        // particles closer than this are treated as duplicates
+        let min_spacing = radius;
+        let add_point = |points: &mut Vec<Vec2>, pos: Vec2| {
+            if points.last().map_or(true, |last| (pos - *last).length() >= min_spacing) {
+                points.push(pos);
+            }
+        };
+
+        let segment_count = if self.is_closed { self.points.len() } else { self.points.len().saturating_sub(1) };
+        for i in 0..segment_count {
+            let p1 = self.points[i];
+            let p2 = self.points[(i + 1) % self.points.len()];
+
+            // like LineSegment, each segment excludes its end point which is the start of the next segment.
+            // always include the corner, even if the segment is too short to fit a particle
+            let divisions = radius_divisions_between_points(p1, p2, radius).max(1);
+            for d in 0..divisions {
+                add_point(&mut points, p1 + (p2 - p1) * (d as f32 / divisions as f32));
+            }
+        }
+
+        if self.is_closed {
+            // the end joins back up with the first particle
+            if points.len() > 1 && (points[points.len() - 1] - points[0]).length() < min_spacing {
+                points.pop();
+            }
+        } else if let Some(last) = self.points.last() {
+            add_point(&mut points, *last);
+        }
+
+        points
+    }
+}
+
+impl ShapeBuilderOperation for Polyline {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let radius = shape_builder.particle_radius();
+        let points = self.get_points_for_radius(radius);
+        shape_builder.add_particles_from_points(&points);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+
+    #[test]
+    fn open_polyline() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Polyline::open(vec![vec2(0.0, 0.0), vec2(4.0, 0.0), vec2(4.0, 0.0), vec2(4.0, 4.0)]));
+
+        // 4 particles along each segment, plus the end point. The repeated corner is ignored
+        assert_eq!(b.particles.len(), 9);
+        assert_eq!(b.particles[4].pos, vec2(4.0, 0.0));
+        assert_eq!(b.particles[8].pos, vec2(4.0, 4.0));
+    }
+
+    #[test]
+    fn closed_polyline() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Polyline::closed(vec![vec2(0.0, 0.0), vec2(4.0, 0.0), vec2(4.0, 4.0), vec2(0.0, 4.0)]));
+
+        // a square with one particle per corner
+        assert_eq!(b.particles.len(), 16);
+        let corner_count = b.particles.iter().filter(|particle| particle.pos == vec2(4.0, 4.0)).count();
+        assert_eq!(corner_count, 1);
+    }
+
+    #[test]
+    fn nearly_repeated_corner_is_merged() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Polyline::open(vec![vec2(0.0, 0.0), vec2(2.0, 0.0), vec2(2.3, 0.0), vec2(2.3, 2.0)]));
+
+        // the corner at 2.3 is within a radius of the corner at 2.0, so they share a particle
+        assert_eq!(b.particles.iter().map(|particle| particle.pos).collect::<Vec<Vec2>>(), vec![vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(2.0, 0.0), vec2(2.3, 1.0), vec2(2.3, 2.0)]);
+    }
+
+    #[test]
+    fn short_segments_keep_corners() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Polyline::open(vec![vec2(0.0, 0.0), vec2(0.6, 0.0), vec2(0.6, 2.0)]));
+
+        // the first segment is shorter than a particle diameter, but the corner still gets a particle
+        assert_eq!(b.particles.iter().map(|particle| particle.pos).collect::<Vec<Vec2>>(), vec![vec2(0.0, 0.0), vec2(0.6, 0.0), vec2(0.6, 1.0), vec2(0.6, 2.0)]);
+    }
+}
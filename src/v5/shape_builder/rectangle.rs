@@ -1,18 +1,28 @@
-use bevy::math::{vec2, Rect, Vec2};
+use bevy::math::{bounding::Aabb2d, vec2, Rect, Vec2};
 
-use super::shape_builder::{radius_divisions_between_points, ShapeBuilder, ShapeBuilderOperation};
+use super::{packing::{pack_points_in_aabb, Packing}, shape_builder::{radius_divisions_between_points, ShapeBuilder, ShapeBuilderOperation}};
 
 pub struct Rectangle {
-    pub rect: Rect
+    pub rect: Rect,
+
+    // None lays particles on a grid starting at rect.min, which RectangleStickGrid relies on.
+    // Otherwise particles are packed so they do not poke out of the rect
+    pub packing: Option<Packing>,
 }
 
 impl Rectangle {
     pub fn from_center_size(centre: Vec2, size: Vec2) -> Self {
-        Self { rect: Rect::from_center_size(centre, size) }
+        Self { rect: Rect::from_center_size(centre, size), packing: None }
     }
 
     pub fn from_corners(p0: Vec2, p1: Vec2) -> Self {
-        Self { rect: Rect::from_corners(p0, p1) }
+        Self { rect: Rect::from_corners(p0, p1), packing: None }
+    }
+
+    /// Fill the rect using the given packing. Use Packing::Hexagonal for fluids so they start near rest density
+    pub fn with_packing(mut self, packing: Packing) -> Self {
+        self.packing = Some(packing);
+        self
     }
 
     pub fn get_divisions_and_deltas_for_radius(&self, radius: f32) -> (usize, usize, Vec2, Vec2) {
@@ -33,6 +43,10 @@ impl Rectangle {
     }
 
     fn get_points_for_radius(&self, radius: f32) -> Vec::<Vec2> {
+        if let Some(packing) = self.packing {
+            return pack_points_in_aabb(Aabb2d { min: self.rect.min, max: self.rect.max }, radius, packing);
+        }
+
         let mut points = vec![];
 
         let min = self.rect.min;
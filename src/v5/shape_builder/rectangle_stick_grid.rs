@@ -1,15 +1,19 @@
-use crate::v4::{constraints::{constraint::Constraint, stick_constraint::StickConstraint}, particle_handle::{self, ParticleHandle}};
+use crate::v5::{constraints::{constraint::Constraint, stick_constraint::StickConstraint}, particle_handle::ParticleHandle};
 
 use super::{rectangle::Rectangle, shape_builder::{ShapeBuilder, ShapeBuilderOperation}};
 
-/// Takes a Rectangle and created stick constraints in a grid layout between them
+/// Takes a Rectangle and created stick constraints in a grid layout between them.
+/// Neighbouring particles sit exactly a diameter apart, so the sticks always exclude collisions between them
+/// otherwise the collision solver fights the sticks and the grid jitters
 pub struct RectangleStickGrid {
     rectangle: Rectangle,
     constraint_template: StickConstraint
 }
 
 impl RectangleStickGrid {
-    pub fn from_rectangle(constraint_template: StickConstraint, rectangle: Rectangle) -> Self {
+    pub fn from_rectangle(mut constraint_template: StickConstraint, rectangle: Rectangle) -> Self {
+        debug_assert!(rectangle.packing.is_none(), "RectangleStickGrid only supports the default grid layout");
+        constraint_template.set_exclude_collisions(true);
         Self {
             constraint_template,
             rectangle
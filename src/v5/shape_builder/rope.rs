@@ -0,0 +1,152 @@
+use bevy::math::Vec2;
+
+use crate::v5::{constraints::stick_constraint::StickConstraint, particle_handle::ParticleHandle};
+
+use super::shape_builder::{ShapeBuilder, ShapeBuilderOperation};
+
+/// A straight rope of particles from start to end, joined by structural sticks between neighbours
+/// and optional bend sticks between every other particle which stop it folding back on itself.
+/// Either end can be pinned (made static) or attached to a particle already in the particle system,
+/// eg. hanging the rope from a static anchor. An attached end is joined to the existing particle at its distance
+/// when created, so start the rope a diameter away from the anchor. A stick of zero length has no direction to push along
+/// so it is never corrected, and an end started on top of the anchor would not stay attached.
+pub struct Rope {
+    start: Vec2,
+    end: Vec2,
+    stick_template: StickConstraint,
+    bend_stick_template: Option<StickConstraint>,
+    is_start_pinned: bool,
+    is_end_pinned: bool,
+    start_attachment: Option<ParticleHandle>,
+    end_attachment: Option<ParticleHandle>,
+}
+
+impl Rope {
+    pub fn new(start: Vec2, end: Vec2) -> Self {
+        Self {
+            start,
+            end,
+            stick_template: StickConstraint::default(),
+            // soft, so the rope bends but still holds a curve
+            bend_stick_template: Some(*StickConstraint::default().set_compliance(0.001)),
+            is_start_pinned: false,
+            is_end_pinned: false,
+            start_attachment: None,
+            end_attachment: None,
+        }
+    }
+
+    /// The structural sticks between neighbouring particles, and any attachments. Use compliance to make the rope stretchy
+    pub fn with_sticks(mut self, stick_template: StickConstraint) -> Self {
+        self.stick_template = stick_template;
+        self
+    }
+
+    /// The sticks between every other particle that resist bending. None = a floppy rope (like a chain)
+    pub fn with_bend_sticks(mut self, bend_stick_template: Option<StickConstraint>) -> Self {
+        self.bend_stick_template = bend_stick_template;
+        self
+    }
+
+    pub fn with_pinned_start(mut self) -> Self {
+        self.is_start_pinned = true;
+        self
+    }
+
+    pub fn with_pinned_end(mut self) -> Self {
+        self.is_end_pinned = true;
+        self
+    }
+
+    pub fn with_attached_start(mut self, particle_handle: ParticleHandle) -> Self {
+        self.start_attachment = Some(particle_handle);
+        self
+    }
+
+    pub fn with_attached_end(mut self, particle_handle: ParticleHandle) -> Self {
+        self.end_attachment = Some(particle_handle);
+        self
+    }
+}
+
+impl ShapeBuilderOperation for Rope {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let diameter = shape_builder.particle_radius() * 2.0;
+
+        // allow for rounding errors, so a length of exactly n diameters fits n segments
+        let segment_count = (((self.end - self.start).length() / diameter + 0.001) as usize).max(1);
+
+        let first = shape_builder.particles.len();
+        for i in 0..=segment_count {
+            shape_builder.add_particle_at_position(self.start.lerp(self.end, i as f32 / segment_count as f32));
+        }
+        let last = shape_builder.particles.len() - 1;
+
+        if self.is_start_pinned {
+            shape_builder.particles[first].set_static(true);
+        }
+        if self.is_end_pinned {
+            shape_builder.particles[last].set_static(true);
+        }
+
+        for i in first..last {
+            shape_builder.add_stick(self.stick_template, [i, i + 1]);
+        }
+
+        if let Some(bend_stick_template) = self.bend_stick_template {
+            for i in first..last - 1 {
+                shape_builder.add_stick(bend_stick_template, [i, i + 2]);
+            }
+        }
+
+        if let Some(particle_handle) = self.start_attachment {
+            shape_builder.add_attachment(first, particle_handle, self.stick_template);
+        }
+        if let Some(particle_handle) = self.end_attachment {
+            shape_builder.add_attachment(last, particle_handle, self.stick_template);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::{particle::Particle, particle_system::ParticleSystem};
+
+    use super::*;
+
+    #[test]
+    fn rope_sticks() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Rope::new(vec2(0.0, 0.0), vec2(4.0, 0.0)).with_pinned_start());
+
+        // a particle every diameter including both ends, 4 structural sticks and 3 bend sticks
+        assert_eq!(b.particles.len(), 5);
+        assert_eq!(b.particles[4].pos, vec2(4.0, 0.0));
+        assert_eq!(b.constraints.len(), 4 + 3);
+        assert!(b.particles[0].is_static);
+        assert!(!b.particles[4].is_static);
+
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Rope::new(vec2(0.0, 0.0), vec2(4.0, 0.0)).with_bend_sticks(None));
+        assert_eq!(b.constraints.len(), 4);
+    }
+
+    #[test]
+    fn hang_from_anchor() {
+        let mut particle_system = ParticleSystem::default();
+        let anchor = particle_system.add_particles(&vec![*Particle::default().set_position(vec2(0.0, 10.0)).set_static(true)])[0];
+
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Rope::new(vec2(0.0, 9.0), vec2(0.0, 5.0)).with_attached_start(anchor).with_bend_sticks(None))
+            .create_in_particle_system(&mut particle_system);
+
+        // 4 structural sticks, plus the attachment which keeps the rope a diameter below the anchor
+        assert_eq!(b.constraint_handles.len(), 5);
+        let attachment = particle_system.constraint_container.get::<StickConstraint>(b.constraint_handles[4]).unwrap();
+        assert_eq!(attachment.particle_handles[0], anchor);
+        assert_eq!(attachment.particle_handles[1], b.particle_handles[0]);
+        assert_eq!(attachment.length, 1.0);
+    }
+}
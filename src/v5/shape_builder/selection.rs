@@ -0,0 +1,145 @@
+use bevy::{color::Color, math::{bounding::Aabb2d, Vec2}};
+
+use crate::v5::particle::Particle;
+
+use super::shape_builder::ShapeBuilder;
+
+/// A side of the ShapeBuilder AABB, for selecting the particles along it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AabbEdge {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl AabbEdge {
+    /// Is pos within tolerance of (or beyond) this edge of the aabb
+    pub fn is_near(&self, aabb: &Aabb2d, pos: Vec2, tolerance: f32) -> bool {
+        match self {
+            AabbEdge::Left => pos.x <= aabb.min.x + tolerance,
+            AabbEdge::Right => pos.x >= aabb.max.x - tolerance,
+            AabbEdge::Bottom => pos.y <= aabb.min.y + tolerance,
+            AabbEdge::Top => pos.y >= aabb.max.y - tolerance,
+        }
+    }
+}
+
+/// Some of the particles in a ShapeBuilder, chosen with one of the ShapeBuilder select functions.
+/// Edits apply to every selected particle and can be chained, eg. to pin both ends of a bridge:
+/// ```ignore
+/// sb.select_aabb_edges(&[AabbEdge::Left, AabbEdge::Right], 0.01).set_static(true);
+/// ```
+pub struct ParticleSelection<'a> {
+    shape_builder: &'a mut ShapeBuilder,
+    indices: Vec<usize>, // ascending
+}
+
+impl<'a> ParticleSelection<'a> {
+    pub fn new(shape_builder: &'a mut ShapeBuilder, indices: Vec<usize>) -> Self {
+        debug_assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+        debug_assert!(indices.last().map_or(true, |last| *last < shape_builder.particles.len()));
+        Self { shape_builder, indices }
+    }
+
+    /// Indices of the selected particles in the ShapeBuilder
+    pub fn indices(&self) -> &Vec<usize> {
+        &self.indices
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn for_each<F: FnMut(&mut Particle)>(&mut self, mut f: F) -> &mut Self {
+        for i in self.indices.iter() {
+            f(&mut self.shape_builder.particles[*i]);
+        }
+        self
+    }
+
+    pub fn set_static(&mut self, is_static: bool) -> &mut Self {
+        self.for_each(|particle| { particle.set_static(is_static); })
+    }
+
+    pub fn set_color(&mut self, color: Color) -> &mut Self {
+        self.for_each(|particle| { particle.set_color(color); })
+    }
+
+    pub fn set_mass(&mut self, mass: f32) -> &mut Self {
+        self.for_each(|particle| { particle.set_mass(mass); })
+    }
+
+    /// Set the collision layer (group) and the layers collided with (mask)
+    pub fn set_collision_layer(&mut self, collision_group: u32, collision_mask: u32) -> &mut Self {
+        self.for_each(|particle| { particle.set_collision_group(collision_group).set_collision_mask(collision_mask); })
+    }
+
+    /// Move the selected particles into a new ShapeBuilder. See ShapeBuilder::extract_particles for what happens to constraints
+    pub fn extract(self) -> ShapeBuilder {
+        let mut is_selected = vec![false; self.shape_builder.particles.len()];
+        for i in self.indices.iter() {
+            is_selected[*i] = true;
+        }
+
+        let mut index = 0;
+        self.shape_builder.extract_particles(|_| {
+            index += 1;
+            is_selected[index - 1]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{color::LinearRgba, math::vec2};
+
+    use crate::v5::shape_builder::rectangle::Rectangle;
+
+    use super::*;
+
+    // 4 x 4 grid of particles from (0, 0) to (3, 3)
+    fn grid() -> ShapeBuilder {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Rectangle::from_corners(vec2(0.0, 0.0), vec2(4.0, 4.0)));
+        b
+    }
+
+    #[test]
+    fn select_edges_with_tolerance() {
+        let mut b = grid();
+        b.particles[4].pos.x += 0.001; // a little off the left edge
+
+        assert_eq!(b.select_aabb_edges(&[AabbEdge::Left], 0.0).indices(), &vec![0, 8, 12]);
+        assert_eq!(b.select_aabb_edges(&[AabbEdge::Left], 0.01).indices(), &vec![0, 4, 8, 12]);
+        assert_eq!(b.select_aabb_edges(&[AabbEdge::Bottom, AabbEdge::Right], 0.01).indices(), &vec![0, 1, 2, 3, 7, 11, 15]);
+
+        b.select_aabb_edges(&[AabbEdge::Left, AabbEdge::Right], 0.01).set_static(true).set_mass(2.0);
+        let static_count = b.particles.iter().filter(|particle| particle.is_static && particle.mass == 2.0).count();
+        assert_eq!(static_count, 8);
+    }
+
+    #[test]
+    fn select_range_every_nth_and_predicate() {
+        let mut b = grid();
+        assert_eq!(b.select_range(2..5).indices(), &vec![2, 3, 4]);
+        assert_eq!(b.select_range(14..20).indices(), &vec![14, 15]);
+        assert_eq!(b.select_every_nth(5, 1).indices(), &vec![1, 6, 11]);
+
+        b.select_where(|particle| particle.pos.y > 2.5).set_color(Color::from(LinearRgba::RED)).set_collision_layer(0x2, 0x1);
+        assert_eq!(b.particles.iter().filter(|particle| particle.collision_group == 0x2 && particle.collision_mask == 0x1).count(), 4);
+    }
+
+    #[test]
+    fn extract_selection() {
+        let mut b = grid();
+        let extracted = b.select_every_nth(2, 0).extract();
+        assert_eq!(extracted.particles.len(), 8);
+        assert_eq!(b.particles.len(), 8);
+        assert!(extracted.particles.iter().all(|particle| particle.pos.x == 0.0 || particle.pos.x == 2.0));
+    }
+}
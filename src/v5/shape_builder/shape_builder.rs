@@ -1,6 +1,10 @@
-use bevy::math::{bounding::Aabb2d, Vec2};
+use std::ops::Range;
 
-use crate::v5::{particle::Particle, particle_handle::ParticleHandle, particle_system::ParticleSystem, particle_vec::SharedParticleVec};
+use bevy::{log::warn, math::{bounding::Aabb2d, Affine2, IVec2, Vec2}, utils::HashMap};
+
+use crate::v5::{constraints::{constraint::Constraint, stick_constraint::StickConstraint}, particle::Particle, particle_handle::{BodyHandle, ConstraintHandle, ParticleHandle, StaticColliderHandle}, particle_system::ParticleSystem, particle_vec::SharedParticleVec, static_collider::StaticCollider};
+
+use super::selection::{AabbEdge, ParticleSelection};
 
 
 
@@ -16,15 +20,27 @@ pub trait ShapeBuilderOperation {
     fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder);
 }
 
+/// A stick joining a ShapeBuilder particle to a particle that already exists in the particle system,
+/// eg. hanging a rope from a static anchor. The stick length is the distance between the two particles when created
+#[derive(Debug, Clone, Copy)]
+pub struct ShapeBuilderAttachment {
+    pub particle_index: usize, // index into ShapeBuilder::particles
+    pub particle_handle: ParticleHandle, // particle in the particle system
+    pub stick_template: StickConstraint,
+}
+
 pub struct ShapeBuilder {
     pub particles: Vec<Particle>,
     pub particle_template: Particle,
 
-    /* 
     pub constraints: Vec<Box<dyn Constraint + Send + Sync>>,
-    */
 
-    pub cursor: Vec2,
+    pub static_colliders: Vec<StaticCollider>,
+
+    pub attachments: Vec<ShapeBuilderAttachment>,
+
+    // transforms applied to particles and static colliders as they are added. See push_transform
+    pub transform_stack: Vec<Affine2>,
     /* 
     sticks: Vec<StickPrim>,
     springs: Vec<SpringPrim>,
@@ -43,10 +59,8 @@ pub struct ShapeBuilder {
 */
     pub particle_handles: Vec<ParticleHandle>,
     //pub stick_handles: Vec<StickHandle>,
-
-    /* 
     pub constraint_handles: Vec<ConstraintHandle>,
-    */
+    pub static_collider_handles: Vec<StaticColliderHandle>,
 }
 
 impl ShapeBuilder {
@@ -54,18 +68,19 @@ impl ShapeBuilder {
         Self { 
             particles: vec![], 
             particle_template: Particle::default(),
-/* 
+
             constraints: vec![],
-            */
             //constraint_template: Box::new(StickConstraint::default()),
 
-            cursor: Vec2::new(0.0, 0.0),
+            static_colliders: vec![],
 
-            particle_handles: vec![],
+            attachments: vec![],
+
+            transform_stack: vec![],
 
-            /* 
+            particle_handles: vec![],
             constraint_handles: vec![],
-*/
+            static_collider_handles: vec![],
 
             //stick_handles: vec![],
             //spring_handles: vec![],
@@ -82,18 +97,69 @@ impl ShapeBuilder {
         new_sb
     }
 
-    /* 
     pub fn add_constraint(&mut self, constraint: Box<dyn Constraint + Send + Sync>) -> &mut Self {
         self.constraints.push(constraint);
         self
-    }*/
+    }
+
+    /// Join the particle at particle_index to an existing particle in the particle system with a stick made from the stick template.
+    /// The stick is created by create_in_particle_system
+    pub fn add_attachment(&mut self, particle_index: usize, particle_handle: ParticleHandle, stick_template: StickConstraint) -> &mut Self {
+        self.attachments.push(ShapeBuilderAttachment { particle_index, particle_handle, stick_template });
+        self
+    }
+
+    /// Join 2 particles in this ShapeBuilder with a stick made from the stick template, at their current distance apart
+    pub fn add_stick(&mut self, stick_template: StickConstraint, particle_indices: [usize; 2]) -> &mut Self {
+        let [a, b] = particle_indices;
+        let length = (self.particles[b].pos - self.particles[a].pos).length();
+        let mut stick = stick_template;
+        stick.set_particle_handles([ParticleHandle::new(a), ParticleHandle::new(b)]).set_length(length);
+        self.add_constraint(stick.box_clone())
+    }
+
+    /// Add a static collider, transformed by the transform stack.
+    /// A collider that cannot take the transform (see StaticCollider::transform) is skipped with a warning
+    pub fn add_static_collider(&mut self, mut static_collider: StaticCollider) -> &mut Self {
+        if let Err(error) = static_collider.transform(self.transform()) {
+            warn!("ShapeBuilder skipped a static collider: {}", error);
+            return self;
+        }
+        self.static_colliders.push(static_collider);
+        self
+    }
+
+    /// Transform every particle and static collider added from now on by transform, on top of any transforms already pushed.
+    /// This lets operations be authored in local space, eg. push a translation to the level cursor and a mirror for the level direction.
+    /// Particle and capsule radii are not scaled. SdfGrid colliders only support translation and uniform scaling,
+    /// so while a rotation, mirror or non-uniform scale is pushed they are skipped by add_static_collider.
+    pub fn push_transform(&mut self, transform: Affine2) -> &mut Self {
+        let transform = self.transform() * transform;
+        self.transform_stack.push(transform);
+        self
+    }
+
+    /// Undo the last push_transform
+    pub fn pop_transform(&mut self) -> &mut Self {
+        debug_assert!(!self.transform_stack.is_empty());
+        self.transform_stack.pop();
+        self
+    }
+
+    /// The combined transform of the transform stack, taking local space to world space
+    pub fn transform(&self) -> Affine2 {
+        self.transform_stack.last().copied().unwrap_or(Affine2::IDENTITY)
+    }
 
     pub fn set_particle_template(&mut self, particle_template: Particle) -> &mut Self {
         self.particle_template = particle_template;
         self
     }
 
-    pub fn add_particle(&mut self, particle: Particle) -> &mut Self {
+    pub fn add_particle(&mut self, mut particle: Particle) -> &mut Self {
+        let transform = self.transform();
+        particle.pos = transform.transform_point2(particle.pos);
+        particle.pos_prev = transform.transform_point2(particle.pos_prev);
         self.particles.push(particle);
         self
     }
@@ -119,10 +185,42 @@ impl ShapeBuilder {
     }
 
     pub fn create_in_particle_system(&mut self, particle_system: &mut ParticleSystem) -> &mut Self {
+        let particle_handle_offset = particle_system.particle_data.len() as u64;
+
         let mut particle_handles = (*particle_system).add_particles(&self.particles);
         self.particle_handles.append(&mut particle_handles);
+
+        for constraint in self.constraints.iter() {
+            let mut constraint = constraint.box_clone();
+            constraint.offset_particle_handles(particle_handle_offset);
+            let constraint_handle = particle_system.add_constraint(constraint);
+            self.constraint_handles.push(constraint_handle);
+        }
+
+        for attachment in self.attachments.iter() {
+            let mut particle_handle = ParticleHandle::new(attachment.particle_index);
+            particle_handle.offset(particle_handle_offset);
+            let length = (particle_system.particle_data.get_pos_vec2(particle_handle) - particle_system.particle_data.get_pos_vec2(attachment.particle_handle)).length();
+            let mut stick = attachment.stick_template;
+            stick.set_particle_handles([attachment.particle_handle, particle_handle]).set_length(length);
+            let constraint_handle = particle_system.add_constraint(stick.box_clone());
+            self.constraint_handles.push(constraint_handle);
+        }
+
+        for static_collider in self.static_colliders.iter() {
+            let static_collider_handle = particle_system.add_static_collider(static_collider.clone());
+            self.static_collider_handles.push(static_collider_handle);
+        }
         self
     }
+
+    /// Create the particles and constraints in the particle system, grouped into a single body
+    pub fn create_body_in_particle_system(&mut self, particle_system: &mut ParticleSystem) -> BodyHandle {
+        let particle_handle_start = self.particle_handles.len();
+        let constraint_handle_start = self.constraint_handles.len();
+        self.create_in_particle_system(particle_system);
+        particle_system.add_body(self.particle_handles[particle_handle_start..].to_vec(), self.constraint_handles[constraint_handle_start..].to_vec())
+    }
 /* 
     fn create_in_particle_container(&mut self, particle_container: &mut ParticleContainer) -> &mut Self {
         for particle in self.particles.iter() {
@@ -189,15 +287,157 @@ impl ShapeBuilder {
     }
 
     pub fn extract_left_most_particles(&mut self) -> ShapeBuilder {
+        self.select_aabb_edges(&[AabbEdge::Left], 0.0).extract()
+    }
+
+    /// Select the particles matching the predicate
+    pub fn select_where<F: FnMut(&Particle) -> bool>(&mut self, mut predicate: F) -> ParticleSelection<'_> {
+        let indices = self.particles.iter().enumerate().filter(|(_, particle)| predicate(particle)).map(|(i, _)| i).collect();
+        ParticleSelection::new(self, indices)
+    }
+
+    /// Select the particles within tolerance of any of the given edges of the ShapeBuilder AABB.
+    /// A small tolerance avoids missing particles due to rounding errors
+    pub fn select_aabb_edges(&mut self, edges: &[AabbEdge], tolerance: f32) -> ParticleSelection<'_> {
+        if self.particles.is_empty() {
+            return ParticleSelection::new(self, vec![]);
+        }
+
         let aabb = self.get_aabb();
+        let edges = edges.to_vec();
+        self.select_where(|particle| edges.iter().any(|edge| edge.is_near(&aabb, particle.pos, tolerance)))
+    }
 
+    /// Select the particles by index. The range is clamped to the particles that exist
+    pub fn select_range(&mut self, range: Range<usize>) -> ParticleSelection<'_> {
+        let end = range.end.min(self.particles.len());
+        let indices = (range.start.min(end)..end).collect();
+        ParticleSelection::new(self, indices)
+    }
+
+    /// Select every nth particle, starting from the particle at index offset
+    pub fn select_every_nth(&mut self, n: usize, offset: usize) -> ParticleSelection<'_> {
+        debug_assert!(n > 0);
+        let indices = (offset..self.particles.len()).step_by(n).collect();
+        ParticleSelection::new(self, indices)
+    }
+
+    /// Move the particles matching the predicate into a new ShapeBuilder with the same particle template.
+    /// Constraints between extracted particles move with them, constraints between the remaining particles stay,
+    /// and constraints that join the two sets are dropped. Attachments move with their particle and static colliders stay in this ShapeBuilder.
+    pub fn extract_particles<F: FnMut(&Particle) -> bool>(&mut self, mut predicate: F) -> ShapeBuilder {
         let mut s = ShapeBuilder::from_shape_builder_templates(&self);
 
-        let r = self.particles.extract_if(|particle| particle.pos.x == aabb.min.x).collect::<Vec<_>>();
-        //s.add_particles(r); // todo: add a fn for this
-        for particle in r {
-            s.add_particle(particle);
+        let is_extracted = self.particles.iter().map(|particle| predicate(particle)).collect::<Vec<bool>>();
+
+        // where each particle ends up in each ShapeBuilder
+        let mut remaining_remap = vec![];
+        let mut extracted_remap = vec![];
+        let mut remaining_count = 0;
+        let mut extracted_count = 0;
+        for extracted in is_extracted.iter() {
+            if *extracted {
+                remaining_remap.push(None);
+                extracted_remap.push(Some(ParticleHandle::new(extracted_count)));
+                extracted_count += 1;
+            } else {
+                remaining_remap.push(Some(ParticleHandle::new(remaining_count)));
+                extracted_remap.push(None);
+                remaining_count += 1;
+            }
         }
+
+        // the particles are already transformed, so do not use add_particle which would transform them again
+        let mut index = 0;
+        s.particles = self.particles.extract_if(.., |_| {
+            index += 1;
+            is_extracted[index - 1]
+        }).collect();
+
+        for constraint in std::mem::take(&mut self.constraints) {
+            let mut remaining_constraint = constraint.box_clone();
+            if remaining_constraint.remap_particle_handles(&remaining_remap) {
+                self.constraints.push(remaining_constraint);
+                continue;
+            }
+
+            let mut extracted_constraint = constraint;
+            if extracted_constraint.remap_particle_handles(&extracted_remap) {
+                s.constraints.push(extracted_constraint);
+            }
+        }
+
+        for mut attachment in std::mem::take(&mut self.attachments) {
+            if let Some(particle_handle) = remaining_remap[attachment.particle_index] {
+                attachment.particle_index = particle_handle.id();
+                self.attachments.push(attachment);
+            } else if let Some(particle_handle) = extracted_remap[attachment.particle_index] {
+                attachment.particle_index = particle_handle.id();
+                s.attachments.push(attachment);
+            }
+        }
+
         s
     }
+
+    /// Remove the particles matching the predicate, along with any constraints using them
+    pub fn remove_particles<F: FnMut(&Particle) -> bool>(&mut self, predicate: F) -> &mut Self {
+        self.extract_particles(predicate);
+        self
+    }
+
+    /// Add the particles, constraints, attachments and static colliders from other to this ShapeBuilder.
+    /// A particle from other within tolerance of a particle already in this ShapeBuilder, or of an earlier particle in other,
+    /// is dropped and constraints from other use the existing particle in its place.
+    /// Constraints from other that end up joining a particle to itself (eg. both ends of a stick were duplicates) are dropped.
+    pub fn merge(&mut self, other: &ShapeBuilder, tolerance: f32) -> &mut Self {
+        // bucket the particles in a grid with cells the size of tolerance, so each particle from other
+        // only checks the 3x3 cells around it
+        let cell_size = tolerance.max(f32::EPSILON);
+        let cell_for_pos = |pos: Vec2| (pos / cell_size).floor().as_ivec2();
+        let mut grid: HashMap<IVec2, Vec<usize>> = HashMap::default();
+        for (i, particle) in self.particles.iter().enumerate() {
+            grid.entry(cell_for_pos(particle.pos)).or_default().push(i);
+        }
+
+        let mut remap = vec![];
+        for particle in other.particles.iter() {
+            let cell = cell_for_pos(particle.pos);
+            let duplicate_index = (-1..=1).flat_map(|y| (-1..=1).map(move |x| cell + IVec2::new(x, y)))
+                .filter_map(|neighbour_cell| grid.get(&neighbour_cell))
+                .flatten()
+                .copied()
+                .filter(|i| (self.particles[*i].pos - particle.pos).length() <= tolerance)
+                .min();
+            match duplicate_index {
+                Some(i) => remap.push(Some(ParticleHandle::new(i))),
+                None => {
+                    // later particles from other can merge with this one too
+                    grid.entry(cell).or_default().push(self.particles.len());
+                    remap.push(Some(ParticleHandle::new(self.particles.len())));
+                    self.particles.push(*particle);
+                },
+            }
+        }
+
+        for constraint in other.constraints.iter() {
+            let mut constraint = constraint.box_clone();
+            if !constraint.remap_particle_handles(&remap) {
+                continue;
+            }
+
+            if constraint.particle_links().iter().any(|[handle_a, handle_b]| handle_a == handle_b) {
+                continue;
+            }
+            self.constraints.push(constraint);
+        }
+
+        for attachment in other.attachments.iter() {
+            let Some(particle_handle) = remap[attachment.particle_index] else { continue };
+            self.attachments.push(ShapeBuilderAttachment { particle_index: particle_handle.id(), ..*attachment });
+        }
+
+        self.static_colliders.extend(other.static_colliders.iter().cloned());
+        self
+    }
 }
@@ -0,0 +1,59 @@
+use bevy::math::Vec2;
+
+use crate::v5::{constraints::{constraint::Constraint, shape_matching_constraint::ShapeMatchingConstraint}, particle_handle::ParticleHandle};
+
+use super::shape_builder::{ShapeBuilder, ShapeBuilderOperation};
+
+/// Takes all the particles currently in the ShapeBuilder and groups them into a single
+/// shape matching constraint, using their current positions as the rest shape.
+///
+/// eg. a soft jelly cube without needing a stick grid:
+///     .apply_operation(Rectangle::from_center_size(origin, vec2(0.4, 0.8)))
+///     .apply_operation(ShapeMatching::new(ShapeMatchingConstraint::default().set_compliance(0.00004).clone()))
+pub struct ShapeMatching {
+    constraint_template: ShapeMatchingConstraint,
+}
+
+impl ShapeMatching {
+    pub fn new(constraint_template: ShapeMatchingConstraint) -> Self {
+        Self {
+            constraint_template
+        }
+    }
+}
+
+impl ShapeBuilderOperation for ShapeMatching {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let particle_handles = (0..shape_builder.particles.len()).map(|pi| ParticleHandle::new(pi)).collect::<Vec<ParticleHandle>>();
+        let positions = shape_builder.particles.iter().map(|particle| particle.pos).collect::<Vec<Vec2>>();
+        let masses = shape_builder.particles.iter().map(|particle| particle.mass).collect::<Vec<f32>>();
+
+        let constraint = self.constraint_template.clone().set_particles(particle_handles, &positions, &masses).box_clone();
+        shape_builder.add_constraint(constraint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::{particle_system::ParticleSystem, shape_builder::{rectangle::Rectangle, shape_builder::ShapeBuilder}};
+
+    use super::*;
+
+    #[test]
+    fn rectangle() {
+        let mut particle_system = ParticleSystem::default();
+
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Rectangle::from_center_size(vec2(0.0, 0.0), vec2(4.0, 4.0)))
+            .apply_operation(ShapeMatching::new(ShapeMatchingConstraint::default()))
+            .create_in_particle_system(&mut particle_system);
+
+        assert_eq!(b.constraints.len(), 1);
+
+        let shape_matching = particle_system.constraint_container.constraints[0].as_any().downcast_ref::<ShapeMatchingConstraint>().unwrap();
+        assert_eq!(shape_matching.particle_handles.len(), b.particles.len());
+        assert_eq!(shape_matching.rest_positions.len(), b.particles.len());
+    }
+}
@@ -0,0 +1,673 @@
+use bevy::{color::Color, math::{Affine2, Vec2}};
+
+use super::{curve::{cubic_bezier_point, points_along_polyline, quadratic_bezier_point}, packing::Packing, polygon::Polygon, polyline::Polyline, shape_builder::{ShapeBuilder, ShapeBuilderOperation}};
+
+// how many straight pieces each curve command is broken into
+const SAMPLES_PER_CURVE: usize = 16;
+
+/// One continuous piece of a path, flattened into straight lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgSubpath {
+    pub points: Vec<Vec2>, // a closed subpath ends with its first point
+    pub corners: Vec<usize>, // ascending indices of the points at the end of each command. the points between two corners are samples along a curve
+    pub is_closed: bool,
+}
+
+impl SvgSubpath {
+    /// Particle positions along the subpath at the given radius. Corners get a particle like Polyline,
+    /// while curves are resampled so their particles are evenly spaced
+    pub fn get_stroke_points_for_radius(&self, radius: f32) -> Vec<Vec2> {
+        let mut stroke_points: Vec<Vec2> = vec![];
+
+        // each piece starts where the previous one ended, so skip particles closer than radius like Polyline does
+        let mut add_points = |points: Vec<Vec2>| {
+            for pos in points {
+                if stroke_points.last().map_or(true, |last| (pos - *last).length() >= radius) {
+                    stroke_points.push(pos);
+                }
+            }
+        };
+
+        // runs of straight segments go through Polyline, curves through points_along_polyline
+        let mut straight_start = 0;
+        for corner_pair in self.corners.windows(2) {
+            let (start, end) = (corner_pair[0], corner_pair[1]);
+            if end == start + 1 {
+                continue;
+            }
+
+            if start > straight_start {
+                add_points(Polyline::open(self.points[straight_start..=start].to_vec()).get_points_for_radius(radius));
+            }
+            add_points(points_along_polyline(&self.points[start..=end].to_vec(), radius));
+            straight_start = end;
+        }
+
+        let last = self.points.len().saturating_sub(1);
+        if last > straight_start {
+            add_points(Polyline::open(self.points[straight_start..=last].to_vec()).get_points_for_radius(radius));
+        }
+
+        // the end joins back up with the first particle
+        if self.is_closed && stroke_points.len() > 1 && (stroke_points[stroke_points.len() - 1] - stroke_points[0]).length() < radius {
+            stroke_points.pop();
+        }
+
+        stroke_points
+    }
+}
+
+/// Properties inherited from groups
+#[derive(Debug, Clone, Copy)]
+struct SvgStyle {
+    transform: Affine2,
+    fill: Option<Color>,
+    stroke: Option<Color>,
+}
+
+/// A <path> element, in SVG user units
+#[derive(Debug, Clone)]
+pub struct SvgPath {
+    pub subpaths: Vec<SvgSubpath>,
+    pub transform: Affine2, // includes the transforms of the groups the path is in
+    pub fill: Option<Color>,
+    pub stroke: Option<Color>,
+}
+
+#[derive(Debug, Clone)]
+struct SvgTag {
+    name: String,
+    attributes: Vec<(String, String)>,
+    is_closing: bool, // </g>
+    is_self_closing: bool, // <path />
+}
+
+impl SvgTag {
+    fn get_attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(attribute_name, _)| attribute_name == name).map(|(_, value)| value.as_str())
+    }
+
+    /// A presentation attribute, where the style attribute takes priority, eg. style="fill:#ff0000" or fill="#ff0000"
+    fn get_presentation_attribute(&self, name: &str) -> Option<&str> {
+        let style_value = self.get_attribute("style").and_then(|style| {
+            style.split(';')
+                .filter_map(|declaration| declaration.split_once(':'))
+                .find(|(property, _)| property.trim() == name)
+                .map(|(_, value)| value.trim())
+        });
+        style_value.or(self.get_attribute(name))
+    }
+}
+
+/// Split the svg into its tags, ignoring text, comments, processing instructions and doctypes
+fn parse_tags(svg: &str) -> Result<Vec<SvgTag>, String> {
+    let bytes = svg.as_bytes();
+    let mut tags = vec![];
+    let mut i = 0;
+
+    let skip_past = |i: usize, end: &str| -> Result<usize, String> {
+        svg[i..].find(end).map(|offset| i + offset + end.len()).ok_or(format!("expected '{}'", end))
+    };
+
+    while let Some(offset) = svg[i..].find('<') {
+        i += offset;
+
+        if svg[i..].starts_with("<!--") {
+            i = skip_past(i, "-->")?;
+            continue;
+        }
+        if svg[i..].starts_with("<?") {
+            i = skip_past(i, "?>")?;
+            continue;
+        }
+        if svg[i..].starts_with("<!") {
+            i = skip_past(i, ">")?;
+            continue;
+        }
+
+        i += 1;
+        let is_closing = bytes.get(i) == Some(&b'/');
+        if is_closing {
+            i += 1;
+        }
+
+        let name_start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'/' && bytes[i] != b'>' {
+            i += 1;
+        }
+        let mut tag = SvgTag { name: svg[name_start..i].to_string(), attributes: vec![], is_closing, is_self_closing: false };
+
+        loop {
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+
+            match bytes.get(i) {
+                None => return Err(format!("unterminated tag <{}>", tag.name)),
+                Some(b'>') => {
+                    i += 1;
+                    break;
+                },
+                Some(b'/') => {
+                    if bytes.get(i + 1) != Some(&b'>') {
+                        return Err(format!("expected '/>' in tag <{}>", tag.name));
+                    }
+                    tag.is_self_closing = true;
+                    i += 2;
+                    break;
+                },
+                Some(_) => {
+                    let attribute_start = i;
+                    while i < bytes.len() && bytes[i] != b'=' && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                        i += 1;
+                    }
+                    let attribute_name = svg[attribute_start..i].to_string();
+
+                    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                        i += 1;
+                    }
+                    if bytes.get(i) != Some(&b'=') {
+                        return Err(format!("expected '=' after attribute '{}' in tag <{}>", attribute_name, tag.name));
+                    }
+                    i += 1;
+                    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                        i += 1;
+                    }
+
+                    let quote = match bytes.get(i) {
+                        Some(quote @ (b'"' | b'\'')) => *quote as char,
+                        _ => return Err(format!("expected a quoted value for attribute '{}' in tag <{}>", attribute_name, tag.name)),
+                    };
+                    i += 1;
+                    let value_end = svg[i..].find(quote).map(|offset| i + offset).ok_or(format!("unterminated value for attribute '{}'", attribute_name))?;
+                    tag.attributes.push((attribute_name, svg[i..value_end].to_string()));
+                    i = value_end + 1;
+                },
+            }
+        }
+
+        tags.push(tag);
+    }
+
+    Ok(tags)
+}
+
+/// Parse a list of numbers separated by whitespace and/or commas, eg. "10,-5 2.5"
+fn parse_numbers(s: &str) -> Result<Vec<f32>, String> {
+    s.split(|c: char| c.is_ascii_whitespace() || c == ',')
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse::<f32>().map_err(|_| format!("invalid number '{}'", number)))
+        .collect()
+}
+
+/// Parse a transform attribute, eg. "translate(10, 20) rotate(45)"
+pub fn parse_transform(s: &str) -> Result<Affine2, String> {
+    let mut transform = Affine2::IDENTITY;
+    let mut rest = s.trim();
+
+    while !rest.is_empty() {
+        let open = rest.find('(').ok_or(format!("expected '(' in transform '{}'", s))?;
+        let close = rest.find(')').ok_or(format!("expected ')' in transform '{}'", s))?;
+        let name = rest[..open].trim_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        let args = parse_numbers(&rest[open + 1..close])?;
+
+        let arg = |i: usize| args.get(i).copied().ok_or(format!("{} needs at least {} arguments", name, i + 1));
+
+        let next = match name {
+            "matrix" => Affine2::from_cols_array(&[arg(0)?, arg(1)?, arg(2)?, arg(3)?, arg(4)?, arg(5)?]),
+            "translate" => Affine2::from_translation(Vec2::new(arg(0)?, args.get(1).copied().unwrap_or(0.0))),
+            "scale" => Affine2::from_scale(Vec2::new(arg(0)?, args.get(1).copied().unwrap_or(arg(0)?))),
+            "rotate" => {
+                let centre = Vec2::new(args.get(1).copied().unwrap_or(0.0), args.get(2).copied().unwrap_or(0.0));
+                Affine2::from_translation(centre) * Affine2::from_angle(arg(0)?.to_radians()) * Affine2::from_translation(-centre)
+            },
+            "skewX" => Affine2::from_cols_array(&[1.0, 0.0, arg(0)?.to_radians().tan(), 1.0, 0.0, 0.0]),
+            "skewY" => Affine2::from_cols_array(&[1.0, arg(0)?.to_radians().tan(), 0.0, 1.0, 0.0, 0.0]),
+            _ => return Err(format!("unsupported transform '{}'", name)),
+        };
+
+        transform = transform * next;
+        rest = rest[close + 1..].trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+    }
+
+    Ok(transform)
+}
+
+/// Parse a fill or stroke colour. "none" is Ok(None)
+pub fn parse_colour(s: &str) -> Result<Option<Color>, String> {
+    let s = s.trim();
+    let hex = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| format!("invalid colour '{}'", s));
+
+    if let Some(channels) = s.strip_prefix("rgb(").and_then(|rest| rest.strip_suffix(')')) {
+        let channels = parse_numbers(channels)?;
+        if channels.len() != 3 {
+            return Err(format!("invalid colour '{}'", s));
+        }
+        return Ok(Some(Color::srgb_u8(channels[0] as u8, channels[1] as u8, channels[2] as u8)));
+    }
+
+    let colour = match s {
+        "none" | "transparent" => return Ok(None),
+        "black" => Color::srgb_u8(0, 0, 0),
+        "white" => Color::srgb_u8(255, 255, 255),
+        "red" => Color::srgb_u8(255, 0, 0),
+        "green" => Color::srgb_u8(0, 128, 0),
+        "blue" => Color::srgb_u8(0, 0, 255),
+        "yellow" => Color::srgb_u8(255, 255, 0),
+        "gray" | "grey" => Color::srgb_u8(128, 128, 128),
+        _ if s.starts_with('#') && s.len() == 7 => Color::srgb_u8(hex(&s[1..3])?, hex(&s[3..5])?, hex(&s[5..7])?),
+        _ if s.starts_with('#') && s.len() == 4 => Color::srgb_u8(hex(&s[1..2])? * 17, hex(&s[2..3])? * 17, hex(&s[3..4])? * 17),
+        _ => return Err(format!("unsupported colour '{}'", s)),
+    };
+
+    Ok(Some(colour))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PathToken {
+    Command(char),
+    Number(f32),
+}
+
+/// Split path data into commands and numbers. Numbers do not need separators when it is unambiguous, eg. "M0-5L1.5.5"
+fn tokenize_path_data(d: &str) -> Result<Vec<PathToken>, String> {
+    let bytes = d.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() || c == b',' {
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_alphabetic() {
+            tokens.push(PathToken::Command(c as char));
+            i += 1;
+            continue;
+        }
+
+        // sign, digits, a single '.', digits, then an optional exponent
+        let start = i;
+        if c == b'+' || c == b'-' {
+            i += 1;
+        }
+        let mut has_dot = false;
+        while i < bytes.len() && (bytes[i].is_ascii_digit() || (bytes[i] == b'.' && !has_dot)) {
+            has_dot |= bytes[i] == b'.';
+            i += 1;
+        }
+        if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+            i += 1;
+            if i < bytes.len() && (bytes[i] == b'+' || bytes[i] == b'-') {
+                i += 1;
+            }
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+        }
+
+        let number = &d[start..i];
+        tokens.push(PathToken::Number(number.parse::<f32>().map_err(|_| format!("invalid number '{}' in path data", number))?));
+    }
+
+    Ok(tokens)
+}
+
+/// Parse the d attribute of a path. Supports the M, L, H, V, C, Q and Z commands, absolute and relative
+pub fn parse_path_data(d: &str) -> Result<Vec<SvgSubpath>, String> {
+    let tokens = tokenize_path_data(d)?;
+
+    let mut subpaths = vec![];
+    let mut points: Vec<Vec2> = vec![];
+    let mut corners: Vec<usize> = vec![];
+    let mut command: Option<char> = None;
+    let mut current = Vec2::ZERO;
+    let mut start = Vec2::ZERO;
+
+    let mut i = 0;
+    let read_number = |i: &mut usize| -> Result<f32, String> {
+        match tokens.get(*i) {
+            Some(PathToken::Number(number)) => {
+                *i += 1;
+                Ok(*number)
+            },
+            _ => Err(format!("expected a number in path data '{}'", d)),
+        }
+    };
+
+    while i < tokens.len() {
+        if let PathToken::Command(c) = tokens[i] {
+            i += 1;
+            if c == 'Z' || c == 'z' {
+                if !points.is_empty() {
+                    points.push(start);
+                    corners.push(points.len() - 1);
+                    subpaths.push(SvgSubpath { points: std::mem::take(&mut points), corners: std::mem::take(&mut corners), is_closed: true });
+                }
+                current = start;
+                command = None;
+            } else {
+                command = Some(c);
+            }
+            continue;
+        }
+
+        let c = command.ok_or(format!("expected a command in path data '{}'", d))?;
+        let offset = if c.is_ascii_lowercase() { current } else { Vec2::ZERO };
+        let read_point = |i: &mut usize| -> Result<Vec2, String> { Ok(offset + Vec2::new(read_number(i)?, read_number(i)?)) };
+
+        // drawing continues from the current point, eg. after a Z
+        if points.is_empty() && c.to_ascii_uppercase() != 'M' {
+            points.push(current);
+            corners = vec![0];
+        }
+
+        match c.to_ascii_uppercase() {
+            'M' => {
+                let pos = read_point(&mut i)?;
+                if points.len() > 1 {
+                    subpaths.push(SvgSubpath { points: std::mem::take(&mut points), corners: std::mem::take(&mut corners), is_closed: false });
+                }
+                points = vec![pos];
+                corners = vec![0];
+                current = pos;
+                start = pos;
+
+                // extra coordinates after a move are line tos
+                command = Some(if c == 'm' { 'l' } else { 'L' });
+            },
+            'L' => {
+                current = read_point(&mut i)?;
+                points.push(current);
+                corners.push(points.len() - 1);
+            },
+            'H' => {
+                let x = read_number(&mut i)?;
+                current.x = if c == 'h' { current.x + x } else { x };
+                points.push(current);
+                corners.push(points.len() - 1);
+            },
+            'V' => {
+                let y = read_number(&mut i)?;
+                current.y = if c == 'v' { current.y + y } else { y };
+                points.push(current);
+                corners.push(points.len() - 1);
+            },
+            'C' => {
+                let p1 = read_point(&mut i)?;
+                let p2 = read_point(&mut i)?;
+                let p3 = read_point(&mut i)?;
+                for s in 1..=SAMPLES_PER_CURVE {
+                    points.push(cubic_bezier_point(current, p1, p2, p3, s as f32 / SAMPLES_PER_CURVE as f32));
+                }
+                current = p3;
+                corners.push(points.len() - 1);
+            },
+            'Q' => {
+                let p1 = read_point(&mut i)?;
+                let p2 = read_point(&mut i)?;
+                for s in 1..=SAMPLES_PER_CURVE {
+                    points.push(quadratic_bezier_point(current, p1, p2, s as f32 / SAMPLES_PER_CURVE as f32));
+                }
+                current = p2;
+                corners.push(points.len() - 1);
+            },
+            _ => return Err(format!("unsupported path command '{}'", c)),
+        }
+    }
+
+    if points.len() > 1 {
+        subpaths.push(SvgSubpath { points, corners, is_closed: false });
+    }
+
+    Ok(subpaths)
+}
+
+/// Parse the paths out of an svg document, applying group transforms and inheriting fill and stroke from groups
+pub fn parse_svg_paths(svg: &str) -> Result<Vec<SvgPath>, String> {
+    // the svg defaults are a black fill and no stroke
+    let mut style_stack = vec![SvgStyle { transform: Affine2::IDENTITY, fill: Some(Color::srgb_u8(0, 0, 0)), stroke: None }];
+    let mut paths = vec![];
+
+    for tag in parse_tags(svg)? {
+        let is_group = tag.name == "g" || tag.name == "svg";
+        if !is_group && tag.name != "path" {
+            continue;
+        }
+
+        if tag.is_closing {
+            if is_group && style_stack.len() > 1 {
+                style_stack.pop();
+            }
+            continue;
+        }
+
+        let parent_style = style_stack[style_stack.len() - 1];
+        let style = SvgStyle {
+            transform: match tag.get_attribute("transform") {
+                Some(transform) => parent_style.transform * parse_transform(transform)?,
+                None => parent_style.transform,
+            },
+            fill: match tag.get_presentation_attribute("fill") {
+                Some(fill) => parse_colour(fill)?,
+                None => parent_style.fill,
+            },
+            stroke: match tag.get_presentation_attribute("stroke") {
+                Some(stroke) => parse_colour(stroke)?,
+                None => parent_style.stroke,
+            },
+        };
+
+        if is_group {
+            if !tag.is_self_closing {
+                style_stack.push(style);
+            }
+            continue;
+        }
+
+        paths.push(SvgPath {
+            subpaths: parse_path_data(tag.get_attribute("d").unwrap_or(""))?,
+            transform: style.transform,
+            fill: style.fill,
+            stroke: style.stroke,
+        });
+    }
+
+    Ok(paths)
+}
+
+/// Builds particles from the paths in an svg document, so level geometry can be drawn in a vector editor.
+/// Stroked paths become a line of static particles in the stroke colour, with a particle on every corner.
+/// Filled paths are filled with dynamic particles in the fill colour, with each subpath filled on its own (no holes).
+/// Other particle properties come from the ShapeBuilder particle template.
+pub struct SvgShape {
+    paths: Vec<SvgPath>,
+    transform: Affine2, // svg user units to ShapeBuilder local space
+    packing: Packing,
+}
+
+impl SvgShape {
+    /// Each svg user unit becomes scale in size. The y axis is flipped as svg y points down,
+    /// so the top left of the document is at the origin and the drawing is below it
+    pub fn parse(svg: &str, scale: f32) -> Result<Self, String> {
+        Ok(Self {
+            paths: parse_svg_paths(svg)?,
+            transform: Affine2::from_scale(Vec2::new(scale, -scale)),
+            packing: Packing::Square,
+        })
+    }
+
+    /// Load an svg file, eg. "assets/svg/hills_example.svg"
+    pub fn load(path: &str, scale: f32) -> Result<Self, String> {
+        let svg = std::fs::read_to_string(path).map_err(|error| format!("failed to read '{}': {}", path, error))?;
+        Self::parse(&svg, scale)
+    }
+
+    /// Replace the transform from svg user units to ShapeBuilder local space
+    pub fn with_transform(mut self, transform: Affine2) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// How filled paths are packed with particles
+    pub fn with_packing(mut self, packing: Packing) -> Self {
+        self.packing = packing;
+        self
+    }
+
+    pub fn paths(&self) -> &Vec<SvgPath> {
+        &self.paths
+    }
+}
+
+impl ShapeBuilderOperation for SvgShape {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let radius = shape_builder.particle_radius();
+        let particle_template = shape_builder.particle_template;
+
+        for path in self.paths.iter() {
+            let transform = self.transform * path.transform;
+
+            for subpath in path.subpaths.iter() {
+                let points = subpath.points.iter().map(|point| transform.transform_point2(*point)).collect::<Vec<Vec2>>();
+
+                if let Some(fill) = path.fill {
+                    let mut fill_template = particle_template;
+                    fill_template.set_static(false).set_color(fill);
+                    shape_builder.set_particle_template(fill_template)
+                        .apply_operation(Polygon::new(points.clone(), self.packing));
+                }
+
+                if let Some(stroke) = path.stroke {
+                    let transformed_subpath = SvgSubpath { points: points.clone(), corners: subpath.corners.clone(), is_closed: subpath.is_closed };
+                    let stroke_points = transformed_subpath.get_stroke_points_for_radius(radius);
+
+                    let mut stroke_template = particle_template;
+                    stroke_template.set_static(true).set_color(stroke);
+                    shape_builder.set_particle_template(stroke_template)
+                        .add_particles_from_points(&stroke_points);
+                }
+            }
+        }
+
+        shape_builder.set_particle_template(particle_template);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::particle::Particle;
+
+    use super::*;
+
+    #[test]
+    fn transforms() {
+        let transform = parse_transform("translate(10, 20) scale(2)").unwrap();
+        assert_eq!(transform.transform_point2(vec2(1.0, 1.0)), vec2(12.0, 22.0));
+
+        let transform = parse_transform("rotate(90 1 1)").unwrap();
+        assert!((transform.transform_point2(vec2(2.0, 1.0)) - vec2(1.0, 2.0)).length() < 0.0001);
+
+        let transform = parse_transform("matrix(1,0,0,1,5,6)").unwrap();
+        assert_eq!(transform.transform_point2(vec2(0.0, 0.0)), vec2(5.0, 6.0));
+
+        assert!(parse_transform("spin(10)").is_err());
+    }
+
+    #[test]
+    fn colours() {
+        assert_eq!(parse_colour("none").unwrap(), None);
+        assert_eq!(parse_colour("#ff0080").unwrap(), Some(Color::srgb_u8(255, 0, 128)));
+        assert_eq!(parse_colour("#f08").unwrap(), Some(Color::srgb_u8(255, 0, 136)));
+        assert_eq!(parse_colour("rgb(1, 2, 3)").unwrap(), Some(Color::srgb_u8(1, 2, 3)));
+        assert!(parse_colour("url(#gradient)").is_err());
+    }
+
+    #[test]
+    fn lines() {
+        let subpaths = parse_path_data("M0 0 L10 0 H20 V10 Z").unwrap();
+        assert_eq!(subpaths, vec![SvgSubpath { points: vec![vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(20.0, 0.0), vec2(20.0, 10.0), vec2(0.0, 0.0)], corners: vec![0, 1, 2, 3, 4], is_closed: true }]);
+
+        // relative commands, with implicit line tos after the move and no separators between numbers
+        let subpaths = parse_path_data("m1 1 2 0 0 2M0-5l1.5.5").unwrap();
+        assert_eq!(subpaths.len(), 2);
+        assert_eq!(subpaths[0].points, vec![vec2(1.0, 1.0), vec2(3.0, 1.0), vec2(3.0, 3.0)]);
+        assert_eq!(subpaths[1].points, vec![vec2(0.0, -5.0), vec2(1.5, -4.5)]);
+
+        assert!(parse_path_data("M0 0 A 1 1 0 0 0 2 2").is_err());
+        assert!(parse_path_data("M0 0 L 1").is_err());
+    }
+
+    #[test]
+    fn curves() {
+        let subpaths = parse_path_data("M0 0 C0 10 10 10 10 0 q5 -5 10 0").unwrap();
+        let points = &subpaths[0].points;
+        assert_eq!(points.len(), 1 + SAMPLES_PER_CURVE * 2);
+        assert_eq!(points[SAMPLES_PER_CURVE], vec2(10.0, 0.0));
+        assert_eq!(points[SAMPLES_PER_CURVE / 2], vec2(5.0, 7.5));
+        assert_eq!(points[points.len() - 1], vec2(20.0, 0.0));
+        assert_eq!(subpaths[0].corners, vec![0, SAMPLES_PER_CURVE, SAMPLES_PER_CURVE * 2]);
+    }
+
+    #[test]
+    fn stroke_keeps_corners() {
+        let svg = r##"<svg><path d="M0 0 L10 0 V10" stroke="#000000" fill="none" /></svg>"##;
+
+        // 10 does not divide evenly into diameters, so spacing by distance along the path would miss the corner
+        let mut b = ShapeBuilder::new();
+        b.set_particle_template(*Particle::default().set_radius(0.3))
+            .apply_operation(SvgShape::parse(svg, 1.0).unwrap());
+
+        assert!(b.particles.iter().any(|particle| particle.pos == vec2(10.0, 0.0)));
+        assert_eq!(b.particles[0].pos, vec2(0.0, 0.0));
+        assert_eq!(b.particles[b.particles.len() - 1].pos, vec2(10.0, -10.0));
+    }
+
+    #[test]
+    fn stroke_resamples_curves() {
+        let subpaths = parse_path_data("M0 0 L4 0 Q7 3 10 0").unwrap();
+        let stroke_points = subpaths[0].get_stroke_points_for_radius(0.5);
+
+        // the corner where the line meets the curve keeps its particle, and neighbours never overlap
+        assert!(stroke_points.contains(&vec2(4.0, 0.0)));
+        assert_eq!(stroke_points[stroke_points.len() - 1], vec2(10.0, 0.0));
+        assert!(stroke_points.windows(2).all(|pair| (pair[1] - pair[0]).length() >= 1.0 - 0.001));
+    }
+
+    #[test]
+    fn stroke_and_fill() {
+        let svg = r##"<?xml version="1.0" encoding="UTF-8"?>
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <!-- the ground -->
+                <g transform="translate(0, 10)" stroke="#ff0000">
+                    <path d="M0 0 H10" fill="none" />
+                </g>
+                <path d="M0 0 H4 V4 H0 Z" style="fill: blue; stroke: none"/>
+            </svg>"##;
+
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(SvgShape::parse(svg, 1.0).unwrap());
+
+        let ground = b.particles.iter().filter(|particle| particle.is_static).collect::<Vec<_>>();
+        assert_eq!(ground.len(), 11);
+        assert!(ground.iter().all(|particle| particle.pos.y == -10.0 && particle.color == Color::srgb_u8(255, 0, 0)));
+
+        let liquid = b.particles.iter().filter(|particle| !particle.is_static).collect::<Vec<_>>();
+        assert_eq!(liquid.len(), 16);
+        assert!(liquid.iter().all(|particle| particle.pos.y < 0.0 && particle.color == Color::srgb_u8(0, 0, 255)));
+
+        // the template is restored afterwards
+        assert!(!b.particle_template.is_static);
+    }
+
+    #[test]
+    fn load_svg() {
+        let svg_shape = SvgShape::load("assets/svg/hills_example.svg", 0.1).unwrap();
+        assert_eq!(svg_shape.paths().len(), 2);
+        assert!(svg_shape.paths().iter().all(|path| path.stroke.is_some()));
+    }
+}
@@ -3,7 +3,7 @@
 mod tests {
     use bevy::math::Vec2;
 
-    use crate::v5::{particle::Particle, particle_vec::SharedParticleVec, shape_builder::{circle::Circle, line_segment::LineSegment, shape_builder::ShapeBuilder}};
+    use crate::v5::{constraints::stick_constraint::StickConstraint, particle::Particle, particle_handle::ParticleHandle, particle_system::ParticleSystem, particle_vec::SharedParticleVec, shape_builder::{adjacent_sticks::AdjacentSticks, circle::Circle, line_segment::LineSegment, line_segment_collider::LineSegmentCollider, packing::Packing, rectangle::Rectangle, shape_builder::ShapeBuilder}};
 
     use super::*;
 
@@ -41,5 +41,136 @@ mod tests {
         assert_eq!(particle_vec.len(), b.particle_handles.len());
         assert_eq!(particle_vec.len(), b.particles.len());
     }
-}
 
+    #[test]
+    fn create_in_particle_system_offsets_constraints() {
+        let mut particle_system = ParticleSystem::default();
+
+        let mut b1 = ShapeBuilder::new();
+        b1.apply_operation(LineSegment::new(Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0)))
+            .create_in_particle_system(&mut particle_system);
+
+        let mut b2 = ShapeBuilder::new();
+        b2.apply_operation(LineSegment::new(Vec2::new(0.0, 5.0), Vec2::new(10.0, 5.0)))
+            .apply_operation(AdjacentSticks::new(StickConstraint::default(), 1, false))
+            .create_in_particle_system(&mut particle_system);
+
+        assert_eq!(b2.constraint_handles.len(), 9);
+
+        let stick = particle_system.constraint_container.constraints[0].as_any().downcast_ref::<StickConstraint>().unwrap();
+        assert_eq!(stick.particle_handles[0].id(), 10);
+        assert_eq!(stick.particle_handles[1].id(), 11);
+    }
+
+    #[test]
+    fn create_body_in_particle_system() {
+        let mut particle_system = ParticleSystem::default();
+
+        let mut b = ShapeBuilder::new();
+        let body_handle = b.apply_operation(LineSegment::new(Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0)))
+            .apply_operation(AdjacentSticks::new(StickConstraint::default(), 1, false))
+            .create_body_in_particle_system(&mut particle_system);
+
+        let body = particle_system.get_body(body_handle).unwrap();
+        assert_eq!(body.particle_handles, b.particle_handles);
+        assert_eq!(body.constraint_handles, b.constraint_handles);
+        assert_eq!(particle_system.particle_data.dynamic_particles.body_id[0], 1);
+    }
+
+    #[test]
+    fn line_segment_collider() {
+        let mut particle_system = ParticleSystem::default();
+
+        let mut b = ShapeBuilder::new();
+        b.set_particle_template(Particle::default().set_static(true).set_radius(0.25).clone())
+            .apply_operation(LineSegmentCollider::new(Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0)))
+            .create_in_particle_system(&mut particle_system);
+
+        assert_eq!(b.particles.len(), 0);
+        assert_eq!(b.static_collider_handles.len(), 1);
+        assert_eq!(particle_system.static_collider_container.len(), 1);
+    }
+
+    #[test]
+    fn rectangle_packing() {
+        // by default the grid starts on the corner of the rect
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Rectangle::from_corners(Vec2::new(0.0, 0.0), Vec2::new(4.0, 4.0)));
+        assert_eq!(b.particles.len(), 16);
+        assert_eq!(b.particles[0].pos, Vec2::new(0.0, 0.0));
+
+        // packed particles sit inside the rect
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Rectangle::from_corners(Vec2::new(0.0, 0.0), Vec2::new(4.0, 4.0)).with_packing(Packing::Hexagonal));
+        assert_eq!(b.particles.len(), 4 + 3 + 4 + 3);
+        assert_eq!(b.particles[0].pos, Vec2::new(0.5, 0.5));
+    }
+
+    #[test]
+    fn merge_removes_duplicates() {
+        let mut a = ShapeBuilder::new();
+        a.apply_operation(LineSegment::new(Vec2::new(0.0, 0.0), Vec2::new(3.0, 0.0)));
+
+        // shares its first particle with the last particle of a
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(LineSegment::new(Vec2::new(2.0, 0.001), Vec2::new(5.0, 0.001)))
+            .apply_operation(AdjacentSticks::new(StickConstraint::default(), 1, false));
+
+        a.merge(&b, 0.01);
+        assert_eq!(a.particles.len(), 5);
+        assert_eq!(a.constraints.len(), 2);
+        let stick = a.constraints[0].as_any().downcast_ref::<StickConstraint>().unwrap();
+        assert_eq!(stick.particle_handles, [ParticleHandle::new(2), ParticleHandle::new(3)]);
+    }
+
+    #[test]
+    fn merge_drops_collapsed_constraints() {
+        let mut a = ShapeBuilder::new();
+        a.add_particle_at_position(Vec2::new(0.0, 0.0));
+
+        // both ends of the stick are duplicates of the same particle in a
+        let mut b = ShapeBuilder::new();
+        b.add_particle_at_position(Vec2::new(0.0, 0.0))
+            .add_particle_at_position(Vec2::new(0.1, 0.0))
+            .apply_operation(AdjacentSticks::new(StickConstraint::default(), 1, false));
+        assert_eq!(b.constraints.len(), 1);
+
+        a.merge(&b, 0.2);
+        assert_eq!(a.particles.len(), 1);
+        assert!(a.constraints.is_empty());
+    }
+
+    #[test]
+    fn merge_removes_duplicates_within_other() {
+        let mut a = ShapeBuilder::new();
+
+        let mut b = ShapeBuilder::new();
+        b.add_particle_at_position(Vec2::new(0.0, 0.0))
+            .add_particle_at_position(Vec2::new(0.05, 0.0))
+            .add_particle_at_position(Vec2::new(1.0, 0.0))
+            .apply_operation(AdjacentSticks::new(StickConstraint::default(), 1, false));
+
+        a.merge(&b, 0.1);
+        assert_eq!(a.particles.len(), 2);
+        assert_eq!(a.constraints.len(), 1);
+        let stick = a.constraints[0].as_any().downcast_ref::<StickConstraint>().unwrap();
+        assert_eq!(stick.particle_handles, [ParticleHandle::new(0), ParticleHandle::new(1)]);
+    }
+
+    #[test]
+    fn extract_particles_with_constraints() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(LineSegment::new(Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0)))
+            .apply_operation(AdjacentSticks::new(StickConstraint::default(), 1, false));
+
+        let extracted = b.extract_particles(|particle| particle.pos.x >= 2.0);
+        assert_eq!(b.particles.len(), 2);
+        assert_eq!(extracted.particles.len(), 2);
+
+        // the stick joining the 2 halves is dropped
+        assert_eq!(b.constraints.len(), 1);
+        assert_eq!(extracted.constraints.len(), 1);
+        let stick = extracted.constraints[0].as_any().downcast_ref::<StickConstraint>().unwrap();
+        assert_eq!(stick.particle_handles, [ParticleHandle::new(0), ParticleHandle::new(1)]);
+    }
+}
@@ -0,0 +1,236 @@
+use bevy::math::Vec2;
+
+use crate::v5::constraints::stick_constraint::StickConstraint;
+
+use super::shape_builder::{ShapeBuilder, ShapeBuilderOperation};
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+
+/// Built in 5 x 7 bitmap font. Each row is 5 bits with the left most pixel in the highest bit, starting from the top row
+fn get_glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'D' => [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        ' ' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000],
+        '.' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100],
+        ',' => [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000],
+        '!' => [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100],
+        '-' => [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000],
+        '+' => [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000],
+        '=' => [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000],
+        ':' => [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+        '\'' => [0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000],
+        '/' => [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000],
+        // anything else is shown as a question mark
+        _ => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100],
+    }
+}
+
+fn is_glyph_pixel_set(glyph: &[u8; GLYPH_HEIGHT], x: usize, y_from_top: usize) -> bool {
+    glyph[y_from_top] & (1 << (GLYPH_WIDTH - 1 - x)) != 0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlignment {
+    Left,
+    Centre,
+    Right,
+}
+
+/// A string written in particles using a built in 5 x 7 bitmap font, eg. for start and finish banners.
+/// Each font pixel is filled with a square of particles at the template radius, so the glyph height is rounded
+/// down to a whole number of particles per font pixel (and is at least 7 particles tall).
+/// Lines are separated with '\n', and the first line sits on the origin.
+pub struct Text {
+    text: String,
+    origin: Vec2,
+    size: f32, // glyph height
+    spacing: Option<f32>, // gap between glyphs and lines. None = 1 font pixel
+    alignment: TextAlignment, // horizontal alignment of each line with the origin
+    stick_template: Option<StickConstraint>,
+}
+
+impl Text {
+    pub fn new(text: &str, origin: Vec2, size: f32) -> Self {
+        Self {
+            text: text.to_string(),
+            origin,
+            size,
+            spacing: None,
+            alignment: TextAlignment::Left,
+            stick_template: None,
+        }
+    }
+
+    pub fn with_spacing(mut self, spacing: f32) -> Self {
+        self.spacing = Some(spacing);
+        self
+    }
+
+    pub fn with_alignment(mut self, alignment: TextAlignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Join the particles in each glyph to their neighbours with sticks made from the stick template,
+    /// so each letter is a soft body that can be knocked over
+    pub fn with_sticks(mut self, stick_template: StickConstraint) -> Self {
+        self.stick_template = Some(stick_template);
+        self
+    }
+
+    /// Add the particles (and sticks) for a glyph with its bottom left corner at pos
+    fn add_glyph(&self, shape_builder: &mut ShapeBuilder, glyph: &[u8; GLYPH_HEIGHT], pos: Vec2, particles_per_pixel: usize) {
+        let diameter = shape_builder.particle_radius() * 2.0;
+        let width = GLYPH_WIDTH * particles_per_pixel;
+        let height = GLYPH_HEIGHT * particles_per_pixel;
+
+        // the particle index at each position in the glyph, from the bottom left
+        let mut particle_indices: Vec<Option<usize>> = vec![None; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                if !is_glyph_pixel_set(glyph, x / particles_per_pixel, GLYPH_HEIGHT - 1 - y / particles_per_pixel) {
+                    continue;
+                }
+
+                particle_indices[y * width + x] = Some(shape_builder.particles.len());
+                shape_builder.add_particle_at_position(pos + (Vec2::new(x as f32, y as f32) + Vec2::splat(0.5)) * diameter);
+            }
+        }
+
+        let Some(stick_template) = self.stick_template else { return };
+        let get_particle_index = |x: i32, y: i32| -> Option<usize> {
+            if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+                return None;
+            }
+            particle_indices[y as usize * width + x as usize]
+        };
+
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let Some(a) = get_particle_index(x, y) else { continue };
+
+                // right, up and both diagonals above, so each pair is only joined once
+                for (dx, dy) in [(1, 0), (0, 1), (1, 1), (-1, 1)] {
+                    let Some(b) = get_particle_index(x + dx, y + dy) else { continue };
+                    shape_builder.add_stick(stick_template, [a, b]);
+                }
+            }
+        }
+    }
+}
+
+impl ShapeBuilderOperation for Text {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        let diameter = shape_builder.particle_radius() * 2.0;
+
+        // allow for rounding errors, so a size of exactly n particles per pixel fits n
+        let particles_per_pixel = ((self.size / GLYPH_HEIGHT as f32 / diameter + 0.001) as usize).max(1);
+        let pixel_size = particles_per_pixel as f32 * diameter;
+        let spacing = self.spacing.unwrap_or(pixel_size);
+
+        let glyph_advance = GLYPH_WIDTH as f32 * pixel_size + spacing;
+        let line_advance = GLYPH_HEIGHT as f32 * pixel_size + spacing;
+
+        for (line_index, line) in self.text.lines().enumerate() {
+            let glyph_count = line.chars().count();
+            let line_width = if glyph_count > 0 { glyph_count as f32 * glyph_advance - spacing } else { 0.0 };
+            let x_offset = match self.alignment {
+                TextAlignment::Left => 0.0,
+                TextAlignment::Centre => -line_width * 0.5,
+                TextAlignment::Right => -line_width,
+            };
+
+            for (glyph_index, c) in line.chars().enumerate() {
+                let pos = self.origin + Vec2::new(x_offset + glyph_index as f32 * glyph_advance, -(line_index as f32) * line_advance);
+                self.add_glyph(shape_builder, &get_glyph(c), pos, particles_per_pixel);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+
+    #[test]
+    fn glyph_resolution() {
+        // 1 particle per font pixel
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Text::new("I", vec2(0.0, 0.0), 7.0));
+        assert_eq!(b.particles.len(), 11);
+        assert_eq!(b.particles[0].pos, vec2(1.5, 0.5));
+
+        // 2 x 2 particles per font pixel, and sizes in between round down
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Text::new("I", vec2(0.0, 0.0), 20.0));
+        assert_eq!(b.particles.len(), 44);
+
+        // too small still gets 1 particle per font pixel
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Text::new("I", vec2(0.0, 0.0), 1.0));
+        assert_eq!(b.particles.len(), 11);
+    }
+
+    #[test]
+    fn alignment_and_lines() {
+        // 2 glyphs 5 wide, with a gap of 1
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Text::new("--", vec2(0.0, 0.0), 7.0).with_alignment(TextAlignment::Centre));
+        assert_eq!(b.particles.len(), 10);
+        assert_eq!(b.particles[0].pos, vec2(-5.0, 3.5));
+        assert_eq!(b.particles[9].pos, vec2(5.0, 3.5));
+
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Text::new("-\n-", vec2(0.0, 0.0), 7.0).with_alignment(TextAlignment::Right).with_spacing(2.0));
+        assert_eq!(b.particles[0].pos, vec2(-4.5, 3.5));
+        assert_eq!(b.particles[5].pos, vec2(-4.5, 3.5 - 9.0));
+    }
+
+    #[test]
+    fn glyph_sticks() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(Text::new("-.", vec2(0.0, 0.0), 7.0).with_sticks(StickConstraint::default()));
+
+        // 4 sticks along the dash. The 2 x 2 dot has 2 across, 2 up and 2 diagonals, and nothing joins the 2 glyphs
+        assert_eq!(b.particles.len(), 5 + 4);
+        assert_eq!(b.constraints.len(), 4 + 6);
+    }
+}
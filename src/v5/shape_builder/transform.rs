@@ -0,0 +1,143 @@
+use bevy::{log::warn, math::{Affine2, Vec2}};
+
+use super::shape_builder::{ShapeBuilder, ShapeBuilderOperation};
+
+/// Moves the particles, constraints and static colliders already in the ShapeBuilder.
+/// The transform is in the local space of the ShapeBuilder transform stack, so eg. rotate_about uses a local point.
+/// Particle and capsule collider radii are never scaled, only their positions.
+/// SdfGrid colliders only support translation and uniform scaling, and are left unchanged with a warning by anything else.
+pub struct ShapeTransform {
+    transform: Affine2,
+}
+
+impl ShapeTransform {
+    pub fn new(transform: Affine2) -> Self {
+        Self { transform }
+    }
+
+    pub fn translate(offset: Vec2) -> Self {
+        Self::new(Affine2::from_translation(offset))
+    }
+
+    /// Rotate counter clockwise by angle radians around point
+    pub fn rotate_about(angle: f32, point: Vec2) -> Self {
+        Self::new(Affine2::from_translation(point) * Affine2::from_angle(angle) * Affine2::from_translation(-point))
+    }
+
+    pub fn scale_about(scale: Vec2, point: Vec2) -> Self {
+        Self::new(Affine2::from_translation(point) * Affine2::from_scale(scale) * Affine2::from_translation(-point))
+    }
+
+    /// Flip the x coordinates in the vertical line through x
+    pub fn mirror_x(x: f32) -> Self {
+        Self::scale_about(Vec2::new(-1.0, 1.0), Vec2::new(x, 0.0))
+    }
+
+    /// Flip the y coordinates in the horizontal line through y
+    pub fn mirror_y(y: f32) -> Self {
+        Self::scale_about(Vec2::new(1.0, -1.0), Vec2::new(0.0, y))
+    }
+}
+
+impl ShapeBuilderOperation for ShapeTransform {
+    fn apply_to_shape_builder(&self, shape_builder: &mut ShapeBuilder) {
+        // particles are stored in world space, so convert the local space transform to world space
+        let local_to_world = shape_builder.transform();
+        let transform = local_to_world * self.transform * local_to_world.inverse();
+
+        let positions = shape_builder.particles.iter().map(|particle| particle.pos).collect::<Vec<Vec2>>();
+
+        for particle in shape_builder.particles.iter_mut() {
+            particle.pos = transform.transform_point2(particle.pos);
+            particle.pos_prev = transform.transform_point2(particle.pos_prev);
+        }
+
+        for constraint in shape_builder.constraints.iter_mut() {
+            constraint.transform(transform.matrix2, &positions);
+        }
+
+        for static_collider in shape_builder.static_colliders.iter_mut() {
+            if let Err(error) = static_collider.transform(transform) {
+                warn!("ShapeTransform left a static collider unchanged: {}", error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use bevy::math::vec2;
+
+    use crate::v5::{constraints::{area_constraint::AreaConstraint, constraint::Constraint, stick_constraint::StickConstraint}, particle_handle::ParticleHandle, shape_builder::{adjacent_sticks::AdjacentSticks, line_segment::LineSegment, line_segment_collider::LineSegmentCollider}, static_collider::StaticColliderShape};
+
+    use super::*;
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!((a - b).length() < 0.0001, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn rotate_and_scale_sticks() {
+        let mut b = ShapeBuilder::new();
+        b.apply_operation(LineSegment::new(vec2(0.0, 0.0), vec2(3.0, 0.0)))
+            .apply_operation(AdjacentSticks::new(StickConstraint::default(), 1, false))
+            .apply_operation(ShapeTransform::rotate_about(PI * 0.5, vec2(1.0, 0.0)));
+
+        assert_near(b.particles[0].pos, vec2(1.0, -1.0));
+        assert_near(b.particles[2].pos, vec2(1.0, 1.0));
+
+        // stretching along y is now along the sticks
+        b.apply_operation(ShapeTransform::scale_about(vec2(1.0, 2.0), vec2(0.0, 0.0)));
+        let stick = b.constraints[0].as_any().downcast_ref::<StickConstraint>().unwrap();
+        assert!((stick.length - 2.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn mirror_flips_area() {
+        let points = vec![vec2(0.0, 0.0), vec2(2.0, 0.0), vec2(2.0, 2.0), vec2(0.0, 2.0)];
+
+        let mut b = ShapeBuilder::new();
+        b.add_particles_from_points(&points);
+        let handles = (0..points.len()).map(|i| ParticleHandle::new(i)).collect();
+        b.add_constraint(AreaConstraint::default().set_particles(handles, &points).box_clone());
+        b.apply_operation(ShapeTransform::mirror_x(5.0));
+
+        assert_near(b.particles[1].pos, vec2(8.0, 0.0));
+        let area = b.constraints[0].as_any().downcast_ref::<AreaConstraint>().unwrap();
+        assert_eq!(area.rest_area, -4.0);
+    }
+
+    #[test]
+    fn transform_stack() {
+        let mut b = ShapeBuilder::new();
+        b.add_particle_at_position(vec2(1.0, 0.0))
+            .push_transform(Affine2::from_translation(vec2(10.0, 0.0)))
+            .push_transform(Affine2::from_scale(vec2(-1.0, 1.0)))
+            .add_particle_at_position(vec2(1.0, 0.0))
+            .apply_operation(LineSegmentCollider::new(vec2(0.0, 0.0), vec2(2.0, 0.0)))
+            .pop_transform()
+            .add_particle_at_position(vec2(1.0, 0.0))
+            .pop_transform()
+            .add_particle_at_position(vec2(1.0, 0.0));
+
+        let positions = b.particles.iter().map(|particle| particle.pos).collect::<Vec<Vec2>>();
+        assert_eq!(positions, vec![vec2(1.0, 0.0), vec2(9.0, 0.0), vec2(11.0, 0.0), vec2(1.0, 0.0)]);
+
+        let StaticColliderShape::Capsule { a, b: capsule_b, .. } = b.static_colliders[0].shape else { panic!() };
+        assert_eq!(a, vec2(10.0, 0.0));
+        assert_eq!(capsule_b, vec2(8.0, 0.0));
+    }
+
+    #[test]
+    fn transform_in_local_space() {
+        let mut b = ShapeBuilder::new();
+        b.push_transform(Affine2::from_translation(vec2(10.0, 0.0)))
+            .add_particle_at_position(vec2(1.0, 0.0))
+            .apply_operation(ShapeTransform::rotate_about(PI, vec2(0.0, 0.0)));
+
+        // rotated about the local origin, which is (10, 0) in world space
+        assert_near(b.particles[0].pos, vec2(9.0, 0.0));
+    }
+}
@@ -74,6 +74,10 @@ impl SpatialHashParticleSolver {
                     }
                     collision_check[bi] = ai;
 
+                    if !particle_vec.should_collide_with(ai, &particle_vec, bi) {
+                        continue;
+                    }
+
                     let mut a_pos = particle_vec.get_pos_vec2(ai); //vec2(particle_vec.pos_x[ai], particle_vec.pos_y[ai]);
                     let b_pos = particle_vec.get_pos_vec2(bi); //vec2(particle_vec.pos_x[bi], particle_vec.pos_y[bi]);
                     
@@ -130,7 +134,10 @@ impl SpatialHashParticleSolver {
                         continue;
                     }
                     collision_check[bi] = ai;
-                    
+
+                    if !particle_vec.should_collide_with(ai, &particle_vec, bi) {
+                        continue;
+                    }
 
                     let mut a_pos = particle_vec.get_pos_vec2(ai); //vec2(particle_vec.pos_x[ai], particle_vec.pos_y[ai]);
                     let mut b_pos = particle_vec.get_pos_vec2(bi); //vec2(particle_vec.pos_x[bi], particle_vec.pos_y[bi]);
@@ -14,7 +14,9 @@ use crate::v5::simd_ext::f32x4Ext;
 use crate::v5::spatial_hash_simd_2::KeyIter;
 
 use super::aabb_simd::AabbSimd;
+use super::particle::COLLISION_MASK_NONE;
 use super::particle_data::ParticleData;
+use super::particle_solver::should_collide;
 use super::particle_vec::{ParticleVec, SharedParticleVec};
 use super::spatial_hash_simd::{SpatialHashSimd};
 use super::simd_ext::f32x2Ext;
@@ -309,6 +311,10 @@ impl SpatialHashSimdParticleSolver {
                 }
                 collision_check[bi] = ai;
 
+                if !dynamic_particles.should_collide_with(ai, static_particles, bi) {
+                    continue;
+                }
+
                 let mut a_pos = dynamic_particles.pos[ai]; //vec2(particle_vec.pos_x[ai], particle_vec.pos_y[ai]);
                 let b_pos = static_particles.pos[bi]; //vec2(particle_vec.pos_x[bi], particle_vec.pos_y[bi]);
                 
@@ -387,6 +393,10 @@ impl SpatialHashSimdParticleSolver {
                         }
                         collision_check[static_particle_idx] = dynamic_particle_idx;
 
+                        if !dynamic_particles.should_collide_with(dynamic_particle_idx, static_particles, static_particle_idx) {
+                            continue;
+                        }
+
                         unsafe {
                             let collision_axis = *dynamic_pos_ptr.offset(dynamic_idx) - *static_pos_ptr.offset(static_idx);
 
@@ -818,7 +828,10 @@ impl SpatialHashSimdParticleSolver {
                     continue;
                 }
                 collision_check[bi] = ai;
-                
+
+                if !dynamic_particles.should_collide_with(ai, dynamic_particles, bi) {
+                    continue;
+                }
 
                 let mut a_pos = dynamic_particles.pos[ai]; //vec2(particle_vec.pos_x[ai], particle_vec.pos_y[ai]);
                 let mut b_pos = dynamic_particles.pos[bi]; //vec2(particle_vec.pos_x[bi], particle_vec.pos_y[bi]);
@@ -954,7 +967,7 @@ impl SpatialHashSimdParticleSolver {
                     match entry {
                         Some(particle_idxs) => {
                             for p_idx in particle_idxs {
-                                if *p_idx > uidx_0 {
+                                if *p_idx > uidx_0 && dynamic_particles.should_collide_with(uidx_0, dynamic_particles, *p_idx) {
                                     particle_idxs_set.push(*p_idx);
                                 }
                             }
@@ -1084,7 +1097,9 @@ impl SpatialHashSimdParticleSolver {
                     match entry {
                         Some(particle_idxs) => {
                             for p_idx in particle_idxs {
-                                particle_idxs_set.push(*p_idx);
+                                if dynamic_particles.should_collide_with(uidx_0, static_particles, *p_idx) {
+                                    particle_idxs_set.push(*p_idx);
+                                }
                             }
                         },
                         None => {}
@@ -1242,6 +1257,19 @@ impl SpatialHashSimdParticleSolver {
         spatial_hash_keys_for_particles_keys(dynamic_particles, |uidx_0: usize, keys: &SmallVec::<[i32x2; 100]>| {
             let idx_0 = uidx_0 as isize;
 
+            let collision_group_0 = dynamic_particles.collision_group[uidx_0];
+            let collision_mask_0 = dynamic_particles.collision_mask[uidx_0];
+
+            // particles in the no collisions mask never collide, so skip the spatial hash lookups
+            if collision_mask_0 == COLLISION_MASK_NONE {
+                return;
+            }
+
+            // most particles have no exclusions, so only do the full check when needed.
+            // any particle in a body needs it, as the other particle may have self collision disabled
+            let has_collision_exclusions_0 = !dynamic_particles.collision_exclusions[uidx_0].is_empty()
+                || dynamic_particles.body_id[uidx_0] != 0;
+
             /*
             // testing problem with dynamic cols
             {
@@ -1301,6 +1329,14 @@ impl SpatialHashSimdParticleSolver {
                                 }
                                 dynamic_dynamic_collision_matrix[collision_matrix_idx] = true;
 
+                                if !should_collide(collision_group_0, collision_mask_0, dynamic_particles.collision_group[*p_idx], dynamic_particles.collision_mask[*p_idx]) {
+                                    continue;
+                                }
+
+                                if has_collision_exclusions_0 && dynamic_particles.is_collision_excluded(uidx_0, dynamic_particles, *p_idx) {
+                                    continue;
+                                }
+
                                 /*
                                 if *p_idx <= uidx_0 {
                                     continue;
@@ -1440,6 +1476,14 @@ impl SpatialHashSimdParticleSolver {
                                 }
                                 dynamic_static_collision_matrix[collision_matrix_idx] = true;
 
+                                if !should_collide(collision_group_0, collision_mask_0, static_particles.collision_group[*p_idx], static_particles.collision_mask[*p_idx]) {
+                                    continue;
+                                }
+
+                                if has_collision_exclusions_0 && dynamic_particles.is_collision_excluded(uidx_0, static_particles, *p_idx) {
+                                    continue;
+                                }
+
                                 let idx_1 = *p_idx as isize;
 /* 
                                 // avoid double checking against the same particle
@@ -0,0 +1,467 @@
+use std::simd::f32x2;
+
+use bevy::{math::{bounding::{Aabb2d, BoundingVolume}, IVec2, Vec2}, utils::HashSet};
+
+use super::{aabb_simd::AabbSimd, particle_data::ParticleData, particle_handle::ParticleHandle, spatial_hash_simd_2::SpatialHashSimd2, spatial_hash_simd_particle_solver::spatial_hash_keys_for_particles};
+
+// search areas are grown by this so a zero sized area on a cell boundary still covers a cell
+const QUERY_MARGIN: f32 = 0.001;
+
+/// A particle found by a spatial query
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpatialQueryParticle {
+    pub particle_handle: ParticleHandle,
+    pub pos: Vec2,
+    pub radius: f32,
+}
+
+/// Where a ray or circle cast hit a particle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    pub particle_handle: ParticleHandle,
+    pub point: Vec2, // on the surface of the particle
+    pub normal: Vec2, // surface normal of the particle at point
+    pub distance: f32, // how far along the ray the cast travelled before hitting
+}
+
+/// Walk the grid cells (of size 1) a ray passes through in order using a DDA (Amanatides & Woo).
+/// func is given each cell and the distance along the ray where the ray leaves it, and returns false to stop early.
+/// Nothing is walked if any input is not finite or max_distance is negative, as the walk would never end.
+pub fn for_each_cell_along_ray<F>(origin: Vec2, dir: Vec2, max_distance: f32, mut func: F)
+where
+    F: FnMut(IVec2, f32) -> bool
+{
+    if !origin.is_finite() || !dir.is_finite() || !max_distance.is_finite() || max_distance < 0.0 {
+        return;
+    }
+
+    let mut cell = origin.floor().as_ivec2();
+    let step = IVec2::new(dir.x.signum() as i32, dir.y.signum() as i32);
+
+    let distance_to_boundary = |pos: f32, cell: i32, dir: f32| -> f32 {
+        if dir > 0.0 {
+            (cell as f32 + 1.0 - pos) / dir
+        } else if dir < 0.0 {
+            (cell as f32 - pos) / dir
+        } else {
+            f32::INFINITY
+        }
+    };
+
+    let mut t_max = Vec2::new(distance_to_boundary(origin.x, cell.x, dir.x), distance_to_boundary(origin.y, cell.y, dir.y));
+    let t_delta = Vec2::new(1.0 / dir.x.abs(), 1.0 / dir.y.abs()); // infinite if dir is axis aligned
+
+    loop {
+        let t_exit = t_max.x.min(t_max.y);
+        if !func(cell, t_exit.min(max_distance)) || t_exit >= max_distance {
+            return;
+        }
+
+        if t_max.x < t_max.y {
+            cell.x += step.x;
+            t_max.x += t_delta.x;
+        } else {
+            cell.y += step.y;
+            t_max.y += t_delta.y;
+        }
+    }
+}
+
+/// The distances along the ray where it enters and leaves the aabb, if it passes through it at all.
+/// The enter distance is negative if origin is inside the aabb
+fn ray_aabb_intersection(origin: Vec2, dir: Vec2, aabb: Aabb2d) -> Option<(f32, f32)> {
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    for axis in 0..2 {
+        if dir[axis] == 0.0 {
+            if origin[axis] < aabb.min[axis] || origin[axis] > aabb.max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let t1 = (aabb.min[axis] - origin[axis]) / dir[axis];
+        let t2 = (aabb.max[axis] - origin[axis]) / dir[axis];
+        t_enter = t_enter.max(t1.min(t2));
+        t_exit = t_exit.min(t1.max(t2));
+    }
+
+    if t_enter > t_exit { None } else { Some((t_enter, t_exit)) }
+}
+
+/// Cast a circle of radius cast_radius from origin along the unit length dir and find where it first touches the particle, if at all.
+/// A cast_radius of 0 is a ray cast. If the cast starts overlapping the particle it hits at distance 0.
+fn cast_against_particle(origin: Vec2, dir: Vec2, cast_radius: f32, max_distance: f32, particle: &SpatialQueryParticle) -> Option<RaycastHit> {
+    let combined_radius = cast_radius + particle.radius;
+    let m = origin - particle.pos;
+    let b = m.dot(dir);
+    let c = m.length_squared() - combined_radius * combined_radius;
+
+    // starting outside and moving away
+    if c > 0.0 && b > 0.0 {
+        return None;
+    }
+
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let distance = (-b - discriminant.sqrt()).max(0.0);
+    if distance > max_distance {
+        return None;
+    }
+
+    let cast_pos = origin + dir * distance;
+    let normal = (cast_pos - particle.pos).try_normalize().unwrap_or(-dir);
+    Some(RaycastHit {
+        particle_handle: particle.particle_handle,
+        point: particle.pos + normal * particle.radius,
+        normal,
+        distance,
+    })
+}
+
+/// Answers gameplay questions like "which particles are within 2m of the car".
+///
+/// Unlike the spatial hashes in SpatialHashSimdParticleSolver, which key by ParticleVec index and
+/// are rebuilt part way through each sub step, this keys by ParticleHandle and covers both static and
+/// dynamic particles. The ParticleSystem rebuilds it on the first query after an update or after particles are added,
+/// so results stay valid between steps.
+///
+/// Particles are filtered by layer mask: a particle is included if (collision_group & layer_mask) != 0.
+pub struct SpatialQuery {
+    pub spatial_hash: SpatialHashSimd2<ParticleHandle>,
+    pub occupied_aabb: Option<Aabb2d>, // covers every cell holding a particle. None if there are no particles
+}
+
+impl Default for SpatialQuery {
+    fn default() -> Self {
+        Self {
+            spatial_hash: SpatialHashSimd2::new(),
+            occupied_aabb: None,
+        }
+    }
+}
+
+impl SpatialQuery {
+
+    /// Re-insert all enabled particles into the spatial hash
+    pub fn rebuild(&mut self, particle_data: &ParticleData) {
+        self.spatial_hash.soft_clear();
+
+        let mut cell_min = IVec2::MAX;
+        let mut cell_max = IVec2::MIN;
+        for particle_vec in [&particle_data.static_particles, &particle_data.dynamic_particles] {
+            spatial_hash_keys_for_particles(particle_vec, |key, particle_idx| {
+                self.spatial_hash.map.entry(key).or_default().push(particle_vec.particle_handle[particle_idx]);
+
+                let cell = IVec2::new(key[0], key[1]);
+                cell_min = cell_min.min(cell);
+                cell_max = cell_max.max(cell);
+            });
+        }
+
+        // cells are 1 unit in size
+        self.occupied_aabb = if cell_min.x <= cell_max.x {
+            Some(Aabb2d { min: cell_min.as_vec2(), max: (cell_max + IVec2::ONE).as_vec2() })
+        } else {
+            None
+        };
+    }
+
+    fn aabb_handles(&self, aabb: Aabb2d) -> impl Iterator<Item = ParticleHandle> + '_ {
+        let aabb_simd = AabbSimd::from_min_max(f32x2::from_array([aabb.min.x, aabb.min.y]), f32x2::from_array([aabb.max.x, aabb.max.y]));
+        self.spatial_hash.aabb_iter(&aabb_simd)
+    }
+
+    fn filter_particle(&self, particle_data: &ParticleData, particle_handle: ParticleHandle, layer_mask: u32) -> Option<SpatialQueryParticle> {
+        if !particle_data.is_enabled(particle_handle) || (particle_data.get_collision_group(particle_handle) & layer_mask) == 0 {
+            return None;
+        }
+
+        Some(SpatialQueryParticle {
+            particle_handle,
+            pos: particle_data.get_pos_vec2(particle_handle),
+            radius: particle_data.get_radius(particle_handle),
+        })
+    }
+
+    /// Find the particles in the grid cells covered by aabb that match the layer mask.
+    /// Each particle is returned once, ordered by handle.
+    pub fn candidates(&self, particle_data: &ParticleData, aabb: Aabb2d, layer_mask: u32) -> Vec<SpatialQueryParticle> {
+        let mut particle_handles = self.aabb_handles(aabb).collect::<Vec<ParticleHandle>>();
+        particle_handles.sort_unstable_by_key(|handle| handle.id());
+        particle_handles.dedup();
+
+        particle_handles.into_iter()
+            .filter_map(|handle| self.filter_particle(particle_data, handle, layer_mask))
+            .collect()
+    }
+
+    /// Particles that overlap the aabb
+    pub fn query_aabb(&self, particle_data: &ParticleData, aabb: Aabb2d, layer_mask: u32) -> Vec<ParticleHandle> {
+        self.candidates(particle_data, aabb.grow(Vec2::splat(QUERY_MARGIN)), layer_mask).into_iter()
+            .filter(|particle| {
+                let closest_point = particle.pos.clamp(aabb.min, aabb.max);
+                (closest_point - particle.pos).length_squared() <= particle.radius * particle.radius
+            })
+            .map(|particle| particle.particle_handle)
+            .collect()
+    }
+
+    /// Particles that overlap the circle
+    pub fn query_circle(&self, particle_data: &ParticleData, centre: Vec2, radius: f32, layer_mask: u32) -> Vec<ParticleHandle> {
+        self.candidates(particle_data, Aabb2d::new(centre, Vec2::splat(radius + QUERY_MARGIN)), layer_mask).into_iter()
+            .filter(|particle| {
+                let max_distance = radius + particle.radius;
+                (particle.pos - centre).length_squared() <= max_distance * max_distance
+            })
+            .map(|particle| particle.particle_handle)
+            .collect()
+    }
+
+    /// Particles that contain point
+    pub fn query_point(&self, particle_data: &ParticleData, point: Vec2, layer_mask: u32) -> Vec<ParticleHandle> {
+        self.query_circle(particle_data, point, 0.0, layer_mask)
+    }
+
+    /// Up to k particles whose centres are closest to point and no more than max_distance away, nearest first
+    pub fn query_k_nearest(&self, particle_data: &ParticleData, point: Vec2, k: usize, max_distance: f32, layer_mask: u32) -> Vec<ParticleHandle> {
+        let mut particles = self.candidates(particle_data, Aabb2d::new(point, Vec2::splat(max_distance + QUERY_MARGIN)), layer_mask).into_iter()
+            .map(|particle| (particle.particle_handle, (particle.pos - point).length_squared()))
+            .filter(|(_, distance_squared)| *distance_squared <= max_distance * max_distance)
+            .collect::<Vec<(ParticleHandle, f32)>>();
+
+        particles.sort_by(|a, b| a.1.total_cmp(&b.1));
+        particles.into_iter().take(k).map(|(handle, _)| handle).collect()
+    }
+
+    /// Cast a circle (or a ray if cast_radius is 0) and return the hits ordered by distance.
+    /// Walks the grid cells along the ray, checking each cell grown by cast_radius, so only particles near the ray are tested.
+    /// If first_hit_only, stops as soon as no later cell can contain a closer hit.
+    /// max_distance can be f32::INFINITY to cast until something is hit. Returns no hits if max_distance is NaN or negative.
+    pub fn cast(&self, particle_data: &ParticleData, origin: Vec2, dir: Vec2, cast_radius: f32, max_distance: f32, layer_mask: u32, first_hit_only: bool) -> Vec<RaycastHit> {
+        debug_assert!(cast_radius >= 0.0);
+        if max_distance.is_nan() || max_distance < 0.0 || !origin.is_finite() {
+            return vec![];
+        }
+        let Some(dir) = dir.try_normalize() else { return vec![] };
+
+        // only walk the part of the ray that passes through cells holding particles, so a long (or infinite) ray does not walk empty space
+        let Some(occupied_aabb) = self.occupied_aabb else { return vec![] };
+        let Some((t_enter, t_leave)) = ray_aabb_intersection(origin, dir, occupied_aabb.grow(Vec2::splat(cast_radius))) else { return vec![] };
+        let t_start = t_enter.max(0.0);
+        let t_end = t_leave.min(max_distance);
+        if t_start > t_end {
+            return vec![];
+        }
+
+        let mut hits: Vec<RaycastHit> = vec![];
+        let mut tested = HashSet::<ParticleHandle>::default();
+
+        for_each_cell_along_ray(origin + dir * t_start, dir, t_end - t_start, |cell, t_exit| {
+            let t_exit = t_start + t_exit;
+
+            let cell_min = cell.as_vec2();
+            let aabb = Aabb2d { min: cell_min, max: cell_min + Vec2::ONE }.grow(Vec2::splat(cast_radius));
+
+            for particle_handle in self.aabb_handles(aabb) {
+                if !tested.insert(particle_handle) {
+                    continue;
+                }
+
+                let Some(particle) = self.filter_particle(particle_data, particle_handle, layer_mask) else { continue };
+                if let Some(hit) = cast_against_particle(origin, dir, cast_radius, max_distance, &particle) {
+                    hits.push(hit);
+                }
+            }
+
+            // any hit in a later cell is further along the ray than where we leave this cell
+            !(first_hit_only && hits.iter().any(|hit| hit.distance <= t_exit))
+        });
+
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        if first_hit_only {
+            hits.truncate(1);
+        }
+        hits
+    }
+
+    /// The first particle hit by a ray
+    pub fn raycast(&self, particle_data: &ParticleData, origin: Vec2, dir: Vec2, max_distance: f32, layer_mask: u32) -> Option<RaycastHit> {
+        self.cast(particle_data, origin, dir, 0.0, max_distance, layer_mask, true).first().copied()
+    }
+
+    /// Every particle hit by a ray, nearest first
+    pub fn raycast_all(&self, particle_data: &ParticleData, origin: Vec2, dir: Vec2, max_distance: f32, layer_mask: u32) -> Vec<RaycastHit> {
+        self.cast(particle_data, origin, dir, 0.0, max_distance, layer_mask, false)
+    }
+
+    /// The first particle hit by a circle swept along a ray
+    pub fn circle_cast(&self, particle_data: &ParticleData, origin: Vec2, dir: Vec2, radius: f32, max_distance: f32, layer_mask: u32) -> Option<RaycastHit> {
+        self.cast(particle_data, origin, dir, radius, max_distance, layer_mask, true).first().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::{particle::{Particle, COLLISION_MASK_ALL}, particle_system::ParticleSystem};
+
+    use super::*;
+
+    fn add_row_of_particles(particle_system: &mut ParticleSystem) -> Vec<ParticleHandle> {
+        particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+            *Particle::default().set_position(vec2(2.0, 0.0)).set_static(true),
+            *Particle::default().set_position(vec2(4.0, 0.0)).set_collision_group(0x2),
+            *Particle::default().set_position(vec2(6.0, 0.0)),
+        ])
+    }
+
+    #[test]
+    fn query_aabb_and_circle() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = add_row_of_particles(&mut particle_system);
+
+        let aabb = Aabb2d { min: vec2(1.8, -1.0), max: vec2(4.0, 1.0) };
+        assert_eq!(particle_system.query_aabb(aabb, COLLISION_MASK_ALL), vec![handles[1], handles[2]]);
+        assert_eq!(particle_system.query_circle(vec2(3.0, 0.0), 0.6, COLLISION_MASK_ALL), vec![handles[1], handles[2]]);
+        assert_eq!(particle_system.query_circle(vec2(3.0, 0.0), 0.4, COLLISION_MASK_ALL), vec![]);
+    }
+
+    #[test]
+    fn query_point_and_layer_mask() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = add_row_of_particles(&mut particle_system);
+
+        assert_eq!(particle_system.query_point(vec2(4.2, 0.1), COLLISION_MASK_ALL), vec![handles[2]]);
+        assert_eq!(particle_system.query_point(vec2(4.2, 0.1), 0x1), vec![]);
+        assert_eq!(particle_system.query_point(vec2(5.0, 0.0), COLLISION_MASK_ALL), vec![]);
+    }
+
+    #[test]
+    fn query_k_nearest() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = add_row_of_particles(&mut particle_system);
+
+        assert_eq!(particle_system.query_k_nearest(vec2(5.5, 0.0), 2, 10.0, COLLISION_MASK_ALL), vec![handles[3], handles[2]]);
+        assert_eq!(particle_system.query_k_nearest(vec2(5.5, 0.0), 2, 10.0, 0x1), vec![handles[3], handles[1]]);
+        assert_eq!(particle_system.query_k_nearest(vec2(5.5, 0.0), 10, 1.0, COLLISION_MASK_ALL), vec![handles[3]]);
+    }
+
+    #[test]
+    fn walk_cells_along_ray() {
+        let mut cells = vec![];
+        for_each_cell_along_ray(vec2(0.5, 0.5), vec2(1.0, 1.0).normalize(), 2.0, |cell, _| {
+            cells.push(cell);
+            true
+        });
+        assert_eq!(cells, vec![IVec2::new(0, 0), IVec2::new(0, 1), IVec2::new(1, 1)]);
+
+        let mut cells = vec![];
+        for_each_cell_along_ray(vec2(0.5, 0.5), vec2(-1.0, 0.0), 1.8, |cell, _| {
+            cells.push(cell);
+            true
+        });
+        assert_eq!(cells, vec![IVec2::new(0, 0), IVec2::new(-1, 0), IVec2::new(-2, 0)]);
+    }
+
+    #[test]
+    fn raycast() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = add_row_of_particles(&mut particle_system);
+
+        let hit = particle_system.raycast(vec2(-2.0, 0.0), vec2(1.0, 0.0), 100.0, COLLISION_MASK_ALL).unwrap();
+        assert_eq!(hit.particle_handle, handles[0]);
+        assert_eq!(hit.point, vec2(-0.5, 0.0));
+        assert_eq!(hit.normal, vec2(-1.0, 0.0));
+        assert_eq!(hit.distance, 1.5);
+
+        // from the other direction, filtered by layer mask
+        let hit = particle_system.raycast(vec2(10.0, 0.0), vec2(-1.0, 0.0), 100.0, 0x2).unwrap();
+        assert_eq!(hit.particle_handle, handles[2]);
+        assert_eq!(hit.distance, 5.5);
+
+        assert!(particle_system.raycast(vec2(-2.0, 0.0), vec2(1.0, 0.0), 1.0, COLLISION_MASK_ALL).is_none());
+        assert!(particle_system.raycast(vec2(-2.0, 1.0), vec2(1.0, 0.0), 100.0, COLLISION_MASK_ALL).is_none());
+    }
+
+    #[test]
+    fn raycast_all() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = add_row_of_particles(&mut particle_system);
+
+        let hits = particle_system.raycast_all(vec2(-2.0, 0.0), vec2(1.0, 0.0), 100.0, COLLISION_MASK_ALL);
+        assert_eq!(hits.iter().map(|hit| hit.particle_handle).collect::<Vec<ParticleHandle>>(), handles);
+        assert_eq!(hits.iter().map(|hit| hit.distance).collect::<Vec<f32>>(), vec![1.5, 3.5, 5.5, 7.5]);
+    }
+
+    #[test]
+    fn cast_distances() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = add_row_of_particles(&mut particle_system);
+
+        // an infinite ray only walks the cells holding particles
+        let hit = particle_system.raycast(vec2(-2.0, 0.0), vec2(1.0, 0.0), f32::INFINITY, COLLISION_MASK_ALL).unwrap();
+        assert_eq!(hit.particle_handle, handles[0]);
+        assert_eq!(hit.distance, 1.5);
+        assert_eq!(particle_system.raycast_all(vec2(-2.0, 0.0), vec2(1.0, 0.0), f32::INFINITY, COLLISION_MASK_ALL).len(), 4);
+
+        assert!(particle_system.raycast_all(vec2(-2.0, 0.0), vec2(1.0, 0.0), f32::NAN, COLLISION_MASK_ALL).is_empty());
+        assert!(particle_system.raycast_all(vec2(-2.0, 0.0), vec2(1.0, 0.0), -1.0, COLLISION_MASK_ALL).is_empty());
+
+        // a huge distance only walks the cells holding particles
+        let hits = particle_system.raycast_all(vec2(-2.0, 0.0), vec2(1.0, 0.0), 1.0e9, COLLISION_MASK_ALL);
+        assert_eq!(hits.iter().map(|hit| hit.particle_handle).collect::<Vec<ParticleHandle>>(), handles);
+
+        let mut cells = 0;
+        for_each_cell_along_ray(vec2(0.0, 0.0), vec2(1.0, 0.0), f32::INFINITY, |_, _| {
+            cells += 1;
+            true
+        });
+        assert_eq!(cells, 0);
+    }
+
+    #[test]
+    fn circle_cast() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = add_row_of_particles(&mut particle_system);
+
+        // a ray passing above the particles misses, but a circle of radius 0.5 touches them
+        assert!(particle_system.raycast(vec2(-2.0, 0.9), vec2(1.0, 0.0), 100.0, COLLISION_MASK_ALL).is_none());
+
+        let hit = particle_system.circle_cast(vec2(2.5, 5.0), vec2(0.0, -1.0), 0.5, 100.0, COLLISION_MASK_ALL).unwrap();
+        assert!(hit.particle_handle == handles[1] || hit.particle_handle == handles[2]);
+        assert!((hit.distance - (5.0 - 0.75_f32.sqrt())).abs() < 0.0001);
+        assert!(((hit.point - particle_system.particle_data.get_pos_vec2(hit.particle_handle)).length() - 0.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn rebuilds_lazily() {
+        let mut particle_system = ParticleSystem::default();
+        particle_system.add_particles(&vec![*Particle::default().set_position(vec2(0.0, 0.0))]);
+        let handles = particle_system.add_particles(&vec![*Particle::default().set_position(vec2(3.0, 0.0))]);
+
+        // adding particles only marks the spatial hash as out of date, the first query rebuilds it
+        assert!(particle_system.spatial_query.occupied_aabb.is_none());
+        assert_eq!(particle_system.query_circle(vec2(3.0, 0.0), 0.5, COLLISION_MASK_ALL), handles);
+        assert!(particle_system.spatial_query.occupied_aabb.is_some());
+    }
+
+    #[test]
+    fn handles_stay_valid_after_update() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = add_row_of_particles(&mut particle_system);
+
+        // disabling a particle swaps the others around inside ParticleData, but handles are unchanged
+        particle_system.particle_data.set_enabled(handles[0], false);
+        particle_system.pre_update();
+        particle_system.update(1.0 / 60.0);
+
+        assert_eq!(particle_system.query_circle(vec2(0.0, 0.0), 1.0, COLLISION_MASK_ALL), vec![]);
+        assert_eq!(particle_system.query_k_nearest(vec2(6.0, 0.0), 1, 1.0, COLLISION_MASK_ALL), vec![handles[3]]);
+    }
+}
@@ -0,0 +1,476 @@
+use bevy::math::{bounding::{Aabb2d, BoundingVolume, IntersectsVolume}, Affine2, Mat2, Vec2};
+
+use super::{constraints::area_constraint::compute_signed_area, particle::{COLLISION_GROUP_DEFAULT, COLLISION_MASK_ALL}, particle_data::ParticleData, particle_handle::StaticColliderHandle, particle_solver::should_collide};
+
+/// The closest point on the line segment a -> b to pos
+pub fn closest_point_on_line_segment(a: Vec2, b: Vec2, pos: Vec2) -> Vec2 {
+    let edge = b - a;
+    let t = ((pos - a).dot(edge) / edge.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+    a + edge * t
+}
+
+/// A grid of signed distances, sampled with bilinear interpolation.
+/// Negative values are inside the terrain, positive values are outside.
+#[derive(Debug, Clone)]
+pub struct SdfGrid {
+    pub origin: Vec2, // position of the first sample (the bottom left corner)
+    pub cell_size: f32, // distance between samples
+    pub width: usize, // number of samples along x
+    pub height: usize, // number of samples along y
+    pub distances: Vec<f32>, // row major, starting from the bottom row
+}
+
+impl SdfGrid {
+    /// The grid is always axis aligned, so StaticCollider::transform can only translate and uniformly scale it.
+    /// Rotations, mirrors and non-uniform scales are rejected with an error.
+    pub fn new(origin: Vec2, cell_size: f32, width: usize, height: usize, distances: Vec<f32>) -> Self {
+        debug_assert!(cell_size > 0.0);
+        debug_assert!(width >= 2 && height >= 2);
+        debug_assert!(distances.len() == width * height);
+        Self { origin, cell_size, width, height, distances }
+    }
+
+    /// Build a grid by sampling a signed distance function at each grid point
+    pub fn from_fn<F: Fn(Vec2) -> f32>(origin: Vec2, cell_size: f32, width: usize, height: usize, distance_fn: F) -> Self {
+        let mut distances = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                distances.push(distance_fn(origin + Vec2::new(x as f32, y as f32) * cell_size));
+            }
+        }
+        Self::new(origin, cell_size, width, height, distances)
+    }
+
+    pub fn compute_aabb(&self) -> Aabb2d {
+        Aabb2d {
+            min: self.origin,
+            max: self.origin + Vec2::new((self.width - 1) as f32, (self.height - 1) as f32) * self.cell_size,
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> f32 {
+        self.distances[y * self.width + x]
+    }
+
+    /// Bilinear sample of the distance at pos. None outside the grid
+    pub fn sample(&self, pos: Vec2) -> Option<f32> {
+        let grid_pos = (pos - self.origin) / self.cell_size;
+        let max = Vec2::new((self.width - 1) as f32, (self.height - 1) as f32);
+        if grid_pos.x < 0.0 || grid_pos.y < 0.0 || grid_pos.x > max.x || grid_pos.y > max.y {
+            return None;
+        }
+
+        // clamp so the sample at the top/right edge uses the last cell
+        let x = (grid_pos.x.floor() as usize).min(self.width - 2);
+        let y = (grid_pos.y.floor() as usize).min(self.height - 2);
+        let fraction = grid_pos - Vec2::new(x as f32, y as f32);
+
+        let bottom = self.get(x, y) + (self.get(x + 1, y) - self.get(x, y)) * fraction.x;
+        let top = self.get(x, y + 1) + (self.get(x + 1, y + 1) - self.get(x, y + 1)) * fraction.x;
+        Some(bottom + (top - bottom) * fraction.y)
+    }
+
+    /// Direction of increasing distance at pos, found with central differences
+    pub fn gradient(&self, pos: Vec2) -> Option<Vec2> {
+        let h = self.cell_size * 0.5;
+        let sample = |offset: Vec2| self.sample(pos + offset).or(self.sample(pos));
+        let dx = sample(Vec2::new(h, 0.0))? - sample(Vec2::new(-h, 0.0))?;
+        let dy = sample(Vec2::new(0.0, h))? - sample(Vec2::new(0.0, -h))?;
+        Vec2::new(dx, dy).try_normalize()
+    }
+}
+
+/// The geometry of a static collider
+#[derive(Debug, Clone)]
+pub enum StaticColliderShape {
+    /// A line segment with rounded ends. A radius of 0 is a thin line
+    Capsule { a: Vec2, b: Vec2, radius: f32 },
+
+    /// Points in counter clockwise order. Use StaticCollider::convex_polygon to fix the winding order
+    ConvexPolygon(Vec<Vec2>),
+
+    SdfGrid(SdfGrid),
+}
+
+impl StaticColliderShape {
+    pub fn compute_aabb(&self) -> Aabb2d {
+        match self {
+            StaticColliderShape::Capsule { a, b, radius } => Aabb2d { min: a.min(*b) - Vec2::splat(*radius), max: a.max(*b) + Vec2::splat(*radius) },
+            StaticColliderShape::ConvexPolygon(points) => {
+                let min = points.iter().fold(Vec2::splat(f32::MAX), |min, point| min.min(*point));
+                let max = points.iter().fold(Vec2::splat(f32::MIN), |max, point| max.max(*point));
+                Aabb2d { min, max }
+            },
+            StaticColliderShape::SdfGrid(sdf_grid) => sdf_grid.compute_aabb(),
+        }
+    }
+
+    /// Signed distance from the surface of the shape to pos, and the surface normal pointing away from the shape
+    pub fn compute_distance_and_normal(&self, pos: Vec2) -> Option<(f32, Vec2)> {
+        match self {
+            StaticColliderShape::Capsule { a, b, radius } => {
+                let delta = pos - closest_point_on_line_segment(*a, *b, pos);
+                let distance = delta.length();
+                let normal = delta.try_normalize().unwrap_or((*b - *a).perp().normalize_or_zero());
+                Some((distance - radius, normal))
+            },
+            StaticColliderShape::ConvexPolygon(points) => {
+                let count = points.len();
+                if count < 3 {
+                    return None;
+                }
+
+                // if inside, the nearest edge is the one we are furthest in front of
+                let mut max_separation = f32::MIN;
+                let mut max_separation_normal = Vec2::ZERO;
+                for i in 0..count {
+                    let a = points[i];
+                    let b = points[(i + 1) % count];
+                    let normal = -(b - a).perp().normalize_or_zero(); // outward for counter clockwise winding
+                    let separation = normal.dot(pos - a);
+                    if separation > max_separation {
+                        max_separation = separation;
+                        max_separation_normal = normal;
+                    }
+                }
+
+                if max_separation <= 0.0 {
+                    return Some((max_separation, max_separation_normal));
+                }
+
+                // outside, so use the closest point on the boundary which handles corners correctly
+                let closest_point = (0..count)
+                    .map(|i| closest_point_on_line_segment(points[i], points[(i + 1) % count], pos))
+                    .min_by(|a, b| (pos - *a).length_squared().total_cmp(&(pos - *b).length_squared()))?;
+                let delta = pos - closest_point;
+                Some((delta.length(), delta.try_normalize().unwrap_or(max_separation_normal)))
+            },
+            StaticColliderShape::SdfGrid(sdf_grid) => {
+                let distance = sdf_grid.sample(pos)?;
+                let normal = sdf_grid.gradient(pos)?;
+                Some((distance, normal))
+            },
+        }
+    }
+}
+
+/// A non-particle static surface that dynamic particles collide with, eg. ground and terrain.
+/// Much cheaper than stamping a surface out of static particles, and smooth so wheels do not catch on it.
+#[derive(Debug, Clone)]
+pub struct StaticCollider {
+    pub shape: StaticColliderShape,
+    pub aabb: Aabb2d, // cached bounds of the shape
+    pub collision_group: u32,
+    pub collision_mask: u32,
+    pub is_enabled: bool,
+}
+
+impl StaticCollider {
+    pub fn new(shape: StaticColliderShape) -> Self {
+        let aabb = shape.compute_aabb();
+        Self {
+            shape,
+            aabb,
+            collision_group: COLLISION_GROUP_DEFAULT,
+            collision_mask: COLLISION_MASK_ALL,
+            is_enabled: true,
+        }
+    }
+
+    pub fn capsule(a: Vec2, b: Vec2, radius: f32) -> Self {
+        Self::new(StaticColliderShape::Capsule { a, b, radius })
+    }
+
+    /// Create a convex polygon collider from points in either winding order
+    pub fn convex_polygon(mut points: Vec<Vec2>) -> Self {
+        if compute_signed_area(&points) < 0.0 {
+            points.reverse();
+        }
+        Self::new(StaticColliderShape::ConvexPolygon(points))
+    }
+
+    /// An axis aligned grid collider. See SdfGrid::new for the transforms it supports
+    pub fn sdf_grid(sdf_grid: SdfGrid) -> Self {
+        Self::new(StaticColliderShape::SdfGrid(sdf_grid))
+    }
+
+    pub fn set_collision_group(&mut self, collision_group: u32) -> &mut Self {
+        self.collision_group = collision_group;
+        self
+    }
+
+    pub fn set_collision_mask(&mut self, collision_mask: u32) -> &mut Self {
+        self.collision_mask = collision_mask;
+        self
+    }
+
+    pub fn set_enabled(&mut self, is_enabled: bool) -> &mut Self {
+        self.is_enabled = is_enabled;
+        self
+    }
+
+    /// Move the collider by the transform. Like particles, capsule radii are never scaled, only the end points move.
+    /// SdfGrid colliders only support translation and uniform scaling, anything else returns an error and leaves the collider unchanged
+    pub fn transform(&mut self, transform: Affine2) -> Result<&mut Self, String> {
+        let matrix = transform.matrix2;
+        match &mut self.shape {
+            StaticColliderShape::Capsule { a, b, .. } => {
+                *a = transform.transform_point2(*a);
+                *b = transform.transform_point2(*b);
+            },
+            StaticColliderShape::ConvexPolygon(points) => {
+                for point in points.iter_mut() {
+                    *point = transform.transform_point2(*point);
+                }
+
+                // a mirror reverses the winding order
+                if matrix.determinant() < 0.0 {
+                    points.reverse();
+                }
+            },
+            StaticColliderShape::SdfGrid(sdf_grid) => {
+                let scale = matrix.x_axis.x;
+                if scale <= 0.0 || !matrix.abs_diff_eq(Mat2::from_diagonal(Vec2::splat(scale)), 0.0001) {
+                    return Err(format!("SdfGrid colliders only support translation and uniform scaling, not {}", matrix));
+                }
+
+                sdf_grid.origin = transform.transform_point2(sdf_grid.origin);
+                sdf_grid.cell_size *= scale;
+                for distance in sdf_grid.distances.iter_mut() {
+                    *distance *= scale;
+                }
+            },
+        }
+
+        self.aabb = self.shape.compute_aabb();
+        Ok(self)
+    }
+}
+
+pub struct StaticColliderContainer {
+    pub static_colliders: Vec<StaticCollider>,
+}
+
+impl StaticColliderContainer {
+    pub fn add(&mut self, static_collider: StaticCollider) -> StaticColliderHandle {
+        let id = self.static_colliders.len();
+        self.static_colliders.push(static_collider);
+        StaticColliderHandle::new(id)
+    }
+
+    pub fn get(&self, static_collider_handle: StaticColliderHandle) -> Option<&StaticCollider> {
+        self.static_colliders.get(static_collider_handle.id())
+    }
+
+    pub fn get_mut(&mut self, static_collider_handle: StaticColliderHandle) -> Option<&mut StaticCollider> {
+        self.static_colliders.get_mut(static_collider_handle.id())
+    }
+
+    pub fn len(&self) -> usize {
+        self.static_colliders.len()
+    }
+
+    /// Push dynamic particles out of any static colliders they overlap.
+    /// The enabled colliders are binned into a coarse grid first, so each particle only checks the colliders in its cell.
+    pub fn solve_collisions(&self, particle_data: &mut ParticleData) {
+        if self.static_colliders.is_empty() {
+            return;
+        }
+
+        let dynamic_particles = &mut particle_data.dynamic_particles;
+        let max_radius = (0..dynamic_particles.len()).fold(0.0, |max_radius: f32, i| max_radius.max(dynamic_particles.radius[i][0]));
+        let Some(grid) = StaticColliderGrid::new(&self.static_colliders, max_radius) else { return };
+
+        for i in 0..dynamic_particles.len() {
+            let radius = dynamic_particles.radius[i][0];
+            let Some(cell_index) = grid.cell_index(dynamic_particles.get_pos_vec2(i)) else { continue };
+
+            for static_collider_index in grid.cells[cell_index].iter() {
+                let static_collider = &self.static_colliders[*static_collider_index];
+                if !should_collide(dynamic_particles.collision_group[i], dynamic_particles.collision_mask[i], static_collider.collision_group, static_collider.collision_mask) {
+                    continue;
+                }
+
+                let pos = dynamic_particles.get_pos_vec2(i);
+                if !static_collider.aabb.intersects(&Aabb2d::new(pos, Vec2::splat(radius))) {
+                    continue;
+                }
+
+                let Some((distance, normal)) = static_collider.shape.compute_distance_and_normal(pos) else { continue };
+                if distance < radius {
+                    let new_pos = pos + normal * (radius - distance);
+                    dynamic_particles.pos[i] = [new_pos.x, new_pos.y].into();
+                }
+            }
+        }
+    }
+}
+
+const STATIC_COLLIDER_GRID_MAX_CELLS_PER_AXIS: usize = 16;
+
+/// A broadphase for StaticColliderContainer::solve_collisions. Each enabled collider is listed in every cell its bounds
+/// (grown by the largest particle radius) overlap, so a particle only needs to look in the cell containing its centre
+struct StaticColliderGrid {
+    aabb: Aabb2d, // bounds of all the cells. Particles outside this cannot touch any collider
+    cell_size: Vec2,
+    width: usize,
+    height: usize,
+    cells: Vec<Vec<usize>>, // indices into StaticColliderContainer::static_colliders, row major
+}
+
+impl StaticColliderGrid {
+    /// None if there are no enabled colliders
+    fn new(static_colliders: &[StaticCollider], max_radius: f32) -> Option<Self> {
+        let collider_aabbs: Vec<(usize, Aabb2d)> = static_colliders.iter().enumerate()
+            .filter(|(_, static_collider)| static_collider.is_enabled)
+            .map(|(i, static_collider)| (i, static_collider.aabb.grow(Vec2::splat(max_radius))))
+            .collect();
+        let aabb = collider_aabbs.iter().map(|(_, aabb)| *aabb).reduce(|a, b| a.merge(&b))?;
+
+        // roughly one collider per cell when they are spread out evenly
+        let cells_per_axis = ((collider_aabbs.len() as f32).sqrt().ceil() as usize).clamp(1, STATIC_COLLIDER_GRID_MAX_CELLS_PER_AXIS);
+        let mut grid = Self {
+            aabb,
+            cell_size: ((aabb.max - aabb.min) / cells_per_axis as f32).max(Vec2::splat(f32::EPSILON)),
+            width: cells_per_axis,
+            height: cells_per_axis,
+            cells: vec![vec![]; cells_per_axis * cells_per_axis],
+        };
+
+        for (static_collider_index, collider_aabb) in collider_aabbs {
+            let [min_x, min_y] = grid.cell_coords(collider_aabb.min);
+            let [max_x, max_y] = grid.cell_coords(collider_aabb.max);
+            for y in min_y..=max_y {
+                for x in min_x..=max_x {
+                    grid.cells[y * grid.width + x].push(static_collider_index);
+                }
+            }
+        }
+
+        Some(grid)
+    }
+
+    /// The cell containing pos, clamped to the grid
+    fn cell_coords(&self, pos: Vec2) -> [usize; 2] {
+        let grid_pos = ((pos - self.aabb.min) / self.cell_size).max(Vec2::ZERO);
+        [(grid_pos.x as usize).min(self.width - 1), (grid_pos.y as usize).min(self.height - 1)]
+    }
+
+    /// The index of the cell containing pos, or None outside the grid
+    fn cell_index(&self, pos: Vec2) -> Option<usize> {
+        if pos.cmplt(self.aabb.min).any() || pos.cmpgt(self.aabb.max).any() {
+            return None;
+        }
+
+        let [x, y] = self.cell_coords(pos);
+        Some(y * self.width + x)
+    }
+}
+
+impl Default for StaticColliderContainer {
+    fn default() -> Self {
+        Self {
+            static_colliders: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use crate::v5::{particle::Particle, particle_system::ParticleSystem};
+
+    use super::*;
+
+    fn collide_particle_at(static_collider: StaticCollider, pos: Vec2) -> Vec2 {
+        let mut particle_system = ParticleSystem::default();
+        let handles = particle_system.add_particles(&vec![*Particle::default().set_position(pos)]);
+        particle_system.add_static_collider(static_collider);
+        particle_system.solve_collisions();
+        particle_system.particle_data.get_pos_vec2(handles[0])
+    }
+
+    #[test]
+    fn capsule() {
+        let capsule = StaticCollider::capsule(vec2(-5.0, 0.0), vec2(5.0, 0.0), 0.1);
+        assert!((collide_particle_at(capsule.clone(), vec2(1.0, 0.4)) - vec2(1.0, 0.6)).length() < 0.0001);
+        assert!((collide_particle_at(capsule.clone(), vec2(1.0, -0.4)) - vec2(1.0, -0.6)).length() < 0.0001);
+        assert_eq!(collide_particle_at(capsule.clone(), vec2(1.0, 1.0)), vec2(1.0, 1.0));
+
+        // round end
+        assert!((collide_particle_at(capsule, vec2(5.3, 0.0)) - vec2(5.6, 0.0)).length() < 0.0001);
+    }
+
+    #[test]
+    fn convex_polygon() {
+        // clockwise, which gets fixed
+        let square = StaticCollider::convex_polygon(vec![vec2(-1.0, -1.0), vec2(-1.0, 1.0), vec2(1.0, 1.0), vec2(1.0, -1.0)]);
+
+        // overlapping an edge from outside and with the centre inside
+        assert!((collide_particle_at(square.clone(), vec2(0.0, 1.2)) - vec2(0.0, 1.5)).length() < 0.0001);
+        assert!((collide_particle_at(square.clone(), vec2(0.8, 0.0)) - vec2(1.5, 0.0)).length() < 0.0001);
+
+        // near a corner
+        let corner_pos = collide_particle_at(square, vec2(1.2, 1.2));
+        assert!(((corner_pos - vec2(1.0, 1.0)).length() - 0.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn sdf_grid() {
+        // flat ground at y = 0
+        let ground = SdfGrid::from_fn(vec2(-10.0, -5.0), 0.5, 41, 21, |pos| pos.y);
+        assert_eq!(ground.sample(vec2(0.3, 0.25)), Some(0.25));
+        assert_eq!(ground.sample(vec2(100.0, 0.0)), None);
+
+        let new_pos = collide_particle_at(StaticCollider::sdf_grid(ground), vec2(2.0, 0.2));
+        assert!((new_pos - vec2(2.0, 0.5)).length() < 0.0001);
+    }
+
+    #[test]
+    fn transform_sdf_grid() {
+        let ground = SdfGrid::from_fn(vec2(-10.0, -5.0), 0.5, 41, 21, |pos| pos.y);
+        let mut collider = StaticCollider::sdf_grid(ground);
+
+        assert!(collider.transform(Affine2::from_scale_angle_translation(Vec2::splat(2.0), 0.0, vec2(1.0, 0.0))).is_ok());
+        let StaticColliderShape::SdfGrid(sdf_grid) = &collider.shape else { panic!() };
+        assert_eq!(sdf_grid.origin, vec2(-19.0, -10.0));
+        assert_eq!(sdf_grid.cell_size, 1.0);
+
+        // rotating or mirroring cannot be represented, so the collider is left alone
+        assert!(collider.transform(Affine2::from_angle(1.0)).is_err());
+        assert!(collider.transform(Affine2::from_scale(vec2(-1.0, 1.0))).is_err());
+        let StaticColliderShape::SdfGrid(sdf_grid) = &collider.shape else { panic!() };
+        assert_eq!(sdf_grid.origin, vec2(-19.0, -10.0));
+    }
+
+    #[test]
+    fn broadphase_finds_colliders_in_every_cell() {
+        // a row of short capsules with a particle resting on each, plus one far away from all of them
+        let mut particle_system = ParticleSystem::default();
+        let mut particles = vec![];
+        for i in 0..20 {
+            let x = i as f32 * 3.0;
+            particle_system.add_static_collider(StaticCollider::capsule(vec2(x - 1.0, 0.0), vec2(x + 1.0, 0.0), 0.1));
+            particles.push(*Particle::default().set_position(vec2(x, 0.4)));
+        }
+        particles.push(*Particle::default().set_position(vec2(30.0, 50.0)));
+        let handles = particle_system.add_particles(&particles);
+
+        // disabled colliders are left out of the grid
+        particle_system.add_static_collider(StaticCollider::capsule(vec2(30.0, 49.0), vec2(30.0, 51.0), 0.1).set_enabled(false).clone());
+
+        particle_system.solve_collisions();
+        for i in 0..20 {
+            assert!((particle_system.particle_data.get_pos_vec2(handles[i]) - vec2(i as f32 * 3.0, 0.6)).length() < 0.0001);
+        }
+        assert_eq!(particle_system.particle_data.get_pos_vec2(handles[20]), vec2(30.0, 50.0));
+    }
+
+    #[test]
+    fn collision_mask() {
+        let mut capsule = StaticCollider::capsule(vec2(-5.0, 0.0), vec2(5.0, 0.0), 0.1);
+        capsule.set_collision_mask(0x2);
+        assert_eq!(collide_particle_at(capsule, vec2(1.0, 0.4)), vec2(1.0, 0.4));
+    }
+}
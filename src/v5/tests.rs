@@ -6,8 +6,12 @@ mod tests {
 
     use crate::v5::spatial_hash_particle_solver::SpatialHashParticleSolver;
     use crate::v5::naive_particle_solver::NaiveParticleSolver;
-    use crate::v5::particle::Particle;
+    use crate::v5::particle::{Particle, COLLISION_MASK_NONE};
+    use crate::v5::particle_data::ParticleData;
     use crate::v5::particle_vec::SharedParticleVec;
+    use crate::v5::spatial_hash_simd_particle_solver::SpatialHashSimdParticleSolver;
+    use crate::v5::particle_system::ParticleSystem;
+    use crate::v5::constraints::{constraint::Constraint, stick_constraint::StickConstraint};
 
     #[test]
     fn naive_particle_solver() {
@@ -58,4 +62,95 @@ mod tests {
         assert_eq!(p_2.pos, vec2(0.0, 0.0));
     }
 
+    #[test]
+    fn naive_particle_solver_collision_groups() {
+        let mut solver = NaiveParticleSolver::default();
+        let shared_particle_vec = SharedParticleVec::default();
+
+        let (ph_1, ph_2) = {
+            // particle 1 is in group 2 but particle 2 only collides with group 1
+            let mut particle_vec = shared_particle_vec.as_ref().write().unwrap();
+            let ph_1 = particle_vec.add(*Particle::default().set_position(vec2(0.9, 0.0)).set_collision_group(0x2));
+            let ph_2 = particle_vec.add(*Particle::default().set_static(true).set_collision_mask(0x1));
+            (ph_1, ph_2)
+        };
+
+        solver.bind(&shared_particle_vec);
+        solver.solve_collisions();
+
+        let particle_vec = shared_particle_vec.as_ref().write().unwrap();
+        assert_eq!(particle_vec.get(ph_1).unwrap().pos, vec2(0.9, 0.0));
+        assert_eq!(particle_vec.get(ph_2).unwrap().pos, vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn spatial_hash_simd_particle_solver_no_collisions_mask() {
+        let mut solver = SpatialHashSimdParticleSolver::default();
+        let mut particle_data = ParticleData::default();
+
+        let handles = particle_data.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)).set_collision_mask(COLLISION_MASK_NONE),
+            *Particle::default().set_position(vec2(0.5, 0.0)),
+            *Particle::default().set_position(vec2(0.0, -0.9)).set_static(true),
+        ]);
+
+        solver.notify_particle_data_changed(&mut particle_data);
+        solver.solve_collisions_6(&mut particle_data);
+
+        // the hub-like particle neither pushes nor is pushed
+        assert_eq!(particle_data.get_pos_vec2(handles[0]), vec2(0.0, 0.0));
+        assert_eq!(particle_data.get_pos_vec2(handles[1]), vec2(0.5, 0.0));
+    }
+
+    #[test]
+    fn spatial_hash_simd_particle_solver_collision_exclusions() {
+        let mut particle_system = ParticleSystem::default();
+
+        // 2 particles joined by a stick and 2 particles in the same body with self collision disabled
+        let handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+            *Particle::default().set_position(vec2(0.5, 0.0)),
+            *Particle::default().set_position(vec2(10.0, 0.0)).set_body_id(1).set_collides_with_self(false),
+            *Particle::default().set_position(vec2(10.5, 0.0)).set_body_id(1).set_collides_with_self(false),
+        ]);
+        particle_system.add_constraint(StickConstraint::default().set_particle_handles([handles[0], handles[1]]).set_length(0.5).set_exclude_collisions(true).box_clone());
+
+        particle_system.solve_collisions();
+
+        for (handle, pos) in handles.iter().zip([vec2(0.0, 0.0), vec2(0.5, 0.0), vec2(10.0, 0.0), vec2(10.5, 0.0)]) {
+            assert_eq!(particle_system.particle_data.get_pos_vec2(*handle), pos);
+        }
+    }
+
+    #[test]
+    fn collision_exclusion_is_symmetric() {
+        // only one of the 2 particles in the body has self collision disabled, which is enough to exclude the pair
+        // whichever particle the solver visits first
+        for collides_with_self in [[false, true], [true, false]] {
+            let mut particle_system = ParticleSystem::default();
+            let handles = particle_system.add_particles(&vec![
+                *Particle::default().set_position(vec2(0.0, 0.0)).set_body_id(1).set_collides_with_self(collides_with_self[0]),
+                *Particle::default().set_position(vec2(0.5, 0.0)).set_body_id(1).set_collides_with_self(collides_with_self[1]),
+            ]);
+
+            particle_system.solve_collisions();
+
+            assert_eq!(particle_system.particle_data.get_pos_vec2(handles[0]), vec2(0.0, 0.0));
+            assert_eq!(particle_system.particle_data.get_pos_vec2(handles[1]), vec2(0.5, 0.0));
+        }
+    }
+
+    #[test]
+    fn sticks_do_not_exclude_collisions_by_default() {
+        let mut particle_system = ParticleSystem::default();
+        let handles = particle_system.add_particles(&vec![
+            *Particle::default().set_position(vec2(0.0, 0.0)),
+            *Particle::default().set_position(vec2(0.5, 0.0)),
+        ]);
+        particle_system.add_constraint(StickConstraint::default().set_particle_handles([handles[0], handles[1]]).set_length(0.5).box_clone());
+
+        particle_system.solve_collisions();
+
+        assert!((particle_system.particle_data.get_pos_vec2(handles[1]) - particle_system.particle_data.get_pos_vec2(handles[0])).length() > 0.5);
+    }
 }
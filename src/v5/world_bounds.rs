@@ -0,0 +1,225 @@
+use bevy::math::{bounding::Aabb2d, Vec2};
+
+use super::{particle_data::ParticleData, particle_handle::ParticleHandle};
+
+/// What happens to a dynamic particle that leaves the world through one side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorldBoundsBehaviour {
+    /// Nothing. Particles can leave the world
+    None,
+
+    /// A solid wall. Particles are kept inside and lose their velocity into the wall
+    Wall,
+
+    /// Particles that leave through this side come back in through the opposite side with the same velocity.
+    /// The opposite side should also be set to Wrap
+    Wrap,
+
+    /// Particles are disabled once their centre leaves the world, so they are no longer hashed or integrated
+    Disable,
+
+    /// Particles can leave the world, but an event is reported when their centre crosses this side
+    EventOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorldBoundsSide {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+/// A particle left the world through a side with the Disable or EventOnly behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldBoundsEvent {
+    pub particle_handle: ParticleHandle,
+    pub side: WorldBoundsSide,
+}
+
+/// Limits of the simulation, with a behaviour for each side
+#[derive(Debug, Clone)]
+pub struct WorldBounds {
+    pub aabb: Aabb2d,
+    pub left: WorldBoundsBehaviour,
+    pub right: WorldBoundsBehaviour,
+    pub bottom: WorldBoundsBehaviour,
+    pub top: WorldBoundsBehaviour,
+}
+
+impl WorldBounds {
+    /// Bounds with the same behaviour on every side
+    pub fn new(aabb: Aabb2d, behaviour: WorldBoundsBehaviour) -> Self {
+        Self {
+            aabb,
+            left: behaviour,
+            right: behaviour,
+            bottom: behaviour,
+            top: behaviour,
+        }
+    }
+
+    pub fn set_side(&mut self, side: WorldBoundsSide, behaviour: WorldBoundsBehaviour) -> &mut Self {
+        match side {
+            WorldBoundsSide::Left => self.left = behaviour,
+            WorldBoundsSide::Right => self.right = behaviour,
+            WorldBoundsSide::Bottom => self.bottom = behaviour,
+            WorldBoundsSide::Top => self.top = behaviour,
+        }
+        self
+    }
+
+    pub fn get_side(&self, side: WorldBoundsSide) -> WorldBoundsBehaviour {
+        match side {
+            WorldBoundsSide::Left => self.left,
+            WorldBoundsSide::Right => self.right,
+            WorldBoundsSide::Bottom => self.bottom,
+            WorldBoundsSide::Top => self.top,
+        }
+    }
+
+    /// Apply the behaviour of each side to every dynamic particle, adding any events to events
+    pub fn apply(&self, particle_data: &mut ParticleData, events: &mut Vec<WorldBoundsEvent>) {
+        let size = self.aabb.max - self.aabb.min;
+        let mut particle_handles_to_disable = vec![];
+
+        let dynamic_particles = &mut particle_data.dynamic_particles;
+        for i in 0..dynamic_particles.len() {
+            let radius = dynamic_particles.radius[i][0];
+            let mut pos = dynamic_particles.get_pos_vec2(i);
+            let mut pos_prev = Vec2::new(dynamic_particles.pos_prev[i][0], dynamic_particles.pos_prev[i][1]);
+
+            // (side, axis, which direction is out of bounds, limit)
+            let sides = [
+                (WorldBoundsSide::Left, 0, -1.0, self.aabb.min.x),
+                (WorldBoundsSide::Right, 0, 1.0, self.aabb.max.x),
+                (WorldBoundsSide::Bottom, 1, -1.0, self.aabb.min.y),
+                (WorldBoundsSide::Top, 1, 1.0, self.aabb.max.y),
+            ];
+
+            for (side, axis, direction, limit) in sides {
+                let is_centre_outside = (pos[axis] - limit) * direction > 0.0;
+
+                match self.get_side(side) {
+                    WorldBoundsBehaviour::None => {},
+                    WorldBoundsBehaviour::Wall => {
+                        let wall_pos = limit - radius * direction;
+                        if (pos[axis] - wall_pos) * direction > 0.0 {
+                            pos[axis] = wall_pos;
+                            pos_prev[axis] = wall_pos;
+                        }
+                    },
+                    WorldBoundsBehaviour::Wrap => {
+                        if is_centre_outside {
+                            pos[axis] -= size[axis] * direction;
+                            pos_prev[axis] -= size[axis] * direction;
+                        }
+                    },
+                    WorldBoundsBehaviour::Disable => {
+                        if is_centre_outside {
+                            let particle_handle = dynamic_particles.particle_handle[i];
+                            if !particle_handles_to_disable.contains(&particle_handle) {
+                                particle_handles_to_disable.push(particle_handle);
+                                events.push(WorldBoundsEvent { particle_handle, side });
+                            }
+                        }
+                    },
+                    WorldBoundsBehaviour::EventOnly => {
+                        let was_centre_outside = (pos_prev[axis] - limit) * direction > 0.0;
+                        if is_centre_outside && !was_centre_outside {
+                            events.push(WorldBoundsEvent { particle_handle: dynamic_particles.particle_handle[i], side });
+                        }
+                    },
+                }
+            }
+
+            dynamic_particles.pos[i] = [pos.x, pos.y].into();
+            dynamic_particles.pos_prev[i] = [pos_prev.x, pos_prev.y].into();
+        }
+
+        // disable after iterating, as disabling moves particles around inside the dynamic ParticleVec
+        for particle_handle in particle_handles_to_disable {
+            particle_data.set_enabled(particle_handle, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::simd::f32x2;
+
+    use bevy::math::vec2;
+
+    use crate::v5::{particle::Particle, particle_system::ParticleSystem};
+
+    use super::*;
+
+    fn bounds(behaviour: WorldBoundsBehaviour) -> WorldBounds {
+        WorldBounds::new(Aabb2d { min: vec2(-10.0, -10.0), max: vec2(10.0, 10.0) }, behaviour)
+    }
+
+    // add a particle at pos moving with the given movement per sub step, then apply the bounds
+    fn apply_bounds_to_particle(world_bounds: WorldBounds, pos: Vec2, movement: Vec2) -> (ParticleSystem, ParticleHandle) {
+        let mut particle_system = ParticleSystem::default();
+        particle_system.set_gravity(f32x2::splat(0.0));
+        let handles = particle_system.add_particles(&vec![*Particle::default().set_position(pos)]);
+        particle_system.particle_data.set_pos_prev_from_vec2(handles[0], &(pos - movement));
+        particle_system.set_world_bounds(Some(world_bounds));
+        particle_system.apply_world_bounds();
+        (particle_system, handles[0])
+    }
+
+    #[test]
+    fn wall() {
+        let (particle_system, handle) = apply_bounds_to_particle(bounds(WorldBoundsBehaviour::Wall), vec2(9.8, 0.0), vec2(0.5, 0.1));
+        assert_eq!(particle_system.particle_data.get_pos_vec2(handle), vec2(9.5, 0.0));
+        assert_eq!(particle_system.particle_data.get_pos_prev_vec2(handle), vec2(9.5, -0.1));
+
+        // exactly touching the wall is left alone
+        let (particle_system, handle) = apply_bounds_to_particle(bounds(WorldBoundsBehaviour::Wall), vec2(0.0, -9.5), vec2(0.0, -0.1));
+        assert_eq!(particle_system.particle_data.get_pos_vec2(handle), vec2(0.0, -9.5));
+        assert_eq!(particle_system.particle_data.get_pos_prev_vec2(handle), vec2(0.0, -9.4));
+    }
+
+    #[test]
+    fn wrap() {
+        let (particle_system, handle) = apply_bounds_to_particle(bounds(WorldBoundsBehaviour::Wrap), vec2(-10.5, 2.0), vec2(-1.0, 0.0));
+        assert_eq!(particle_system.particle_data.get_pos_vec2(handle), vec2(9.5, 2.0));
+        assert_eq!(particle_system.particle_data.get_pos_prev_vec2(handle), vec2(10.5, 2.0));
+
+        // on the boundary is still inside
+        let (particle_system, handle) = apply_bounds_to_particle(bounds(WorldBoundsBehaviour::Wrap), vec2(0.0, 10.0), vec2(0.0, 1.0));
+        assert_eq!(particle_system.particle_data.get_pos_vec2(handle), vec2(0.0, 10.0));
+    }
+
+    #[test]
+    fn disable() {
+        let (particle_system, handle) = apply_bounds_to_particle(bounds(WorldBoundsBehaviour::Disable), vec2(0.0, -10.1), vec2(0.0, -1.0));
+        assert!(!particle_system.particle_data.is_enabled(handle));
+        assert_eq!(particle_system.world_bounds_events(), &vec![WorldBoundsEvent { particle_handle: handle, side: WorldBoundsSide::Bottom }]);
+
+        let (particle_system, handle) = apply_bounds_to_particle(bounds(WorldBoundsBehaviour::Disable), vec2(0.0, -9.9), vec2(0.0, -1.0));
+        assert!(particle_system.particle_data.is_enabled(handle));
+        assert!(particle_system.world_bounds_events().is_empty());
+    }
+
+    #[test]
+    fn event_only() {
+        let mut world_bounds = bounds(WorldBoundsBehaviour::None);
+        world_bounds.set_side(WorldBoundsSide::Right, WorldBoundsBehaviour::EventOnly);
+
+        // crossing the right side reports an event, but the particle is untouched
+        let (particle_system, handle) = apply_bounds_to_particle(world_bounds.clone(), vec2(10.2, 0.0), vec2(0.5, 0.0));
+        assert_eq!(particle_system.particle_data.get_pos_vec2(handle), vec2(10.2, 0.0));
+        assert_eq!(particle_system.world_bounds_events(), &vec![WorldBoundsEvent { particle_handle: handle, side: WorldBoundsSide::Right }]);
+
+        // already outside, so no new event
+        let (particle_system, _) = apply_bounds_to_particle(world_bounds.clone(), vec2(12.0, 0.0), vec2(0.5, 0.0));
+        assert!(particle_system.world_bounds_events().is_empty());
+
+        // crossing the left side does nothing
+        let (particle_system, handle) = apply_bounds_to_particle(world_bounds, vec2(-10.2, 0.0), vec2(-0.5, 0.0));
+        assert_eq!(particle_system.particle_data.get_pos_vec2(handle), vec2(-10.2, 0.0));
+        assert!(particle_system.world_bounds_events().is_empty());
+    }
+}